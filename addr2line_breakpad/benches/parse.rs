@@ -0,0 +1,83 @@
+// Parsing throughput on a generated symbol file, and the scanning and hex
+// decoding it is made of against the std equivalents they replace:
+//   cargo bench -p addr2line_breakpad --bench parse
+// BENCH_SYM_MB sets the size of the file, 1100 for a file over 1 GB.
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+use addr2line_breakpad::{all_symbols, parse_breakpad_symbol_source, parse_hex};
+
+fn generate(path: &std::path::Path, size: u64) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "MODULE Linux arm64 0123456789ABCDEF0123456789ABCDEF0 libbench.so")?;
+    for id in 0..1000 {
+        writeln!(out, "FILE {} src/module{}/File{}.cpp", id, id % 37, id)?;
+    }
+    let mut written = 0u64;
+    let mut address = 0x1000u64;
+    let mut function = 0u64;
+    while written < size {
+        let line = format!("FUNC {:x} 100 0 bench::Namespace{}::Class{}::Method{}(int, char const*)\n", address, function % 97, function % 1013, function);
+        written += line.len() as u64;
+        out.write_all(line.as_bytes())?;
+        for i in 0..8u64 {
+            let line = format!("{:x} 20 {} {}\n", address + i * 0x20, 100 + i, function % 1000);
+            written += line.len() as u64;
+            out.write_all(line.as_bytes())?;
+        }
+        if function.is_multiple_of(16) {
+            let line = format!("PUBLIC {:x} 0 bench_public_{}\n", address + 0x100, function);
+            written += line.len() as u64;
+            out.write_all(line.as_bytes())?;
+        }
+        address += 0x200;
+        function += 1;
+    }
+    out.flush()
+}
+
+fn report(name: &str, bytes: usize, fast: Duration, std: Duration) {
+    let mb_s = |duration: Duration| bytes as f64 / duration.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<16} {:>9.0} MB/s  std {:>9.0} MB/s  {:.2}x", name, mb_s(fast), mb_s(std), std.as_secs_f64() / fast.as_secs_f64());
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> Duration {
+    let started = Instant::now();
+    black_box(f());
+    started.elapsed()
+}
+
+fn main() {
+    let size_mb: u64 = std::env::var("BENCH_SYM_MB").ok().and_then(|mb| mb.parse().ok()).unwrap_or(64);
+    let path = std::env::temp_dir().join(format!("addr2line_breakpad-bench-{}.sym", size_mb));
+    if !path.exists() {
+        generate(&path, size_mb * 1024 * 1024).unwrap();
+    }
+    let data = std::fs::read(&path).unwrap();
+    println!("{} ({} MB)", path.display(), data.len() / (1024 * 1024));
+
+    let fast = time(|| memchr::memchr_iter(b'\n', &data).count());
+    let std = time(|| BufReader::new(&data[..]).split(b'\n').count());
+    report("lines", data.len(), fast, std);
+
+    let text = std::str::from_utf8(&data).unwrap();
+    let fast = time(|| text.lines().map(|line| memchr::memchr_iter(b' ', line.as_bytes()).count()).sum::<usize>());
+    let std = time(|| text.lines().map(|line| line.split(' ').count()).sum::<usize>());
+    report("spaces", data.len(), fast, std);
+
+    let fields: Vec<&str> = text.lines().filter_map(|line| line.split(' ').find(|field| field.len() > 2 && field.bytes().all(|byte| byte.is_ascii_hexdigit()))).collect();
+    let field_bytes = fields.iter().map(|field| field.len()).sum();
+    let fast = time(|| fields.iter().filter_map(|field| parse_hex(field)).fold(0u64, u64::wrapping_add));
+    let std = time(|| fields.iter().filter_map(|field| u64::from_str_radix(field, 16).ok()).fold(0u64, u64::wrapping_add));
+    report("hex", field_bytes, fast, std);
+
+    drop(fields);
+    drop(data);
+    let started = Instant::now();
+    let symbol_file = parse_breakpad_symbol_source(&path, None);
+    let elapsed = started.elapsed();
+    let size = std::fs::metadata(&path).unwrap().len();
+    println!("parse            {:>9.0} MB/s  {:.2?} for {} symbols", size as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0), elapsed, all_symbols(&symbol_file).len());
+}
@@ -0,0 +1,179 @@
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use crate::logging::debug;
+
+use crate::cache::cached_file;
+use crate::http::{download, get_json};
+
+/// `appcenter:<owner>/<app>[@<version>][!<entry>]`: the Breakpad symbols
+/// uploaded to Visual Studio App Center for an app, the newest upload of
+/// `version` if given. `entry` picks a symbol file in the uploaded zip, as
+/// for local archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppCenterSource {
+    pub owner: String,
+    pub app: String,
+    pub version: Option<String>,
+    pub entry: Option<String>,
+}
+
+pub fn parse_appcenter_source(location: &str) -> Option<AppCenterSource> {
+    let rest = location.strip_prefix("appcenter:")?;
+    let (rest, entry) = match rest.split_once('!') {
+        Some((rest, entry)) => (rest, Some(entry.to_string()).filter(|entry| !entry.is_empty())),
+        None => (rest, None),
+    };
+    let (app, version) = match rest.split_once('@') {
+        Some((app, version)) => (app, Some(version.to_string()).filter(|version| !version.is_empty())),
+        None => (rest, None),
+    };
+    let (owner, app) = app.split_once('/')?;
+    if owner.is_empty() || app.is_empty() || app.contains('/') {
+        return None;
+    }
+    Some(AppCenterSource {
+        owner: owner.to_string(),
+        app: app.to_string(),
+        version,
+        entry,
+    })
+}
+
+// APPCENTER_API_URL overrides the endpoint, e.g. for a proxy.
+fn api_url() -> String {
+    env::var("APPCENTER_API_URL").map(|url| url.trim_end_matches('/').to_string()).unwrap_or_else(|_| String::from("https://api.appcenter.ms/v0.1"))
+}
+
+// APPCENTER_API_TOKEN, an App Center API token with read access to the app.
+fn api_headers() -> io::Result<Vec<(String, String)>> {
+    match env::var("APPCENTER_API_TOKEN") {
+        Ok(token) => Ok(vec![(String::from("X-API-Token"), token)]),
+        Err(_) => Err(io::Error::new(io::ErrorKind::PermissionDenied, "APPCENTER_API_TOKEN is not set")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SymbolUpload {
+    id: String,
+    version: Option<String>,
+    build: Option<String>,
+    // Debug ids of the uploaded modules, as far as App Center lists them.
+    symbol_ids: Vec<String>,
+    timestamp: String,
+}
+
+// The committed Breakpad uploads of a `symbol_uploads` listing.
+fn breakpad_uploads(listing: &Value) -> Vec<SymbolUpload> {
+    let text = |upload: &Value, key: &str| upload.get(key).and_then(Value::as_str).map(String::from);
+    listing
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|upload| text(upload, "status").as_deref() == Some("committed") && text(upload, "symbol_type").as_deref() == Some("Breakpad"))
+        .filter_map(|upload| {
+            Some(SymbolUpload {
+                id: text(upload, "symbol_upload_id")?,
+                version: text(upload, "version"),
+                build: text(upload, "build"),
+                symbol_ids: upload
+                    .get("symbols_uploaded")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|symbol| text(symbol, "symbol_id"))
+                    .collect(),
+                timestamp: text(upload, "timestamp").unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+// The newest upload of `version` (its version or build), preferring one
+// listing the module's debug id.
+fn pick_upload<'a>(uploads: &'a [SymbolUpload], version: Option<&str>, debug_id: Option<&str>) -> Option<&'a SymbolUpload> {
+    let normalize = |id: &str| id.replace('-', "").to_uppercase();
+    let candidates = uploads.iter().filter(|upload| version.is_none() || upload.version.as_deref() == version || upload.build.as_deref() == version);
+    let lists_module = |upload: &&SymbolUpload| debug_id.is_some_and(|debug_id| upload.symbol_ids.iter().any(|id| normalize(id) == normalize(debug_id)));
+    // Timestamps are ISO 8601, so they sort as strings.
+    candidates.clone().filter(lists_module).max_by(|a, b| a.timestamp.cmp(&b.timestamp)).or_else(|| candidates.max_by(|a, b| a.timestamp.cmp(&b.timestamp)))
+}
+
+/// Downloads the uploaded symbols zip of `source` into `cache_dir`, once per
+/// upload, and returns its path, with `!entry` appended if one was given.
+/// `debug_id` of the module looked for helps pick among uploads.
+pub fn fetch_appcenter_symbols(source: &AppCenterSource, debug_id: Option<&str>, cache_dir: &Path) -> io::Result<PathBuf> {
+    let headers = api_headers()?;
+    let app_url = format!("{}/apps/{}/{}", api_url(), source.owner, source.app);
+    let listing = get_json(&format!("{}/symbol_uploads?status=committed&symbol_type=Breakpad", app_url), &headers)?;
+    let uploads = breakpad_uploads(&listing);
+    let upload = pick_upload(&uploads, source.version.as_deref(), debug_id).ok_or_else(|| {
+        let version = source.version.as_deref().map(|version| format!(" version {}", version)).unwrap_or_default();
+        io::Error::new(io::ErrorKind::NotFound, format!("no Breakpad symbols uploaded to App Center for {}/{}{}", source.owner, source.app, version))
+    })?;
+    debug!("App Center symbol upload {} ({:?})", upload.id, upload.version);
+
+    let cached = cache_dir.join("appcenter").join(&source.owner).join(&source.app).join(format!("{}.zip", upload.id));
+    let path = match cached_file(&cached) {
+        Some(path) => path,
+        None => {
+            let location = get_json(&format!("{}/symbol_uploads/{}/location", app_url, upload.id), &headers)?;
+            let uri = location.get("uri").and_then(Value::as_str).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("no download uri for symbol upload {}", upload.id)))?;
+            // A signed blob storage URL; the API token stays with App Center.
+            download(uri, &[], cache_dir, &cached)?
+        }
+    };
+    Ok(match &source.entry {
+        Some(entry) => PathBuf::from(format!("{}!{}", path.display(), entry)),
+        None => path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_appcenter_source() {
+        assert_eq!(
+            parse_appcenter_source("appcenter:studio/game-android@1.4.0!arm64-v8a/libil2cpp.sym"),
+            Some(AppCenterSource {
+                owner: String::from("studio"),
+                app: String::from("game-android"),
+                version: Some(String::from("1.4.0")),
+                entry: Some(String::from("arm64-v8a/libil2cpp.sym")),
+            })
+        );
+        assert_eq!(parse_appcenter_source("appcenter:studio/game").unwrap().version, None);
+        assert!(parse_appcenter_source("appcenter:game").is_none());
+        assert!(parse_appcenter_source("s3://studio/game").is_none());
+    }
+
+    #[test]
+    fn test_pick_upload() {
+        let listing: Value = serde_json::from_str(
+            r#"[
+                {"symbol_upload_id": "a", "status": "committed", "symbol_type": "Breakpad", "version": "1.3.0", "build": "41", "timestamp": "2021-05-01T10:00:00Z", "symbols_uploaded": []},
+                {"symbol_upload_id": "b", "status": "committed", "symbol_type": "Breakpad", "version": "1.4.0", "build": "42", "timestamp": "2021-06-01T10:00:00Z",
+                 "symbols_uploaded": [{"symbol_id": "4fbdb2b4-acb3-432b-f6ad-8e4a87ba9d4b0", "platform": "android"}]},
+                {"symbol_upload_id": "c", "status": "committed", "symbol_type": "Breakpad", "version": "1.4.0", "build": "43", "timestamp": "2021-06-02T10:00:00Z", "symbols_uploaded": []},
+                {"symbol_upload_id": "d", "status": "created", "symbol_type": "Breakpad", "version": "1.4.0", "timestamp": "2021-06-03T10:00:00Z"},
+                {"symbol_upload_id": "e", "status": "committed", "symbol_type": "Apple", "version": "1.4.0", "timestamp": "2021-06-04T10:00:00Z"}
+            ]"#,
+        )
+        .unwrap();
+        let uploads = breakpad_uploads(&listing);
+        assert_eq!(uploads.len(), 3);
+        let pick = |version, debug_id| pick_upload(&uploads, version, debug_id).map(|upload| upload.id.as_str());
+        assert_eq!(pick(None, None), Some("c"));
+        assert_eq!(pick(Some("1.3.0"), None), Some("a"));
+        assert_eq!(pick(Some("42"), None), Some("b"));
+        assert_eq!(pick(Some("1.4.0"), Some("4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0")), Some("b"));
+        assert_eq!(pick(Some("1.4.0"), Some("0000")), Some("c"));
+        assert_eq!(pick(Some("2.0.0"), None), None);
+    }
+}
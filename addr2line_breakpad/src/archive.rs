@@ -0,0 +1,255 @@
+#[cfg(feature = "compression")]
+use std::fs::File;
+use std::io;
+#[cfg(feature = "compression")]
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+pub(crate) const ZIP_MAGIC: &[u8] = &[0x50, 0x4b, 0x03, 0x04];
+pub(crate) const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+// "symbols.zip!arm64-v8a/libunity.sym" selects an entry inside an archive.
+// Only split when the path as a whole doesn't exist, so file names that
+// happen to contain '!' keep working.
+pub(crate) fn split_archive_path(path: &Path) -> (PathBuf, Option<String>) {
+    if !path.exists() {
+        if let Some((archive, entry)) = path.to_str().and_then(|path| path.rsplit_once('!')) {
+            if Path::new(archive).is_file() {
+                return (PathBuf::from(archive), Some(entry.to_string()));
+            }
+        }
+    }
+    (path.to_path_buf(), None)
+}
+
+#[cfg(feature = "compression")]
+fn is_symbol_entry(name: &str) -> bool {
+    // `.so.dbg` is the ELF debug info of Android native debug symbols zips,
+    // their `.so.sym` the ELF symbol table.
+    name.ends_with(".sym") || name.ends_with(".sym.gz") || name.ends_with(".sym.zst") || name.ends_with(".so.dbg")
+}
+
+// A hint (module name or ABI such as `arm64-v8a`) matches an entry when it
+// equals one of its path components or the file name without `.sym`/`.dbg`.
+#[cfg(feature = "compression")]
+fn matches_hint(name: &str, hint: &str) -> bool {
+    name.split('/').any(|component| {
+        component == hint || component.split(".sym").next() == Some(hint) || component.strip_suffix(".dbg") == Some(hint)
+    })
+}
+
+#[cfg(feature = "compression")]
+fn not_found(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+// Picks the entry named explicitly, otherwise the only symbol file in the
+// archive, otherwise the only one matching `hint`, and hands it to `read`
+// with its name and size.
+#[cfg(feature = "compression")]
+fn with_zip_entry<R: Read + Seek, T>(
+    reader: R,
+    entry: Option<&str>,
+    hint: Option<&str>,
+    read: impl FnOnce(&str, u64, &mut dyn Read) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let name = match entry {
+        Some(entry) => entry.to_string(),
+        None => {
+            let candidates: Vec<String> = archive
+                .file_names()
+                .filter_map(|name| name.ok())
+                .filter(|name| is_symbol_entry(name))
+                .map(|name| name.into_owned())
+                .collect();
+            let matching: Vec<&String> = match hint {
+                Some(hint) if candidates.len() > 1 => candidates.iter().filter(|name| matches_hint(name, hint)).collect(),
+                _ => candidates.iter().collect(),
+            };
+            match matching.as_slice() {
+                [name] => name.to_string(),
+                [] => return Err(not_found(format!("no symbol file in archive matches {:?}, candidates: {:?}", hint, candidates))),
+                _ => return Err(not_found(format!("ambiguous symbol files in archive, pick one with `archive.zip!entry`: {:?}", matching))),
+            }
+        }
+    };
+
+    let mut file = archive
+        .by_name(&name)
+        .map_err(|_| not_found(format!("no entry {} in archive", name)))?;
+    let size = file.size();
+    read(&name, size, &mut file)
+}
+
+// The whole entry, for readers that must outlive the archive. The size in
+// its header is not trusted for preallocating.
+#[cfg(feature = "compression")]
+pub(crate) fn read_zip_entry<R: Read + Seek>(reader: R, entry: Option<&str>, hint: Option<&str>) -> io::Result<Vec<u8>> {
+    with_zip_entry(reader, entry, hint, |_, _, file| {
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    })
+}
+
+// Hands the entry read_zip_entry would pick to `read` as it is decompressed,
+// so parsing never holds all of it in memory.
+#[cfg(feature = "compression")]
+pub(crate) fn stream_zip_entry<T>(archive: &Path, entry: Option<&str>, hint: Option<&str>, read: impl FnOnce(&mut dyn Read) -> io::Result<T>) -> io::Result<T> {
+    with_zip_entry(File::open(archive)?, entry, hint, |_, _, file| read(file))
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn stream_zip_entry<T>(archive: &Path, _entry: Option<&str>, _hint: Option<&str>, _read: impl FnOnce(&mut dyn io::Read) -> io::Result<T>) -> io::Result<T> {
+    Err(without_compression(archive))
+}
+
+// The first bytes of the entry read_zip_entry would pick, to tell its format
+// without reading all of it.
+#[cfg(feature = "compression")]
+pub(crate) fn zip_entry_magic(archive: &Path, entry: Option<&str>, hint: Option<&str>) -> io::Result<Vec<u8>> {
+    with_zip_entry(File::open(archive)?, entry, hint, |_, _, file| {
+        let mut magic = vec![];
+        file.take(4).read_to_end(&mut magic)?;
+        Ok(magic)
+    })
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn zip_entry_magic(archive: &Path, entry: Option<&str>, hint: Option<&str>) -> io::Result<Vec<u8>> {
+    open_zip_entry(archive, entry, hint)
+}
+
+// Copies the entry read_zip_entry would pick out of `archive` into the cache,
+// for backends that need a file of their own. The copy is keyed by the
+// archive's name and modification time, so replacing the archive invalidates
+// it.
+#[cfg(all(feature = "compression", feature = "debuginfod"))]
+pub(crate) fn extract_zip_entry(archive: &Path, entry: Option<&str>, hint: Option<&str>, cache_dir: &Path) -> io::Result<PathBuf> {
+    let modified = std::fs::metadata(archive)?.modified()?.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let file_name = archive.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let dir = cache_dir.join("extracted").join(format!("{}-{}", file_name, modified));
+    with_zip_entry(File::open(archive)?, entry, hint, |name, _, file| {
+        // Entry names come from the archive, keep them inside `dir`.
+        let relative: PathBuf = name.split('/').filter(|component| !component.is_empty() && *component != "..").collect();
+        let path = dir.join(relative);
+        match crate::cache::cached_file(&path) {
+            Some(path) => Ok(path),
+            None => crate::cache::store(cache_dir, &path, file),
+        }
+    })
+}
+
+#[cfg(all(not(feature = "compression"), feature = "debuginfod"))]
+pub(crate) fn extract_zip_entry(archive: &Path, entry: Option<&str>, hint: Option<&str>, _cache_dir: &Path) -> io::Result<PathBuf> {
+    open_zip_entry(archive, entry, hint).map(|_| PathBuf::new())
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn open_zip_entry(archive: &Path, entry: Option<&str>, hint: Option<&str>) -> io::Result<Vec<u8>> {
+    read_zip_entry(File::open(archive)?, entry, hint)
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn open_zip_entry(archive: &Path, _entry: Option<&str>, _hint: Option<&str>) -> io::Result<Vec<u8>> {
+    Err(without_compression(archive))
+}
+
+#[cfg(not(feature = "compression"))]
+fn without_compression(archive: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{}: zip archive, built without the compression feature", archive.display()),
+    )
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn archive(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored))
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    fn test_read_zip_entry() {
+        let single = || archive(&[("README.txt", "hello"), ("arm64-v8a/libunity.sym", "arm64")]);
+        assert_eq!(read_zip_entry(single(), None, None).unwrap(), b"arm64");
+
+        let multi = || {
+            archive(&[
+                ("arm64-v8a/libunity.sym", "arm64 unity"),
+                ("arm64-v8a/libil2cpp.sym", "arm64 il2cpp"),
+                ("armeabi-v7a/libunity.sym", "arm unity"),
+            ])
+        };
+        assert_eq!(read_zip_entry(multi(), Some("armeabi-v7a/libunity.sym"), None).unwrap(), b"arm unity");
+        assert_eq!(read_zip_entry(multi(), None, Some("libil2cpp")).unwrap(), b"arm64 il2cpp");
+        assert!(read_zip_entry(multi(), None, Some("libunity")).is_err());
+        assert!(read_zip_entry(multi(), None, None).is_err());
+        assert!(read_zip_entry(multi(), Some("missing.sym"), None).is_err());
+    }
+
+    #[test]
+    fn test_parse_zip_entry() {
+        let zip = std::env::temp_dir().join(format!("addr2line_breakpad_{}_parse_zip_entry.zip", std::process::id()));
+        let data = archive(&[
+            ("arm64-v8a/libunity.sym", "MODULE Linux arm64 0102 libunity.so\nFUNC 10 4 0 unity\n"),
+            ("arm64-v8a/libil2cpp.sym", "MODULE Linux arm64 0304 libil2cpp.so\nFUNC 10 4 0 il2cpp\n"),
+        ]);
+        std::fs::write(&zip, data.into_inner()).unwrap();
+        let function_name = |path: &Path, hint| {
+            let symbol_file = crate::parse_breakpad_symbol_source_with_options(path, hint, &crate::ParserOptions::default()).unwrap();
+            crate::lookup_address(&symbol_file, 0x10).unwrap().function_name.into_owned()
+        };
+        assert_eq!(function_name(&zip, Some("libil2cpp")), "il2cpp");
+        let entry = PathBuf::from(format!("{}!arm64-v8a/libunity.sym", zip.display()));
+        assert_eq!(function_name(&entry, None), "unity");
+        assert!(crate::parse_breakpad_symbol_source_with_options(&zip, None, &crate::ParserOptions::default()).is_err());
+        std::fs::remove_file(&zip).unwrap();
+    }
+
+    #[test]
+    fn test_native_debug_symbols_zip() {
+        let dir = std::env::temp_dir().join(format!("addr2line_breakpad_{}_native_debug_symbols", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let zip = dir.join("native-debug-symbols.zip");
+        let elf = |abi: &str| format!("\x7fELF {}", abi);
+        let data = archive(&[
+            ("arm64-v8a/libgame.so.sym", &elf("arm64")),
+            ("armeabi-v7a/libgame.so.sym", &elf("arm")),
+            ("x86_64/libgame.so.dbg", &elf("x86_64")),
+        ]);
+        std::fs::write(&zip, data.into_inner()).unwrap();
+        let breakpad = dir.join("symbols.zip");
+        std::fs::write(&breakpad, archive(&[("arm64-v8a/libgame.sym", "MODULE Linux arm64 0102 libgame.so")]).into_inner()).unwrap();
+
+        assert!(crate::is_elf_file(&zip, Some("arm64-v8a")));
+        assert!(crate::is_elf_file(&dir.join("native-debug-symbols.zip!x86_64/libgame.so.dbg"), None));
+        assert!(!crate::is_elf_file(&zip, None));
+        assert!(!crate::is_elf_file(&breakpad, None));
+
+        #[cfg(feature = "debuginfod")]
+        {
+            let cache_dir = dir.join("cache");
+            let extracted = extract_zip_entry(&zip, None, Some("armeabi-v7a"), &cache_dir).unwrap();
+            assert!(extracted.ends_with("armeabi-v7a/libgame.so.sym"));
+            assert_eq!(std::fs::read_to_string(&extracted).unwrap(), elf("arm"));
+            assert_eq!(extract_zip_entry(&zip, None, Some("armeabi-v7a"), &cache_dir).unwrap(), extracted);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
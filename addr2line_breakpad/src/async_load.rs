@@ -0,0 +1,130 @@
+use std::io;
+use std::mem;
+use std::path::Path;
+#[cfg(feature = "remote")]
+use std::path::PathBuf;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use crate::logging::info;
+
+#[cfg(feature = "remote")]
+use crate::fetch_symbol_file;
+use crate::{archive, is_symcache, lossy_line, observer, parse_breakpad_symbol_source_with_options, ParseError, ParseMode, Parser, ParserOptions, SymbolFile, GZIP_MAGIC, ZSTD_MAGIC};
+
+impl SymbolFile {
+    /// Loads a symbol file without blocking the async runtime, leniently.
+    /// Plain text is read and parsed a buffer at a time, so other tasks run
+    /// in between; compressed files, archives and SymCaches are parsed on
+    /// tokio's blocking thread pool.
+    pub async fn load_async(path: &Path) -> Result<SymbolFile, ParseError> {
+        if archive::split_archive_path(path).1.is_some() {
+            return parse_blocking(path).await;
+        }
+        info!("loading symbol file {}", path.display());
+        let mut reader = BufReader::new(tokio::fs::File::open(path).await?);
+        let magic = reader.fill_buf().await?;
+        if magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) || magic.starts_with(archive::ZIP_MAGIC) || is_symcache(magic) {
+            return parse_blocking(path).await;
+        }
+
+        let started = observer::start();
+        let result = parse_lines(&mut reader, path).await;
+        observer::observe(started, |observer, duration| observer.parsed(&path.display().to_string(), duration, result.is_ok()));
+        result
+    }
+}
+
+async fn parse_lines(reader: &mut BufReader<tokio::fs::File>, path: &Path) -> Result<SymbolFile, ParseError> {
+    let mut parser = Parser::new(path.display().to_string(), ParseMode::Lenient);
+    let mut buf = Vec::new();
+    let mut line_number = 0;
+    while reader.read_until(b'\n', &mut buf).await? > 0 {
+        let (line, lossy) = lossy_line(mem::take(&mut buf));
+        parser.add_line(line_number, line, lossy)?;
+        line_number += 1;
+    }
+    Ok(parser.finish(None))
+}
+
+async fn parse_blocking(path: &Path) -> Result<SymbolFile, ParseError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || parse_breakpad_symbol_source_with_options(&path, None, &ParserOptions::default()))
+        .await
+        .map_err(io::Error::other)?
+}
+
+// `fetch_symbol_file` on the blocking thread pool, for services downloading
+// symbols from inside the runtime.
+#[cfg(feature = "remote")]
+pub async fn fetch_symbol_file_async(location: &str, module: Option<(&str, &str)>, cache_dir: &Path) -> io::Result<PathBuf> {
+    let location = location.to_string();
+    let module = module.map(|(name, debug_id)| (name.to_string(), debug_id.to_string()));
+    let cache_dir = cache_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let module = module.as_ref().map(|(name, debug_id)| (name.as_str(), debug_id.as_str()));
+        fetch_symbol_file(&location, module, &cache_dir)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup_address;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    const TEXT: &str = "MODULE Linux arm64 0102 libfoo.so\r\nFILE 0 a.c\nFUNC 10 8 0 f\n10 8 3 0\nFUNC zz\nPUBLIC 40 0 p";
+
+    fn check(symbol_file: &SymbolFile) {
+        assert_eq!(symbol_file.module().unwrap().name, "libfoo.so");
+        assert_eq!(lookup_address(symbol_file, 0x14).unwrap().source_file_name, "a.c");
+        assert_eq!(lookup_address(symbol_file, 0x44).unwrap().function_name, "p");
+        assert_eq!(symbol_file.diagnostics()[0].line_number, 5);
+    }
+
+    #[test]
+    fn test_load_async() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_async_{}.sym", std::process::id()));
+        std::fs::write(&path, TEXT).unwrap();
+
+        // Services spawn it on multi-threaded runtimes.
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&SymbolFile::load_async(&path));
+
+        let symbol_file = block_on(SymbolFile::load_async(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        check(&symbol_file);
+        assert!(block_on(SymbolFile::load_async(&path)).is_err());
+    }
+
+    #[test]
+    fn test_load_async_symcache() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_async_{}.symcache", std::process::id()));
+        let mut symcache = Vec::new();
+        TEXT.parse::<SymbolFile>().unwrap().write_symcache(&mut symcache).unwrap();
+        std::fs::write(&path, symcache).unwrap();
+
+        let symbol_file = block_on(SymbolFile::load_async(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(symbol_file.diagnostics().is_empty());
+        assert_eq!(lookup_address(&symbol_file, 0x14).unwrap().source_file_name, "a.c");
+        assert_eq!(lookup_address(&symbol_file, 0x44).unwrap().function_name, "p");
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_load_async_compressed() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_async_{}.sym.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(&path).unwrap(), flate2::Compression::default());
+        io::Write::write_all(&mut encoder, TEXT.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let symbol_file = block_on(SymbolFile::load_async(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        check(&symbol_file);
+    }
+}
@@ -0,0 +1,172 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::logging::debug;
+
+// Parses sizes like `500M`, `2G` or `1048576` (bytes).
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => size.split_at(i),
+        None => (size, ""),
+    };
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+// E.g. from the config file's `cache_size`.
+static CACHE_SIZE_LIMIT: RwLock<Option<u64>> = RwLock::new(None);
+
+/// Caps the download cache when ADDR2LINE_BREAKPAD_CACHE_SIZE is unset.
+pub fn set_cache_size_limit(limit: Option<u64>) {
+    *CACHE_SIZE_LIMIT.write().unwrap() = limit;
+}
+
+// Size cap for the download cache from ADDR2LINE_BREAKPAD_CACHE_SIZE, else
+// `set_cache_size_limit`; unlimited when neither is set.
+pub fn cache_size_limit() -> Option<u64> {
+    env::var("ADDR2LINE_BREAKPAD_CACHE_SIZE").ok().and_then(|size| parse_size(&size)).or(*CACHE_SIZE_LIMIT.read().unwrap())
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanStats {
+    pub removed_files: u64,
+    pub removed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(SystemTime, u64, PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else {
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((used, metadata.len(), entry.path()));
+        }
+    }
+    Ok(())
+}
+
+// Removes directories left empty by eviction, but never `dir` itself.
+fn remove_empty_dirs(dir: &Path) -> io::Result<bool> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() || !remove_empty_dirs(&path)? || fs::remove_dir(&path).is_err() {
+            empty = false;
+        }
+    }
+    Ok(empty)
+}
+
+// Evicts the least recently used files until the cache holds at most
+// `max_size` bytes. Cache hits refresh the modification time, so it doubles
+// as the last use.
+pub fn clean_cache(cache_dir: &Path, max_size: u64) -> io::Result<CleanStats> {
+    let mut stats = CleanStats::default();
+    if !cache_dir.is_dir() {
+        return Ok(stats);
+    }
+    let mut files = vec![];
+    collect_files(cache_dir, &mut files)?;
+    files.sort();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    for (_, size, path) in files {
+        if total <= max_size {
+            break;
+        }
+        debug!("evicting {} ({} bytes)", path.display(), size);
+        fs::remove_file(&path)?;
+        total -= size;
+        stats.removed_files += 1;
+        stats.removed_bytes += size;
+    }
+    stats.remaining_bytes = total;
+    remove_empty_dirs(cache_dir)?;
+    Ok(stats)
+}
+
+// A cache hit; bumps the file to most recently used.
+pub(crate) fn cached_file(path: &Path) -> Option<PathBuf> {
+    if !path.is_file() {
+        return None;
+    }
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(path.to_path_buf())
+}
+
+// Stores a download under `path` in `cache_dir`, then trims the cache to the
+// configured size.
+pub(crate) fn store(cache_dir: &Path, path: &Path, reader: &mut dyn Read) -> io::Result<PathBuf> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    let partial = path.with_extension("partial");
+    let mut file = fs::File::create(&partial)?;
+    if let Err(err) = io::copy(reader, &mut file) {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    fs::rename(&partial, path)?;
+    if let Some(limit) = cache_size_limit() {
+        // Keep the file just stored even if it alone exceeds the limit.
+        let size = fs::metadata(path)?.len();
+        clean_cache(cache_dir, limit.max(size))?;
+    }
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("500M"), Some(500 << 20));
+        assert_eq!(parse_size("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1kb"), Some(1024));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn test_clean_cache_evicts_least_recently_used() {
+        let dir = env::temp_dir().join(format!("addr2line_breakpad_cache_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let now = SystemTime::now();
+        for (i, name) in ["old/a.sym", "mid/b.sym", "new/c.sym"].iter().enumerate() {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, [0u8; 100]).unwrap();
+            let file = fs::File::options().append(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(100 - i as u64 * 10)).unwrap();
+        }
+        // Using the oldest file makes `mid` the eviction candidate.
+        cached_file(&dir.join("old/a.sym")).unwrap();
+
+        let stats = clean_cache(&dir, 250).unwrap();
+        assert_eq!(stats.removed_files, 1);
+        assert_eq!(stats.remaining_bytes, 200);
+        assert!(!dir.join("mid").exists());
+        assert!(dir.join("old/a.sym").exists() && dir.join("new/c.sym").exists());
+
+        assert_eq!(clean_cache(&dir, 0).unwrap().removed_files, 2);
+        assert!(dir.is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
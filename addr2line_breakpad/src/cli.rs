@@ -0,0 +1,1442 @@
+// Argument parsing and commands of `addr2line_breakpad`, shared with
+// `ips_breakpad` and the combined `breakpad-tools`.
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+use tracing::Level;
+
+use crate::{all_symbols, canonical_frame, diff_symbol_files, find_symbol_file, lint_symbol_file, normalize_code_address, parse_address_range, parse_module_address, parse_breakpad_symbol_source_with_options, lookup_address, lookup_range, read_source_snippet, source_basename, symbol_file_exists, Config, LookupOptions, MatchPreference, Module, NameSimplification, ParseMode, ParserOptions, SizeGrouping, SourceBundle, SourceMap, Symbol, SymbolFile, SymbolFormat, SymbolKind, WriteOptions};
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+#[cfg(feature = "remote")]
+use crate::{cache_size_limit, clean_cache, default_cache_dir, fetch_symbol_file, is_remote_source, parse_size, set_cache_size_limit, set_credentials};
+#[cfg(feature = "parquet")]
+use crate::export_parquet;
+#[cfg(feature = "upload")]
+use crate::{lint_symbol_reader, open_symbol_file, upload_symbols, UploadStatus};
+#[cfg(feature = "sqlite")]
+use crate::export_sqlite;
+#[cfg(feature = "debuginfod")]
+use crate::{build_id_to_debug_id, fetch_debuginfo, is_elf_file, parse_debuginfod_source, DwarfModule, SymbolFileBuilder};
+
+struct Options {
+    basenames: bool,
+    simplify_names: NameSimplification,
+    jsonl: bool,
+    // `module!function+0x1c4 (file.cpp:88)`, see --canonical-frames.
+    canonical_frames: bool,
+    strict: bool,
+    source_map: SourceMap,
+    source_root: Option<PathBuf>,
+    source_bundle: Option<SourceBundle>,
+    context_lines: usize,
+    lookup: LookupOptions,
+    // Addresses are normalized for it before lookup, see --arch.
+    arch: String,
+    // Other names of the module in `lib.so+0x1234` addresses, from the config.
+    module_aliases: Vec<String>,
+    // How resolved addresses are printed, see --format.
+    format: SymbolFormat,
+}
+
+fn display_file_name(source_file_name: &str, options: &Options) -> String {
+    if source_file_name.is_empty() {
+        String::from("??")
+    } else if options.basenames {
+        source_basename(source_file_name).to_string()
+    } else {
+        options.source_map.remap(source_file_name).into_owned()
+    }
+}
+
+// `symbol` with the function name and file as they are printed.
+fn display_symbol<'a>(symbol: &'a Symbol, options: &Options) -> Symbol<'a> {
+    Symbol {
+        function_name: options.simplify_names.simplify(&symbol.function_name),
+        source_file_name: Cow::Owned(display_file_name(&symbol.source_file_name, options)),
+        ..symbol.clone()
+    }
+}
+
+// --format, else `default`.
+pub fn parse_symbol_format(matches: &ArgMatches, default: &str) -> SymbolFormat {
+    match matches.value_of("format").unwrap_or(default).parse() {
+        Ok(format) => format,
+        Err(err) => {
+            println!("{}", err);
+            process::exit(-1);
+        }
+    }
+}
+
+// With --source-root or --source-bundle, the lines around the resolved one.
+fn print_source_context(source_file_name: &str, line: i64, options: &Options) {
+    if let Some(snippet) = source_context(source_file_name, line, options.source_root.as_deref(), options.source_bundle.as_ref(), options.context_lines, &options.source_map) {
+        print!("{}", snippet);
+    }
+}
+
+// The lines around `line` of a FILE path, from a checkout under `root`, else
+// from `bundle`.
+pub fn source_context(source_file_name: &str, line: i64, root: Option<&Path>, bundle: Option<&SourceBundle>, context_lines: usize, source_map: &SourceMap) -> Option<String> {
+    let path = source_map.remap(source_file_name);
+    root.and_then(|root| read_source_snippet(Some(root), &path, line, context_lines))
+        .or_else(|| bundle?.snippet(&path, line, context_lines))
+}
+
+pub fn parse_context_lines(matches: &ArgMatches) -> usize {
+    let context = matches.value_of("context").unwrap();
+    match context.parse() {
+        Ok(lines) => lines,
+        Err(_) => {
+            println!("invalid context line count({})", context);
+            process::exit(-1);
+        }
+    }
+}
+
+pub fn parse_source_bundle(matches: &ArgMatches) -> Option<SourceBundle> {
+    let path = matches.value_of("source-bundle")?;
+    match SourceBundle::open(Path::new(path)) {
+        Ok(bundle) => Some(bundle),
+        Err(err) => {
+            println!("{}: {}", path, err);
+            process::exit(-1);
+        }
+    }
+}
+
+pub fn parse_source_map(matches: &ArgMatches) -> SourceMap {
+    let mut source_map = SourceMap::new();
+    for rule in matches.values_of("source-map").into_iter().flatten() {
+        if let Err(err) = source_map.add_rule(rule) {
+            println!("{}", err);
+            process::exit(-1);
+        }
+    }
+    source_map
+}
+
+// One address per line; anything after the first whitespace (or a leading `#`)
+// is treated as a comment, so exports like `0x1234 # crash in Update` work as-is.
+fn read_address_file(path: &Path) -> Vec<String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            println!("address file({}) can not be opened: {}", path.display(), err);
+            process::exit(-1);
+        }
+    };
+    let mut addresses = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                println!("address file({}) can not be read: {}", path.display(), err);
+                process::exit(-1);
+            }
+        };
+        match line.split_whitespace().next() {
+            Some(token) if !token.starts_with('#') => addresses.push(token.to_string()),
+            _ => {}
+        }
+    }
+    addresses
+}
+
+// One line of --jsonl output.
+#[derive(Serialize)]
+struct JsonlSymbol {
+    address: String,
+    function: Option<String>,
+    // `function` shortened by --simplify-names, if that changed it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_function: Option<String>,
+    file: Option<String>,
+    line: Option<i64>,
+    // line, func, public or not_found, as in ips_breakpad's JSON.
+    #[serde(rename = "match")]
+    match_kind: &'static str,
+}
+
+fn print_jsonl_symbol(module: &Module, address: u64, options: &Options) -> bool {
+    let symbols = module.lookup_with_options(normalize_code_address(&options.arch, address), &options.lookup);
+    if symbols.is_empty() {
+        print_jsonl_line(address, None, options);
+    }
+    for symbol in &symbols {
+        print_jsonl_line(address, Some(symbol), options);
+    }
+    !symbols.is_empty()
+}
+
+fn print_jsonl_line(address: u64, symbol: Option<&Symbol>, options: &Options) {
+    let mut json = JsonlSymbol {
+        address: format!("{:#x}", address),
+        function: None,
+        short_function: None,
+        file: None,
+        line: None,
+        match_kind: "not_found",
+    };
+    if let Some(symbol) = symbol {
+        let short_function = options.simplify_names.simplify(&symbol.function_name);
+        if short_function != symbol.function_name {
+            json.short_function = Some(short_function.into_owned());
+        }
+        json.function = Some(symbol.function_name.to_string());
+        if !symbol.source_file_name.is_empty() {
+            json.file = Some(display_file_name(&symbol.source_file_name, options));
+        }
+        if symbol.source_file_number != -1 {
+            json.line = Some(symbol.source_file_number);
+        }
+        json.match_kind = match symbol.kind {
+            _ if json.line.is_some() => "line",
+            SymbolKind::Func => "func",
+            SymbolKind::Public => "public",
+        };
+    }
+    println!("{}", serde_json::to_string(&json).unwrap());
+}
+
+// Returns false when the address could not be resolved.
+fn print_symbol(module: &Module, address: u64, options: &Options) -> bool {
+    if options.jsonl {
+        return print_jsonl_symbol(module, address, options);
+    }
+    let code_address = normalize_code_address(&options.arch, address);
+    let symbols = module.lookup_with_options(code_address, &options.lookup);
+    if options.canonical_frames {
+        println!("{}", canonical_frame(&module.name, code_address, symbols.first(), options.simplify_names));
+        if let Some(symbol) = symbols.first() {
+            print_source_context(&symbol.source_file_name, symbol.source_file_number, options);
+        }
+        return !symbols.is_empty();
+    }
+    if symbols.is_empty() {
+        println!("Not found symbol for address({:#x}", address);
+        return false;
+    }
+    for symbol in symbols {
+        println!("{}", options.format.format(address, &display_symbol(&symbol, options)));
+        print_source_context(&symbol.source_file_name, symbol.source_file_number, options);
+    }
+    true
+}
+
+// Reads whitespace separated addresses from stdin until EOF or `quit`, so a
+// large symbol file only has to be parsed once per investigation.
+fn run_interactive(module: &Module, options: &Options) -> usize {
+    let mut failures = 0;
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        // No prompt in the middle of --jsonl output.
+        if !options.jsonl {
+            print!("> ");
+            io::stdout().flush().unwrap();
+        }
+
+        line.clear();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            if !options.jsonl {
+                println!();
+            }
+            break;
+        }
+        let line = line.trim();
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        for addr in line.split_whitespace() {
+            match parse_module_address(addr) {
+                Some((image, offset)) => match resolve_address_arg(module, addr, image, offset, &options.module_aliases) {
+                    Ok(address) => {
+                        if !print_symbol(module, address, options) {
+                            failures += 1;
+                        }
+                    }
+                    Err(message) => {
+                        println!("{}", message);
+                        failures += 1;
+                    }
+                },
+                None => println!("invalid address({})", addr),
+            }
+        }
+    }
+    failures
+}
+
+// Whether `image` of a `lib.so+0x1234` address names the loaded module, by
+// name, path, id or alias. A module without a name, from debuginfod, takes
+// any.
+fn is_loaded_module(module: &Module, image: &str, aliases: &[String]) -> bool {
+    module.name.is_empty()
+        || module.matches(image)
+        || module.matches(source_basename(image))
+        || aliases.iter().any(|alias| alias == image || alias == source_basename(image))
+}
+
+// A parsed `prefix+offset` address is relative to the loaded module, or to
+// the function of that name as in partially symbolicated logs.
+fn resolve_address_arg(module: &Module, addr: &str, prefix: Option<&str>, offset: u64, aliases: &[String]) -> Result<u64, String> {
+    match prefix {
+        None => Ok(offset),
+        Some(image) if is_loaded_module(module, image, aliases) => Ok(offset),
+        Some(name) => function_offset_address(&module.symbols, addr, name, offset),
+    }
+}
+
+fn function_offset_address(symbol_file: &SymbolFile, addr: &str, name: &str, offset: u64) -> Result<u64, String> {
+    match symbol_file.function_addresses(name).as_slice() {
+        [address] => address.checked_add(offset).ok_or_else(|| format!("invalid address({})", addr)),
+        [] => Err(format!("address({}): no module or FUNC record named {}", addr, name)),
+        addresses => {
+            let addresses: Vec<String> = addresses.iter().map(|address| format!("{:#x}", address)).collect();
+            Err(format!("{} names several functions, add the offset to one of {}", name, addresses.join(", ")))
+        }
+    }
+}
+
+// Local path of the symbol file, downloading s3://, gs:// and http(s):// objects first.
+fn resolve_input(input: &str, config: &Config) -> PathBuf {
+    #[cfg(feature = "debuginfod")]
+    {
+        if let Some(build_id) = parse_debuginfod_source(input) {
+            return match fetch_debuginfo(build_id, &default_cache_dir()) {
+                Ok(path) => path,
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    #[cfg(feature = "remote")]
+    {
+        if is_remote_source(input) {
+            return match fetch_symbol_file(input, None, &default_cache_dir()) {
+                Ok(path) => path,
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    let path = PathBuf::from(input);
+    if !symbol_file_exists(&path) {
+        if let Some(path) = find_symbol_file(&config.symbol_search_path(), input, None) {
+            return path;
+        }
+        println!("input file({}) is not exists", path.display());
+        process::exit(-1);
+    }
+    path
+}
+
+// The config file is shared by all commands, `format = "json"` only changes
+// the output of those that have any.
+pub fn load_config(matches: &ArgMatches) -> Config {
+    let config = match Config::load(matches.value_of("config").map(Path::new)) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("invalid config: {}", err);
+            process::exit(-1);
+        }
+    };
+    if let Some(format) = config.output.format.as_deref().filter(|format| *format != "text" && *format != "json") {
+        println!("invalid config: unsupported output format({})", format);
+        process::exit(-1);
+    }
+    #[cfg(feature = "remote")]
+    {
+        for (url_prefix, server) in &config.server_credentials {
+            set_credentials(url_prefix, server.credentials());
+        }
+        if let Some(size) = &config.cache_size {
+            match parse_size(size) {
+                Some(size) => set_cache_size_limit(Some(size)),
+                None => {
+                    println!("invalid config: cache_size({})", size);
+                    process::exit(-1);
+                }
+            }
+        }
+    }
+    config
+}
+
+pub struct ParseSettings {
+    pub show_progress: bool,
+    pub mode: ParseMode,
+    pub unknown_file_placeholder: Option<String>,
+    pub low_memory: bool,
+    pub keep_unparsed_records: bool,
+}
+
+impl ParseSettings {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        ParseSettings {
+            show_progress: !matches.is_present("quiet"),
+            mode: if matches.is_present("strict-parse") {
+                ParseMode::Strict
+            } else {
+                ParseMode::Lenient
+            },
+            unknown_file_placeholder: matches.value_of("unknown-file-placeholder").map(String::from),
+            low_memory: matches.is_present("low-memory"),
+            keep_unparsed_records: false,
+        }
+    }
+}
+
+// Progress bar on stderr while parsing a symbol file. indicatif hides it when
+// stderr isn't a terminal; --quiet turns it off.
+pub fn parse_with_progress(path: &Path, module_hint: Option<&str>, settings: &ParseSettings) -> SymbolFile {
+    let mut options = ParserOptions {
+        mode: settings.mode,
+        unknown_file_placeholder: settings.unknown_file_placeholder.clone(),
+        low_memory: settings.low_memory,
+        keep_unparsed_records: settings.keep_unparsed_records,
+        ..ParserOptions::default()
+    };
+    if !settings.show_progress {
+        return parse_or_exit(path, module_hint, &options);
+    }
+    let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} parsing {msg} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .unwrap(),
+    );
+    bar.set_message(path.display().to_string());
+    let progress_bar = bar.clone();
+    options.progress = Some(Box::new(move |parsed, total| {
+        if let Some(total) = total {
+            progress_bar.set_length(total);
+        }
+        progress_bar.set_position(parsed);
+    }));
+    let symbol_file = parse_or_exit(path, module_hint, &options);
+    bar.finish_and_clear();
+    symbol_file
+}
+
+fn parse_or_exit(path: &Path, module_hint: Option<&str>, options: &ParserOptions) -> SymbolFile {
+    match parse_breakpad_symbol_source_with_options(path, module_hint, options) {
+        Ok(symbol_file) => symbol_file,
+        Err(err) => {
+            println!("{}: {}", path.display(), err);
+            process::exit(-1);
+        }
+    }
+}
+
+// `input` is the command line spec, `path` the file resolve_input found for it.
+fn load_module(input: &str, path: &Path, config: &Config, settings: &ParseSettings) -> Module {
+    #[cfg(feature = "debuginfod")]
+    {
+        if let Some(build_id) = parse_debuginfod_source(input) {
+            let mut module = Module::new(SymbolFileBuilder::new().build());
+            module.debug_id = build_id_to_debug_id(build_id);
+            module.dwarf = match DwarfModule::open(path) {
+                Ok(mut dwarf) => {
+                    dwarf.set_demangle(config.demangle.unwrap_or(true));
+                    Some(dwarf)
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+            return module;
+        }
+        if is_elf_file(path, None) {
+            return match Module::from_elf("", path, None, &default_cache_dir()) {
+                Ok(mut module) => {
+                    if let Some(dwarf) = module.dwarf.as_mut() {
+                        dwarf.set_demangle(config.demangle.unwrap_or(true));
+                    }
+                    module
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    let _ = (input, config);
+    #[allow(unused_mut)]
+    let mut module = Module::new(parse_with_progress(path, None, settings));
+    // Addresses the symbols miss are looked up by the build id in INFO CODE_ID.
+    #[cfg(feature = "debuginfod")]
+    module.enable_debuginfod(&default_cache_dir(), config.demangle.unwrap_or(true));
+    module
+}
+
+#[cfg(feature = "remote")]
+fn run_cache_clean(matches: &ArgMatches) {
+    load_config(matches);
+    let max_size = match matches.value_of("max-size") {
+        Some(size) => match parse_size(size) {
+            Some(size) => size,
+            None => {
+                println!("invalid size({})", size);
+                process::exit(-1);
+            }
+        },
+        // Emptying the cache takes an explicit --max-size 0.
+        None => match cache_size_limit() {
+            Some(size) => size,
+            None => {
+                println!("no cache size limit configured, pass --max-size");
+                process::exit(-1);
+            }
+        },
+    };
+    let cache_dir = default_cache_dir();
+    match clean_cache(&cache_dir, max_size) {
+        Ok(stats) => println!(
+            "{}: removed {} file(s), {} bytes; {} bytes remaining",
+            cache_dir.display(),
+            stats.removed_files,
+            stats.removed_bytes,
+            stats.remaining_bytes
+        ),
+        Err(err) => {
+            println!("{}", err);
+            process::exit(-1);
+        }
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "parquet"))]
+fn run_export(matches: &ArgMatches, config: &Config) {
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let symbol_file = parse_with_progress(&input, None, &ParseSettings::from_matches(matches));
+    let mut result = Ok(());
+    #[cfg(feature = "sqlite")]
+    {
+        if let Some(path) = matches.value_of("sqlite") {
+            result = result.and_then(|_| export_sqlite(&symbol_file, Path::new(path)));
+        }
+    }
+    #[cfg(feature = "parquet")]
+    {
+        if let Some(dir) = matches.value_of("parquet") {
+            result = result.and_then(|_| export_parquet(&symbol_file, Path::new(dir)));
+        }
+    }
+    if let Err(err) = result {
+        println!("{}", err);
+        process::exit(-1);
+    }
+}
+
+fn run_diff(old: &str, new: &str, config: &Config, settings: &ParseSettings) {
+    let old_symbols = parse_with_progress(&resolve_input(old, config), None, settings);
+    let new_symbols = parse_with_progress(&resolve_input(new, config), None, settings);
+    let diff = diff_symbol_files(&old_symbols, &new_symbols);
+
+    println!("--- {}", old);
+    println!("+++ {}", new);
+    for file in &diff.files_removed {
+        println!("- FILE {}", file);
+    }
+    for file in &diff.files_added {
+        println!("+ FILE {}", file);
+    }
+    for function in &diff.removed {
+        println!("- FUNC {:x} {:x} {}", function.address, function.size, function.name);
+    }
+    for function in &diff.added {
+        println!("+ FUNC {:x} {:x} {}", function.address, function.size, function.name);
+    }
+    for change in &diff.changed {
+        let shift = change.address_shift();
+        let sign = if shift < 0 { "-" } else { "+" };
+        println!(
+            "~ FUNC {:x} {:x} -> {:x} {:x} ({}{:#x}) {}",
+            change.old_address, change.old_size, change.new_address, change.new_size, sign, shift.unsigned_abs(), change.name
+        );
+    }
+    let resized = diff.changed.iter().filter(|change| change.is_resized()).count();
+    let moved = diff.changed.iter().filter(|change| change.is_moved()).count();
+    println!(
+        "{} added, {} removed, {} resized, {} moved, {} files added, {} files removed",
+        diff.added.len(), diff.removed.len(), resized, moved, diff.files_added.len(), diff.files_removed.len()
+    );
+}
+
+#[derive(Serialize)]
+struct JsonFunctionLines {
+    function: String,
+    address: String,
+    size: u64,
+    lines: Vec<JsonLine>,
+}
+
+#[derive(Serialize)]
+struct JsonLine {
+    address: String,
+    size: u64,
+    file: String,
+    line: i64,
+}
+
+// Quoted only when needed, doubling quotes as RFC 4180 has it.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// `address size kind name` of the records --kind selects and `filter` accepts.
+fn print_symbol_records(symbol_file: &SymbolFile, matches: &ArgMatches, filter: impl Fn(&str) -> bool) -> usize {
+    let kind = match matches.value_of("kind") {
+        Some("func") => Some(SymbolKind::Func),
+        Some("public") => Some(SymbolKind::Public),
+        _ => None,
+    };
+    let mut count = 0;
+    for record in all_symbols(symbol_file) {
+        if kind.is_some_and(|kind| kind != record.kind) || !filter(&record.name) {
+            continue;
+        }
+        println!("{:x} {:x} {} {}", record.address, record.size, record.kind.as_str(), record.name);
+        count += 1;
+    }
+    count
+}
+
+fn run_search(matches: &ArgMatches, config: &Config) {
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let symbol_file = parse_with_progress(&input, None, &ParseSettings::from_matches(matches));
+    let pattern = matches.value_of("pattern").unwrap();
+    let count = if matches.is_present("ignore-case") {
+        let pattern = pattern.to_lowercase();
+        print_symbol_records(&symbol_file, matches, |name| name.to_lowercase().contains(&pattern))
+    } else {
+        print_symbol_records(&symbol_file, matches, |name| name.contains(pattern))
+    };
+    if count == 0 {
+        println!("no symbol matches {}", pattern);
+        process::exit(1);
+    }
+}
+
+fn run_export_lines(matches: &ArgMatches, config: &Config) {
+    let address = matches.value_of("address").map(|addr| match parse_module_address(addr) {
+        Some((prefix, offset)) => (addr, prefix, offset),
+        None => {
+            println!("invalid address({})", addr);
+            process::exit(-1);
+        }
+    });
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let symbol_file = parse_with_progress(&input, None, &ParseSettings::from_matches(matches));
+    let address = match (address, matches.value_of("function")) {
+        (Some((_, None, address)), _) => address,
+        (Some((addr, Some(name), offset)), _) => match function_offset_address(&symbol_file, addr, name, offset) {
+            Ok(address) => address,
+            Err(message) => {
+                println!("{}", message);
+                process::exit(-1);
+            }
+        },
+        (None, Some(name)) => match symbol_file.function_addresses(name).as_slice() {
+            [address] => *address,
+            [] => {
+                println!("no FUNC record named {}", name);
+                process::exit(-1);
+            }
+            addresses => {
+                let addresses: Vec<String> = addresses.iter().map(|address| format!("{:#x}", address)).collect();
+                println!("{} names several functions, pass one of {} as --address", name, addresses.join(", "));
+                process::exit(-1);
+            }
+        },
+        (None, None) => unreachable!(),
+    };
+    let function = match lookup_address(&symbol_file, address) {
+        Some(symbol) if symbol.kind == SymbolKind::Func => symbol,
+        _ => {
+            println!("no FUNC record contains {:#x}", address);
+            process::exit(-1);
+        }
+    };
+    let lines = symbol_file.lines_for_function(address);
+    if matches.value_of("format") == Some("json") {
+        let json = JsonFunctionLines {
+            function: function.function_name.into_owned(),
+            address: format!("{:#x}", function.function_address.unwrap()),
+            size: function.function_size.unwrap(),
+            lines: lines
+                .into_iter()
+                .map(|line| JsonLine {
+                    address: format!("{:#x}", line.address),
+                    size: line.size,
+                    file: line.source_file_name,
+                    line: line.line_number,
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return;
+    }
+    println!("address,size,file,line");
+    for line in &lines {
+        println!("{:#x},{},{},{}", line.address, line.size, csv_field(&line.source_file_name), line.line_number);
+    }
+}
+
+fn run_lint(matches: &ArgMatches, config: &Config) {
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let issues = match lint_symbol_file(&input) {
+        Ok(issues) => issues,
+        Err(err) => {
+            println!("{}: {}", input.display(), err);
+            process::exit(-1);
+        }
+    };
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&issues).unwrap());
+    } else {
+        for issue in &issues {
+            match issue.line_number {
+                Some(line_number) => println!("{}:{}: {}: {}", input.display(), line_number, issue.kind.as_str(), issue.message),
+                None => println!("{}: {}: {}", input.display(), issue.kind.as_str(), issue.message),
+            }
+        }
+        println!("{} issue(s)", issues.len());
+    }
+    if !issues.is_empty() {
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "upload")]
+fn run_upload(matches: &ArgMatches, config: &Config) {
+    let api_key = match matches.value_of("api-key").map(String::from).or_else(|| std::env::var("ADDR2LINE_BREAKPAD_UPLOAD_KEY").ok()) {
+        Some(api_key) => api_key,
+        None => {
+            println!("no API key, pass --api-key or set ADDR2LINE_BREAKPAD_UPLOAD_KEY");
+            process::exit(-1);
+        }
+    };
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    // Collectors take the plain text, whatever it was stored as.
+    let mut data = vec![];
+    if let Err(err) = open_symbol_file(&input, None).and_then(|mut reader| reader.read_to_end(&mut data)) {
+        println!("{}: {}", input.display(), err);
+        process::exit(-1);
+    }
+    if !matches.is_present("skip-lint") {
+        let issues = lint_symbol_reader(data.as_slice()).unwrap_or_default();
+        if let Some(issue) = issues.first() {
+            println!("{}: {} lint issue(s), the first: {}; see `lint`, or pass --skip-lint", input.display(), issues.len(), issue.message);
+            process::exit(1);
+        }
+    }
+    let first_line = data.split(|b| *b == b'\n').next().unwrap_or_default();
+    let module = match SymbolFile::from_bytes(first_line).ok().and_then(|symbol_file| symbol_file.module().cloned()) {
+        Some(module) => module,
+        None => {
+            println!("{}: no MODULE record, the collector needs its name and debug id", input.display());
+            process::exit(-1);
+        }
+    };
+    match upload_symbols(matches.value_of("url").unwrap(), &api_key, &module.name, &module.debug_id, &data, matches.is_present("force")) {
+        Ok(UploadStatus::Uploaded) => println!("uploaded {}/{}", module.name, module.debug_id),
+        Ok(UploadStatus::Duplicate) => println!("uploaded {}/{}, the collector already had identical symbols", module.name, module.debug_id),
+        Ok(UploadStatus::AlreadyPresent) => println!("{}/{} is already on the collector, pass --force to upload anyway", module.name, module.debug_id),
+        Err(err) => {
+            println!("upload of {}/{} failed: {}", module.name, module.debug_id, err);
+            process::exit(-1);
+        }
+    }
+}
+
+fn run_coverage(matches: &ArgMatches, config: &Config) {
+    let gaps = matches.value_of("gaps").unwrap();
+    let gaps: usize = match gaps.parse() {
+        Ok(gaps) => gaps,
+        Err(_) => {
+            println!("invalid gap count({})", gaps);
+            process::exit(-1);
+        }
+    };
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let coverage = parse_with_progress(&input, None, &ParseSettings::from_matches(matches)).coverage();
+    let (start, end) = match coverage.range {
+        Some(range) => range,
+        None => {
+            println!("no FUNC records");
+            return;
+        }
+    };
+    println!("{} FUNC records in {:#x}..{:#x}", coverage.function_count, start, end);
+    println!(
+        "covered {} of {} bytes ({:.2}%)",
+        coverage.covered_bytes,
+        coverage.span(),
+        coverage.covered_ratio() * 100.0
+    );
+    println!("{} gap(s), {} bytes", coverage.gaps.len(), coverage.gap_bytes());
+    for gap in coverage.gaps.iter().take(gaps) {
+        println!("  {:#x}..{:#x} {:#x}", gap.start, gap.start + gap.size, gap.size);
+    }
+    println!("{} overlapping FUNC record(s), {} bytes", coverage.overlapping_functions, coverage.overlapping_bytes);
+}
+
+fn run_top_functions(matches: &ArgMatches, config: &Config) {
+    let parse_count = |name| {
+        let value = matches.value_of(name).unwrap();
+        match value.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                println!("invalid {}({})", name, value);
+                process::exit(-1);
+            }
+        }
+    };
+    let count = parse_count("count");
+    let grouping = match matches.value_of("group-by") {
+        Some("file") => SizeGrouping::File,
+        Some("namespace") => SizeGrouping::Namespace(parse_count("depth")),
+        _ => SizeGrouping::Function,
+    };
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let sizes = parse_with_progress(&input, None, &ParseSettings::from_matches(matches)).function_sizes(grouping);
+    println!("{} FUNC records, {} bytes", sizes.function_count, sizes.total_bytes);
+    for entry in sizes.entries.iter().take(count) {
+        let share = if sizes.total_bytes > 0 { entry.size as f64 * 100.0 / sizes.total_bytes as f64 } else { 0.0 };
+        match entry.address {
+            Some(address) => println!("{:>10} {:>6.2}% {:#x} {}", entry.size, share, address, entry.name),
+            None => println!("{:>10} {:>6.2}% {:>6} {}", entry.size, share, entry.count, entry.name),
+        }
+    }
+    println!("size histogram:");
+    for bucket in &sizes.histogram {
+        let max_size = bucket.min_size.saturating_mul(2).max(1);
+        println!("  {:>10}..{:<10} {:>8} function(s) {:>12} bytes", bucket.min_size, max_size, bucket.count, bucket.bytes);
+    }
+}
+
+fn run_compact(matches: &ArgMatches, config: &Config) {
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let output = matches.value_of("output").unwrap();
+    // STACK, INLINE and INFO records are written back as they are.
+    let settings = ParseSettings {
+        keep_unparsed_records: true,
+        ..ParseSettings::from_matches(matches)
+    };
+    let symbol_file = parse_with_progress(&input, None, &settings);
+    let options = WriteOptions { compact_files: true };
+    let written = File::create(output).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        symbol_file.write_breakpad_with_options(&mut writer, &options)?;
+        writer.flush()
+    });
+    if let Err(err) = written {
+        println!("{}: {}", output, err);
+        process::exit(-1);
+    }
+    println!("{}: {} duplicate FILE record(s) folded", output, symbol_file.duplicate_files());
+}
+
+fn run_symcache(matches: &ArgMatches, config: &Config) {
+    let input = resolve_input(matches.value_of("input").unwrap(), config);
+    let output = matches.value_of("output").unwrap();
+    let symbol_file = parse_with_progress(&input, None, &ParseSettings::from_matches(matches));
+    let written = File::create(output).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        symbol_file.write_symcache(&mut writer)?;
+        writer.flush()
+    });
+    if let Err(err) = written {
+        println!("{}: {}", output, err);
+        process::exit(-1);
+    }
+}
+
+// Warnings and errors by default, -v for loaded symbol files, -vv for the
+// records each lookup matched, -vvv for every parsed record.
+pub fn init_logging(matches: &ArgMatches) {
+    let level = match (matches.is_present("quiet"), matches.occurrences_of("verbose")) {
+        (true, _) => Level::ERROR,
+        (_, 0) => Level::WARN,
+        (_, 1) => Level::INFO,
+        (_, 2) => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .without_time()
+        .with_target(false)
+        .init();
+}
+
+// Logging, config and symbol parsing options, taken by every command.
+pub fn global_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .multiple(true)
+            .global(true)
+            .help("log loaded symbol files (-v), matched records (-vv) and parsed records (-vvv) to stderr"),
+        Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .conflicts_with("verbose")
+            .global(true)
+            .help("only log errors"),
+        Arg::with_name("strict-parse")
+            .long("strict-parse")
+            .global(true)
+            .help("fail on the first malformed symbol file record instead of skipping it"),
+        Arg::with_name("unknown-file-placeholder")
+            .long("unknown-file-placeholder")
+            .takes_value(true)
+            .global(true)
+            .value_name("TEXT")
+            .help("source file shown for LINE records whose FILE id is undefined, {id} is replaced with the id [default: <unknown-file-id:{id}>]"),
+        Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .global(true)
+            .value_name("FILE")
+            .help("config file [default: ~/.config/addr2line_breakpad/config.toml]"),
+    ]
+}
+
+pub fn stats_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("stats")
+        .long("stats")
+        .help("print parse time, lookups per module and memory used by the symbol tables to stderr at the end")
+}
+
+// Only for commands that look addresses up, the others need every LINE record.
+pub fn low_memory_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("low-memory")
+        .long("low-memory")
+        .help("don't keep LINE records in memory, re-read them from the symbol file on each lookup, for multi-GB symbol files on small machines")
+}
+
+// --stats, on stderr so it doesn't mix with the output.
+pub fn print_stats(parse_time: Duration, modules: &[Module]) {
+    eprintln!("parse time: {:.3?}", parse_time);
+    for module in modules {
+        let stats = module.lookup_stats();
+        eprintln!(
+            "{}: {} lookup(s), {} hit(s), {} miss(es), ~{} of symbols, {} LINE(s) with undefined FILE",
+            if module.name.is_empty() { "<unnamed module>" } else { &module.name },
+            stats.lookups,
+            stats.hits,
+            stats.misses(),
+            HumanBytes(module.symbols.memory_usage() as u64),
+            module.symbols.undefined_file_lines()
+        );
+    }
+}
+
+// Looking up addresses, the default command of `addr2line_breakpad`.
+pub fn lookup_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(Arg::with_name("input").help("input symbol file, symbols.zip[!entry], an ELF file with debug info or debuginfod:BUILD_ID").required(true))
+        .arg(
+            Arg::with_name("address-file")
+                .long("address-file")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("file containing one address per line"),
+        )
+        .arg(
+            Arg::with_name("interactive")
+                .short("i")
+                .long("interactive")
+                .help("parse the symbol file once, then read addresses from stdin"),
+        )
+        .arg(
+            Arg::with_name("basenames")
+                .short("s")
+                .long("basenames")
+                .help("strip directory names from source file paths"),
+        )
+        .arg(
+            Arg::with_name("simplify-names")
+                .long("simplify-names")
+                .takes_value(true)
+                .value_name("MODE")
+                .possible_values(&NameSimplification::VARIANTS)
+                .help("shorten function names by dropping template arguments, parameter lists or both [default: from the config file, else none]"),
+        )
+        .arg(
+            Arg::with_name("source-map")
+                .long("source-map")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("FROM=TO")
+                .help("rewrite source file paths starting with FROM to start with TO, e.g. a local checkout"),
+        )
+        .arg(
+            Arg::with_name("source-root")
+                .long("source-root")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("print the source lines around each resolved line, reading files from DIR"),
+        )
+        .arg(
+            Arg::with_name("source-bundle")
+                .long("source-bundle")
+                .takes_value(true)
+                .value_name("ARCHIVE")
+                .help("print the source lines around each resolved line, reading files from a zip or tar(.gz) of the build's sources"),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .takes_value(true)
+                .value_name("N")
+                .default_value("2")
+                .help("lines of source to print before and after the resolved line with --source-root or --source-bundle"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("exit with a non-zero status if any address could not be resolved"),
+        )
+        .arg(
+            Arg::with_name("prefer")
+                .long("prefer")
+                .takes_value(true)
+                .possible_values(&["func", "public"])
+                .default_value("func")
+                .help("record reported when an address is in a FUNC and past a PUBLIC starting inside it"),
+        )
+        .arg(
+            Arg::with_name("all-matches")
+                .long("all-matches")
+                .help("report both records in that case, the --prefer one first"),
+        )
+        .arg(
+            Arg::with_name("arch")
+                .long("arch")
+                .takes_value(true)
+                .value_name("ARCH")
+                .help("architecture of the addresses, for arm (armeabi-v7a) the Thumb bit is cleared before lookup [default: the MODULE record's]"),
+        )
+        .arg(stats_arg())
+        .arg(low_memory_arg())
+        .arg(
+            Arg::with_name("jsonl")
+                .long("jsonl")
+                .conflicts_with_all(&["range", "source-root", "source-bundle"])
+                .help("print one JSON object per address as soon as it is resolved, for streaming large batches"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .value_name("TEMPLATE")
+                .conflicts_with("jsonl")
+                .help("print each resolved address as TEMPLATE, with the placeholders {addr}, {func}, {file}, {line} and {offset} (into the function) [default: {addr} {func} {file}:{line}]"),
+        )
+        .arg(
+            Arg::with_name("canonical-frames")
+                .long("canonical-frames")
+                .conflicts_with_all(&["jsonl", "format"])
+                .help("print each address as module!function+0x1c4 (file.cpp:88), as ips_breakpad --canonical-frames prints frames, for log pipelines"),
+        )
+        .arg(
+            Arg::with_name("range")
+                .long("range")
+                .takes_value(true)
+                .value_name("START..END")
+                .help("print every FUNC intersecting the address range, e.g. 0x10000..0x20000"),
+        )
+        .arg(
+            Arg::with_name("address")
+                .help("address to lookup, as 0x1234, lib.so+0x1234 or function+0x40, or @file to read addresses from a file")
+                .multiple(true)
+                .required_unless_one(&["address-file", "interactive", "range"]),
+        )
+}
+
+fn kind_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("kind")
+        .long("kind")
+        .takes_value(true)
+        .possible_values(&["func", "public"])
+        .help("only FUNC or only PUBLIC records")
+}
+
+// `dump-symbols`, `search`, `diff`, `export-lines`, `lint`, `coverage`,
+// `top-functions`, `compact`, and
+// `cache`, `export` and `upload` where built with their features.
+pub fn symbol_subcommands<'a, 'b>() -> Vec<App<'a, 'b>> {
+    #[allow(unused_mut)]
+    let mut subcommands = vec![
+        SubCommand::with_name("dump-symbols")
+            .about("print every FUNC and PUBLIC record as `address size kind name`, sorted by address")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(kind_arg()),
+        SubCommand::with_name("search")
+            .about("print the FUNC and PUBLIC records whose name contains a pattern, like dump-symbols")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(Arg::with_name("pattern").help("part of the name").required(true))
+            .arg(
+                Arg::with_name("ignore-case")
+                    .short("i")
+                    .long("ignore-case")
+                    .help("match the pattern case-insensitively"),
+            )
+            .arg(kind_arg()),
+        SubCommand::with_name("diff")
+            .about("compare two symbol files of the same module")
+            .arg(Arg::with_name("old").help("old symbol file").required(true))
+            .arg(Arg::with_name("new").help("new symbol file").required(true)),
+        SubCommand::with_name("export-lines")
+            .about("print the LINE table of one function, to map profiler hits inside it to source lines")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(
+                Arg::with_name("function")
+                    .long("function")
+                    .takes_value(true)
+                    .value_name("NAME")
+                    .required_unless("address")
+                    .conflicts_with("address")
+                    .help("exact name of the function"),
+            )
+            .arg(
+                Arg::with_name("address")
+                    .long("address")
+                    .takes_value(true)
+                    .value_name("ADDR")
+                    .help("any address inside the function, or function+0x40"),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(&["csv", "json"])
+                    .default_value("csv")
+                    .help("CSV with an address,size,file,line header, or a JSON object with the function and its lines"),
+            ),
+        SubCommand::with_name("lint")
+            .about("check a symbol file for malformed records, LINE records outside their FUNC, undefined FILE ids, overlapping FUNCs, unsorted addresses and a missing MODULE record; exits with 1 if there are any")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(Arg::with_name("json").long("json").help("print the issues as a JSON array of {kind, line_number, message}")),
+        SubCommand::with_name("coverage")
+            .about("report how much of the module the FUNC records cover, the largest gaps between them and overlaps")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(
+                Arg::with_name("gaps")
+                    .long("gaps")
+                    .takes_value(true)
+                    .value_name("N")
+                    .default_value("10")
+                    .help("number of largest gaps to list"),
+            ),
+        SubCommand::with_name("top-functions")
+            .about("list the largest FUNC records, or FILEs or namespaces by the size of their functions, and a histogram of function sizes")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(
+                Arg::with_name("count")
+                    .short("n")
+                    .long("count")
+                    .takes_value(true)
+                    .value_name("N")
+                    .default_value("20")
+                    .help("number of entries to list"),
+            )
+            .arg(
+                Arg::with_name("group-by")
+                    .long("group-by")
+                    .takes_value(true)
+                    .possible_values(&["function", "file", "namespace"])
+                    .default_value("function")
+                    .help("add function sizes up by the FILE of their first LINE record or by namespace"),
+            )
+            .arg(
+                Arg::with_name("depth")
+                    .long("depth")
+                    .takes_value(true)
+                    .value_name("N")
+                    .default_value("1")
+                    .help("namespace components to group by, il2cpp::os at 2"),
+            ),
+        SubCommand::with_name("compact")
+            .about("rewrite a symbol file with each FILE path once and only the FILEs LINE records use, renumbered from 0")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(Arg::with_name("output").help("where to write the compacted symbol file").required(true)),
+        SubCommand::with_name("symcache")
+            .about("convert a symbol file to a Sentry SymCache, for tools of the symbolic ecosystem; SymCaches also load wherever a symbol file does")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(Arg::with_name("output").help("where to write the SymCache").required(true)),
+    ];
+    #[cfg(feature = "remote")]
+    subcommands.push(
+        SubCommand::with_name("cache")
+            .about("manage the download cache for remote symbol sources")
+            .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("clean")
+                    .about("evict least recently used files until the cache fits in --max-size")
+                    .arg(
+                        Arg::with_name("max-size")
+                            .long("max-size")
+                            .takes_value(true)
+                            .value_name("SIZE")
+                            .help("size to shrink the cache to, e.g. 500M, 0 to empty it [default: ADDR2LINE_BREAKPAD_CACHE_SIZE, or cache_size of the config]"),
+                    ),
+            ),
+    );
+    #[cfg(feature = "upload")]
+    subcommands.push(
+        SubCommand::with_name("upload")
+            .about("lint a symbol file and publish it to a symbol collector speaking the sym-upload-v2 protocol, as sym_upload -p sym-upload-v2 does")
+            .arg(Arg::with_name("input").help("input symbol file").required(true))
+            .arg(
+                Arg::with_name("url")
+                    .long("url")
+                    .takes_value(true)
+                    .value_name("URL")
+                    .required(true)
+                    .help("base URL of the symbol collector"),
+            )
+            .arg(
+                Arg::with_name("api-key")
+                    .long("api-key")
+                    .takes_value(true)
+                    .value_name("KEY")
+                    .help("API key of the collector [default: ADDR2LINE_BREAKPAD_UPLOAD_KEY]"),
+            )
+            .arg(Arg::with_name("force").long("force").help("upload even if the collector already has symbols for the module"))
+            .arg(Arg::with_name("skip-lint").long("skip-lint").help("upload even if lint reports issues")),
+    );
+    #[cfg(any(feature = "sqlite", feature = "parquet"))]
+    {
+        let export = SubCommand::with_name("export")
+            .about("write the records of a symbol file to databases for ad-hoc queries")
+            .arg(Arg::with_name("input").help("input symbol file").required(true));
+        #[cfg(feature = "sqlite")]
+        let export = export.arg(
+            Arg::with_name("sqlite")
+                .long("sqlite")
+                .takes_value(true)
+                .value_name("FILE")
+                .required_unless("parquet")
+                .help("SQLite database to create, with module, files, functions, lines and publics tables"),
+        );
+        #[cfg(feature = "parquet")]
+        let export = export.arg(
+            Arg::with_name("parquet")
+                .long("parquet")
+                .takes_value(true)
+                .value_name("DIR")
+                .required_unless("sqlite")
+                .help("directory to write module, files, functions, lines and publics .parquet files to"),
+        );
+        subcommands.push(export);
+    }
+    subcommands
+}
+
+// Runs the `symbol_subcommands` command given, if any. Returns false when
+// there is none.
+pub fn run_symbol_subcommand(matches: &ArgMatches) -> bool {
+    #[cfg(feature = "remote")]
+    {
+        if let Some(matches) = matches.subcommand_matches("cache").and_then(|m| m.subcommand_matches("clean")) {
+            run_cache_clean(matches);
+            return true;
+        }
+    }
+
+    #[cfg(feature = "upload")]
+    {
+        if let Some(matches) = matches.subcommand_matches("upload") {
+            run_upload(matches, &load_config(matches));
+            return true;
+        }
+    }
+
+    #[cfg(any(feature = "sqlite", feature = "parquet"))]
+    {
+        if let Some(matches) = matches.subcommand_matches("export") {
+            run_export(matches, &load_config(matches));
+            return true;
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("dump-symbols") {
+        let input = resolve_input(matches.value_of("input").unwrap(), &load_config(matches));
+        let symbol_file = parse_with_progress(&input, None, &ParseSettings::from_matches(matches));
+        print_symbol_records(&symbol_file, matches, |_| true);
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("search") {
+        run_search(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export-lines") {
+        run_export_lines(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        run_lint(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("coverage") {
+        run_coverage(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("top-functions") {
+        run_top_functions(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("compact") {
+        run_compact(matches, &load_config(matches));
+        return true;
+    }
+    if let Some(matches) = matches.subcommand_matches("symcache") {
+        run_symcache(matches, &load_config(matches));
+        return true;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        run_diff(matches.value_of("old").unwrap(), matches.value_of("new").unwrap(), &load_config(matches), &ParseSettings::from_matches(matches));
+        return true;
+    }
+    false
+}
+
+pub fn run_lookup(matches: &ArgMatches) {
+    let config = load_config(matches);
+    let input_spec = matches.value_of("input").unwrap();
+    let input = resolve_input(input_spec, &config);
+
+    let mut address_args: Vec<String> = vec![];
+    for arg in matches.values_of("address").into_iter().flatten() {
+        match arg.strip_prefix('@') {
+            Some(path) => address_args.extend(read_address_file(Path::new(path))),
+            None => address_args.push(arg.to_string()),
+        }
+    }
+    for path in matches.values_of("address-file").into_iter().flatten() {
+        address_args.extend(read_address_file(Path::new(path)));
+    }
+
+    let parsed: Vec<(&str, Option<&str>, u64)> = address_args
+        .iter()
+        .map(|addr| match parse_module_address(addr) {
+            Some((image, address)) => (addr.as_str(), image, address),
+            None => {
+                println!("invalid address({})", addr);
+                process::exit(-1);
+            }
+        })
+        .collect();
+
+    let mut options = Options {
+        basenames: matches.is_present("basenames") || config.output.basenames,
+        simplify_names: match matches.value_of("simplify-names") {
+            Some(mode) => mode.parse().unwrap(),
+            None if matches.is_present("jsonl") => config.output.simplify_names.json,
+            None => config.output.simplify_names.text,
+        },
+        jsonl: matches.is_present("jsonl"),
+        canonical_frames: matches.is_present("canonical-frames"),
+        strict: matches.is_present("strict"),
+        source_map: parse_source_map(matches),
+        source_root: matches.value_of("source-root").map(PathBuf::from),
+        source_bundle: parse_source_bundle(matches),
+        context_lines: parse_context_lines(matches),
+        lookup: LookupOptions {
+            prefer: match matches.value_of("prefer") {
+                Some("public") => MatchPreference::Public,
+                _ => MatchPreference::Func,
+            },
+            both: matches.is_present("all-matches"),
+        },
+        arch: String::new(),
+        module_aliases: vec![],
+        format: parse_symbol_format(matches, "{addr} {func} {file}:{line}"),
+    };
+
+    let range = matches.value_of("range").map(|range| match parse_address_range(range) {
+        Some(range) => range,
+        None => {
+            println!("invalid address range({})", range);
+            process::exit(-1);
+        }
+    });
+
+    let started = Instant::now();
+    let module = load_module(input_spec, &input, &config, &ParseSettings::from_matches(matches));
+    let parse_time = started.elapsed();
+    options.arch = match matches.value_of("arch") {
+        Some(arch) => arch.to_string(),
+        None => module.symbols.module().map(|module| module.arch.clone()).unwrap_or_default(),
+    };
+    options.module_aliases = config.module_aliases_of(&module.name).into_iter().map(String::from).collect();
+
+    let addresses: Vec<u64> = parsed
+        .into_iter()
+        .map(|(addr, prefix, offset)| match resolve_address_arg(&module, addr, prefix, offset, &options.module_aliases) {
+            Ok(address) => address,
+            Err(message) => {
+                println!("{}", message);
+                process::exit(-1);
+            }
+        })
+        .collect();
+
+    if let Some((start, end)) = range {
+        for function in lookup_range(&module.symbols, start, end) {
+            let source_file_name = display_file_name(&function.source_file_name, &options);
+            println!(
+                "{:#x} {:#x} {} {}",
+                function.address, function.size, options.simplify_names.simplify(&function.function_name), source_file_name
+            );
+        }
+    }
+
+    let mut failures = 0;
+    for address in addresses {
+        if !print_symbol(&module, address, &options) {
+            failures += 1;
+        }
+    }
+
+    if matches.is_present("interactive") {
+        failures += run_interactive(&module, &options);
+    }
+    if matches.is_present("stats") {
+        print_stats(parse_time, std::slice::from_ref(&module));
+    }
+
+    if options.strict && failures > 0 {
+        eprintln!("{} address(es) could not be resolved", failures);
+        process::exit(1);
+    }
+}
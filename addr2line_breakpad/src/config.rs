@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+#[cfg(feature = "remote")]
+use crate::Credentials;
+use crate::NameSimplification;
+
+// Shared team setup, read from `~/.config/addr2line_breakpad/config.toml` or
+// `--config`:
+//
+//   symbol_dirs = ["/srv/symbols"]
+//   symbol_servers = ["s3://team-symbols/breakpad"]
+//   cache_size = "2G"
+//   demangle = true
+//   frame_regex = '^(?P<so>\S+) at 0x(?P<mem_address>\w+) \(0x(?P<base>\w+)\+(?P<offset>\d+)\)$'
+//
+//   [module_aliases]
+//   "libunity.so" = "UnityFramework"
+//
+//   [server_credentials."https://symbols.internal/"]
+//   token_env = "SYMBOLS_TOKEN"   # or token, or username plus password(_env)
+//   headers = { "X-Team" = "mobile" }
+//
+//   [output]
+//   basenames = true
+//   format = "text"   # or "json" for ips_breakpad
+//
+//   [output.simplify_names]   # none, templates, parameters or all
+//   text = "all"
+//   markdown = "templates"
+//
+// Command line flags take precedence over the file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directories searched for symbol files, in order, after those in
+    /// BREAKPAD_SYMBOL_PATH.
+    pub symbol_dirs: Vec<PathBuf>,
+    /// s3://, gs:// and http(s):// symbol stores tried after `symbol_dirs`.
+    pub symbol_servers: Vec<String>,
+    /// Size cap for the download cache of `symbol_servers`, e.g. `2G`.
+    /// ADDR2LINE_BREAKPAD_CACHE_SIZE takes precedence.
+    pub cache_size: Option<String>,
+    /// Credentials for symbol and debuginfod servers, by URL prefix.
+    pub server_credentials: HashMap<String, ServerCredentials>,
+    /// Image name in the crash report -> MODULE name of the symbol file, or
+    /// the other way around: either side matches the other.
+    pub module_aliases: HashMap<String, String>,
+    /// Demangle names from DWARF debug info, defaults to true.
+    pub demangle: Option<bool>,
+    /// Regex for the frame lines of .ips/.crash reports in another dialect,
+    /// see `ips_breakpad --frame-regex`.
+    pub frame_regex: Option<String>,
+    pub output: OutputConfig,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub basenames: bool,
+    pub format: Option<String>,
+    pub simplify_names: SimplifyNamesConfig,
+}
+
+/// Function name simplification per output mode. JSON output always keeps
+/// the full name, `json` only adds a simplified one next to it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimplifyNamesConfig {
+    pub text: NameSimplification,
+    pub json: NameSimplification,
+    pub html: NameSimplification,
+    pub markdown: NameSimplification,
+}
+
+/// Secrets are best kept out of the file, in the environment variables
+/// `token_env` and `password_env` name.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerCredentials {
+    pub token: Option<String>,
+    pub token_env: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub password_env: Option<String>,
+    pub headers: HashMap<String, String>,
+}
+
+#[cfg(feature = "remote")]
+impl ServerCredentials {
+    pub fn credentials(&self) -> Credentials {
+        let from_env = |name: &Option<String>| name.as_ref().and_then(|name| env::var(name).ok());
+        Credentials {
+            bearer_token: self.token.clone().or_else(|| from_env(&self.token_env)),
+            basic: self.username.clone().map(|username| (username, self.password.clone().or_else(|| from_env(&self.password_env)).unwrap_or_default())),
+            headers: self.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Config::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // Directories from BREAKPAD_SYMBOL_PATH come first, then `symbol_dirs`.
+    pub fn symbol_search_path(&self) -> Vec<PathBuf> {
+        let mut dirs = symbol_path_from_env();
+        dirs.extend(self.symbol_dirs.iter().cloned());
+        dirs
+    }
+
+    // The names `module_aliases` pairs with `name`, whichever side it is on.
+    pub fn module_aliases_of(&self, name: &str) -> Vec<&str> {
+        self.module_aliases
+            .iter()
+            .filter_map(|(image_name, module_name)| match (image_name == name, module_name == name) {
+                (true, _) => Some(module_name.as_str()),
+                (_, true) => Some(image_name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // An explicit `--config` must exist; the default location is optional.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        match path {
+            Some(path) => Config::from_path(path),
+            None => match default_config_path() {
+                Some(path) if path.is_file() => Config::from_path(&path),
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+}
+
+// `:`-separated (`;` on Windows) list of symbol directories, as ndk-stack and
+// symsrv-style setups configure them in CI.
+pub fn symbol_path_from_env() -> Vec<PathBuf> {
+    match env::var_os("BREAKPAD_SYMBOL_PATH") {
+        Some(path) => env::split_paths(&path).filter(|dir| !dir.as_os_str().is_empty()).collect(),
+        None => vec![],
+    }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("addr2line_breakpad").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            symbol_dirs = ["/srv/symbols", "symbols"]
+            symbol_servers = ["s3://team-symbols/breakpad"]
+            cache_size = "500M"
+            demangle = false
+            frame_regex = '^(?P<so>\S+) (?P<mem_address>\w+) (?P<base>\w+) (?P<offset>\d+)$'
+
+            [module_aliases]
+            "libunity.so" = "UnityFramework"
+            UnityFramework = "UnityFramework.dylib"
+
+            [server_credentials."https://symbols.internal/"]
+            username = "ci"
+            password_env = "ADDR2LINE_BREAKPAD_TEST_UNSET"
+            headers = { "X-Team" = "mobile" }
+
+            [output]
+            basenames = true
+
+            [output.simplify_names]
+            text = "all"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.symbol_dirs, vec![PathBuf::from("/srv/symbols"), PathBuf::from("symbols")]);
+        assert_eq!(config.symbol_servers, vec!["s3://team-symbols/breakpad"]);
+        assert_eq!(config.cache_size.as_deref(), Some("500M"));
+        assert_eq!(config.module_aliases["libunity.so"], "UnityFramework");
+        let mut aliases = config.module_aliases_of("UnityFramework");
+        aliases.sort();
+        assert_eq!(aliases, vec!["UnityFramework.dylib", "libunity.so"]);
+        assert_eq!(config.module_aliases_of("libunity.so"), vec!["UnityFramework"]);
+        assert_eq!(config.demangle, Some(false));
+        assert_eq!(config.frame_regex.as_deref(), Some(r"^(?P<so>\S+) (?P<mem_address>\w+) (?P<base>\w+) (?P<offset>\d+)$"));
+        let server = &config.server_credentials["https://symbols.internal/"];
+        assert_eq!(server.username.as_deref(), Some("ci"));
+        #[cfg(feature = "remote")]
+        assert_eq!(
+            server.credentials(),
+            Credentials {
+                bearer_token: None,
+                basic: Some((String::from("ci"), String::new())),
+                headers: vec![(String::from("X-Team"), String::from("mobile"))],
+            }
+        );
+        assert!(config.output.basenames);
+        assert_eq!(config.output.format, None);
+        assert_eq!(config.output.simplify_names.text, NameSimplification::All);
+        assert_eq!(config.output.simplify_names.json, NameSimplification::None);
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("symbol_dir = []").is_err());
+        assert!(Config::parse("[output.simplify_names]\ntext = \"short\"").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::SymbolFile;
+
+/// Addresses between two FUNC records that no function covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub start: u64,
+    pub size: u64,
+}
+
+/// How completely the FUNC records describe the module, from
+/// [`SymbolFile::coverage`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub function_count: usize,
+    /// Start of the first FUNC and end of the last one.
+    pub range: Option<(u64, u64)>,
+    /// Bytes inside at least one FUNC.
+    pub covered_bytes: u64,
+    /// Gaps inside `range`, largest first, equally sized ones by address.
+    pub gaps: Vec<Gap>,
+    /// FUNC records starting inside an earlier one.
+    pub overlapping_functions: usize,
+    /// Bytes of those records that an earlier FUNC already covers.
+    pub overlapping_bytes: u64,
+}
+
+impl Coverage {
+    pub fn span(&self) -> u64 {
+        self.range.map_or(0, |(start, end)| end - start)
+    }
+
+    pub fn gap_bytes(&self) -> u64 {
+        self.gaps.iter().map(|gap| gap.size).sum()
+    }
+
+    /// Share of `range` covered by functions, 0 to 1.
+    pub fn covered_ratio(&self) -> f64 {
+        match self.span() {
+            0 => 0.0,
+            span => self.covered_bytes as f64 / span as f64,
+        }
+    }
+}
+
+impl SymbolFile {
+    /// Bytes covered by FUNC records, the gaps between them and where they
+    /// overlap. A symbol file dumped from a partially stripped binary shows
+    /// up with large gaps.
+    pub fn coverage(&self) -> Coverage {
+        let mut coverage = Coverage::default();
+        let mut range: Option<(u64, u64)> = None;
+        for (address, size, _) in self.functions.iter() {
+            let end = address.saturating_add(size);
+            coverage.function_count += 1;
+            range = match range {
+                None => {
+                    coverage.covered_bytes += size;
+                    Some((address, end))
+                }
+                Some((start, covered_end)) if address < covered_end => {
+                    coverage.overlapping_functions += 1;
+                    coverage.overlapping_bytes += end.min(covered_end) - address;
+                    coverage.covered_bytes += end.saturating_sub(covered_end);
+                    Some((start, covered_end.max(end)))
+                }
+                Some((start, covered_end)) => {
+                    if address > covered_end {
+                        coverage.gaps.push(Gap {
+                            start: covered_end,
+                            size: address - covered_end,
+                        });
+                    }
+                    coverage.covered_bytes += size;
+                    Some((start, end))
+                }
+            };
+        }
+        coverage.range = range;
+        coverage.gaps.sort_by(|a, b| b.size.cmp(&a.size).then(a.start.cmp(&b.start)));
+        coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolFileBuilder;
+
+    #[test]
+    fn test_coverage() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_function(0x1000, 0x100, 0, "a")
+            .add_function(0x1100, 0x80, 0, "b")
+            // 0x1180..0x1200 uncovered
+            .add_function(0x1200, 0x100, 0, "c")
+            .add_function(0x1280, 0x100, 0, "overlaps_c")
+            .add_function(0x1290, 0x10, 0, "inside_overlaps_c")
+            // 0x1380..0x2000 uncovered
+            .add_function(0x2000, 0x20, 0, "d");
+        let coverage = builder.build().coverage();
+
+        assert_eq!(coverage.function_count, 6);
+        assert_eq!(coverage.range, Some((0x1000, 0x2020)));
+        assert_eq!(coverage.covered_bytes, 0x100 + 0x80 + 0x180 + 0x20);
+        assert_eq!(
+            coverage.gaps,
+            vec![Gap { start: 0x1380, size: 0xc80 }, Gap { start: 0x1180, size: 0x80 }]
+        );
+        assert_eq!(coverage.gap_bytes() + coverage.covered_bytes, coverage.span());
+        assert_eq!(coverage.overlapping_functions, 2);
+        assert_eq!(coverage.overlapping_bytes, 0x80 + 0x10);
+
+        let empty = SymbolFileBuilder::new().build().coverage();
+        assert_eq!(empty, Coverage::default());
+        assert_eq!(empty.covered_ratio(), 0.0);
+    }
+}
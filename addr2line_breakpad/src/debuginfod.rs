@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::cache::cached_file;
+use crate::http::download;
+use crate::{Symbol, SymbolKind};
+use crate::logging::debug;
+
+// `debuginfod:<build-id>` selects debug info by GNU build id instead of a
+// breakpad symbol file.
+pub fn parse_debuginfod_source(location: &str) -> Option<&str> {
+    let build_id = location.strip_prefix("debuginfod:")?.trim_start_matches('/');
+    if build_id.len() >= 2 && build_id.len() % 2 == 0 && build_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(build_id)
+    } else {
+        None
+    }
+}
+
+// Servers from the space separated DEBUGINFOD_URLS, as used by elfutils.
+pub fn debuginfod_urls() -> Vec<String> {
+    env::var("DEBUGINFOD_URLS")
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|url| url.trim_end_matches('/').to_string())
+        .collect()
+}
+
+// Local copy of the debug info for `build_id`, asking each server in turn.
+pub fn fetch_debuginfo(build_id: &str, cache_dir: &Path) -> io::Result<PathBuf> {
+    let build_id = build_id.to_lowercase();
+    let cached = cache_dir.join("debuginfod").join(&build_id).join("debuginfo");
+    if let Some(cached) = cached_file(&cached) {
+        debug!("cache hit {}", cached.display());
+        return Ok(cached);
+    }
+    let urls = debuginfod_urls();
+    if urls.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "DEBUGINFOD_URLS is not set"));
+    }
+    let mut errors = vec![];
+    for url in urls {
+        let url = format!("{}/buildid/{}/debuginfo", url, build_id);
+        match download(&url, &[], cache_dir, &cached) {
+            Ok(path) => return Ok(path),
+            Err(err) => {
+                debug!("{}: {}", url, err);
+                errors.push(format!("{}: {}", url, err));
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no debug info for build id {}: {}", build_id, errors.join(", ")),
+    ))
+}
+
+// DWARF backend for modules without breakpad symbols. The loader parses
+// lazily through interior mutability, the mutex lets reports symbolicated in
+// parallel share it.
+pub struct DwarfModule {
+    path: PathBuf,
+    loader: Mutex<addr2line::Loader>,
+    demangle: bool,
+}
+
+impl fmt::Debug for DwarfModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DwarfModule").field("path", &self.path).finish()
+    }
+}
+
+impl DwarfModule {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let loader = addr2line::Loader::new(path)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))?;
+        Ok(DwarfModule {
+            path: path.to_path_buf(),
+            loader: Mutex::new(loader),
+            demangle: true,
+        })
+    }
+
+    pub fn set_demangle(&mut self, demangle: bool) {
+        self.demangle = demangle;
+    }
+
+    // Like a breakpad FUNC/LINE pair: the outermost function containing the
+    // address, and the innermost (possibly inlined) source location.
+    pub fn lookup(&self, rel_addr: u64) -> Option<Symbol<'static>> {
+        let loader = self.loader.lock().unwrap();
+        let probe = loader.relative_address_base() + rel_addr;
+        let mut function_name = None;
+        let mut location = None;
+        if let Ok(mut frames) = loader.find_frames(probe) {
+            while let Ok(Some(frame)) = frames.next() {
+                if location.is_none() {
+                    location = frame.location.map(|l| (l.file.unwrap_or("").to_string(), l.line.unwrap_or(0)));
+                }
+                let name = frame
+                    .function
+                    .as_ref()
+                    .and_then(|f| if self.demangle { f.demangle() } else { f.raw_name() }.ok());
+                if let Some(name) = name {
+                    function_name = Some(name.into_owned());
+                }
+            }
+        }
+        // Without DWARF function info only the symbol table is left, the
+        // equivalent of a PUBLIC record.
+        let (function_name, kind) = match function_name {
+            Some(name) => (name, SymbolKind::Func),
+            None => {
+                let name = loader.find_symbol(probe)?;
+                let name = if self.demangle { addr2line::demangle_auto(name.into(), None).into_owned() } else { name.to_string() };
+                (name, SymbolKind::Public)
+            }
+        };
+        let (source_file_name, line) = location.unwrap_or_default();
+        Some(Symbol {
+            function_name: Cow::Owned(function_name),
+            source_file_name: Cow::Owned(source_file_name),
+            source_file_number: line as i64,
+            kind,
+            stack_param_size: 0,
+            function_address: None,
+            function_size: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_debuginfod_source() {
+        assert_eq!(parse_debuginfod_source("debuginfod:B4B2bd4f"), Some("B4B2bd4f"));
+        assert_eq!(parse_debuginfod_source("debuginfod:xyz"), None);
+        assert_eq!(parse_debuginfod_source("libc.so.sym"), None);
+    }
+}
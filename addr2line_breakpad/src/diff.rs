@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::SymbolFile;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionEntry {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionChange {
+    pub name: String,
+    pub old_address: u64,
+    pub new_address: u64,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+impl FunctionChange {
+    pub fn is_resized(&self) -> bool {
+        self.old_size != self.new_size
+    }
+
+    pub fn is_moved(&self) -> bool {
+        self.old_address != self.new_address
+    }
+
+    pub fn address_shift(&self) -> i128 {
+        self.new_address as i128 - self.old_address as i128
+    }
+}
+
+// Functions are matched by name; when a name occurs more than once (static
+// functions in different files) only the lowest address is compared.
+#[derive(Debug, Default)]
+pub struct SymbolDiff {
+    pub added: Vec<FunctionEntry>,
+    pub removed: Vec<FunctionEntry>,
+    pub changed: Vec<FunctionChange>,
+    pub files_added: Vec<String>,
+    pub files_removed: Vec<String>,
+}
+
+impl SymbolDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.files_added.is_empty()
+            && self.files_removed.is_empty()
+    }
+}
+
+fn functions_by_name(symbol_file: &SymbolFile) -> BTreeMap<&str, (u64, u64)> {
+    let mut functions = BTreeMap::new();
+    for function in symbol_file.functions.values() {
+        functions
+            .entry(function.name.as_str())
+            .or_insert((function.address, function.size));
+    }
+    functions
+}
+
+pub fn diff_symbol_files(old: &SymbolFile, new: &SymbolFile) -> SymbolDiff {
+    let mut diff = SymbolDiff::default();
+
+    let old_functions = functions_by_name(old);
+    let new_functions = functions_by_name(new);
+    for (name, &(old_address, old_size)) in &old_functions {
+        match new_functions.get(name) {
+            Some(&(new_address, new_size)) => {
+                if old_address != new_address || old_size != new_size {
+                    diff.changed.push(FunctionChange {
+                        name: name.to_string(),
+                        old_address,
+                        new_address,
+                        old_size,
+                        new_size,
+                    });
+                }
+            }
+            None => diff.removed.push(FunctionEntry {
+                name: name.to_string(),
+                address: old_address,
+                size: old_size,
+            }),
+        }
+    }
+    for (name, &(address, size)) in &new_functions {
+        if !old_functions.contains_key(name) {
+            diff.added.push(FunctionEntry {
+                name: name.to_string(),
+                address,
+                size,
+            });
+        }
+    }
+    diff.added.sort_by_key(|entry| entry.address);
+    diff.removed.sort_by_key(|entry| entry.address);
+    diff.changed.sort_by_key(|change| change.old_address);
+
+    let old_files: BTreeSet<&String> = old.files.values().collect();
+    let new_files: BTreeSet<&String> = new.files.values().collect();
+    diff.files_added = new_files.difference(&old_files).map(|f| f.to_string()).collect();
+    diff.files_removed = old_files.difference(&new_files).map(|f| f.to_string()).collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolFileBuilder;
+
+    #[test]
+    fn test_diff_symbol_files() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(0, "a.cpp")
+            .add_file(1, "old.cpp")
+            .add_function(0x100, 0x10, 0, "same")
+            .add_function(0x110, 0x10, 0, "resized")
+            .add_function(0x120, 0x10, 0, "moved")
+            .add_function(0x130, 0x10, 0, "removed");
+        let old = builder.build();
+
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(3, "a.cpp")
+            .add_file(4, "new.cpp")
+            .add_function(0x100, 0x10, 0, "same")
+            .add_function(0x110, 0x20, 0, "resized")
+            .add_function(0x140, 0x10, 0, "moved")
+            .add_function(0x150, 0x10, 0, "added");
+        let new = builder.build();
+
+        let diff = diff_symbol_files(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "added");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "removed");
+        assert_eq!(diff.changed.len(), 2);
+        assert!(diff.changed[0].is_resized() && !diff.changed[0].is_moved());
+        assert!(diff.changed[1].is_moved() && !diff.changed[1].is_resized());
+        assert_eq!(diff.changed[1].address_shift(), 0x20);
+        assert_eq!(diff.files_added, vec!["new.cpp"]);
+        assert_eq!(diff.files_removed, vec!["old.cpp"]);
+
+        assert!(diff_symbol_files(&old, &old).is_empty());
+    }
+}
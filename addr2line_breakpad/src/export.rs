@@ -0,0 +1,291 @@
+use std::io;
+#[cfg(feature = "parquet")]
+use std::iter;
+use std::path::Path;
+
+#[cfg(feature = "parquet")]
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+#[cfg(feature = "parquet")]
+use parquet::file::{properties::WriterProperties, writer::SerializedFileWriter};
+#[cfg(feature = "parquet")]
+use parquet::schema::parser::parse_message_type;
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, Connection};
+
+use crate::SymbolFile;
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+CREATE TABLE module (os TEXT, arch TEXT, debug_id TEXT, name TEXT, code_id TEXT, code_file TEXT);
+CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+CREATE TABLE functions (address INTEGER NOT NULL, size INTEGER NOT NULL, stack_param_size INTEGER NOT NULL, name TEXT NOT NULL, multiple INTEGER NOT NULL);
+CREATE TABLE lines (address INTEGER NOT NULL, size INTEGER NOT NULL, line INTEGER NOT NULL, file_id INTEGER NOT NULL);
+CREATE TABLE publics (address INTEGER NOT NULL, stack_param_size INTEGER NOT NULL, name TEXT NOT NULL, multiple INTEGER NOT NULL);
+CREATE INDEX functions_address ON functions (address);
+CREATE INDEX functions_name ON functions (name);
+CREATE INDEX lines_address ON lines (address);
+CREATE INDEX lines_file_id ON lines (file_id);
+CREATE INDEX publics_address ON publics (address);
+CREATE INDEX publics_name ON publics (name);
+";
+
+#[cfg(feature = "sqlite")]
+fn sqlite_error(err: rusqlite::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+// One table per record type, addresses as SQLite's signed 64-bit integers:
+//
+//   SELECT name, size FROM functions ORDER BY size DESC LIMIT 10;
+//
+// Replaces the database if it exists.
+#[cfg(feature = "sqlite")]
+pub fn export_sqlite(symbol_file: &SymbolFile, path: &Path) -> io::Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut connection = Connection::open(path).map_err(sqlite_error)?;
+    connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+    let transaction = connection.transaction().map_err(sqlite_error)?;
+    {
+        let module = symbol_file.module.as_ref();
+        transaction
+            .execute(
+                "INSERT INTO module VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    module.map(|module| &module.os),
+                    module.map(|module| &module.arch),
+                    module.map(|module| &module.debug_id),
+                    module.map(|module| &module.name),
+                    symbol_file.code_id,
+                    symbol_file.code_file
+                ],
+            )
+            .map_err(sqlite_error)?;
+
+        let mut insert = transaction.prepare("INSERT INTO files VALUES (?1, ?2)").map_err(sqlite_error)?;
+        for (id, name) in &symbol_file.files {
+            insert.execute(params![id, name]).map_err(sqlite_error)?;
+        }
+        let mut insert = transaction.prepare("INSERT INTO functions VALUES (?1, ?2, ?3, ?4, ?5)").map_err(sqlite_error)?;
+        for function in symbol_file.functions.values() {
+            insert
+                .execute(params![function.address as i64, function.size as i64, function.stack_param_size, function.name, function.is_multiple])
+                .map_err(sqlite_error)?;
+        }
+        let mut insert = transaction.prepare("INSERT INTO lines VALUES (?1, ?2, ?3, ?4)").map_err(sqlite_error)?;
+        let loaded_lines = symbol_file.loaded_lines();
+        for line in loaded_lines.as_ref().unwrap_or(&symbol_file.lines).values() {
+            insert
+                .execute(params![line.address as i64, line.size as i64, line.line_number, line.source_file_id])
+                .map_err(sqlite_error)?;
+        }
+        let mut insert = transaction.prepare("INSERT INTO publics VALUES (?1, ?2, ?3, ?4)").map_err(sqlite_error)?;
+        for public in symbol_file.public_symbols.values() {
+            insert
+                .execute(params![public.address as i64, public.stack_param_size, public.name, public.is_multiple])
+                .map_err(sqlite_error)?;
+        }
+    }
+    transaction.commit().map_err(sqlite_error)
+}
+
+#[cfg(feature = "parquet")]
+enum Column {
+    Int64(Vec<i64>),
+    Bool(Vec<bool>),
+    Utf8(Vec<ByteArray>),
+}
+
+#[cfg(feature = "parquet")]
+impl Column {
+    fn parquet_type(&self) -> &'static str {
+        match self {
+            Column::Int64(_) => "INT64",
+            Column::Bool(_) => "BOOLEAN",
+            Column::Utf8(_) => "BYTE_ARRAY",
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn utf8<'a>(values: impl Iterator<Item = &'a str>) -> Column {
+    Column::Utf8(values.map(ByteArray::from).collect())
+}
+
+#[cfg(feature = "parquet")]
+fn field(value: Option<&String>) -> &str {
+    value.map_or("", |value| value.as_str())
+}
+
+// A single row group of required columns.
+#[cfg(feature = "parquet")]
+fn write_parquet_table(path: &Path, name: &str, columns: Vec<(&str, Column)>) -> io::Result<()> {
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|(column, values)| {
+            let annotation = if let Column::Utf8(_) = values { " (UTF8)" } else { "" };
+            format!("REQUIRED {} {}{};", values.parquet_type(), column, annotation)
+        })
+        .collect();
+    let schema = parse_message_type(&format!("message {} {{ {} }}", name, fields.join(" "))).map_err(io::Error::other)?;
+    let file = std::fs::File::create(path)?;
+    let properties = WriterProperties::builder().build();
+    let mut writer = SerializedFileWriter::new(file, schema.into(), properties.into()).map_err(io::Error::other)?;
+    let mut row_group = writer.next_row_group().map_err(io::Error::other)?;
+    for (_, values) in &columns {
+        let mut column = row_group.next_column().map_err(io::Error::other)?.unwrap();
+        match values {
+            Column::Int64(values) => column.typed::<Int64Type>().write_batch(values, None, None),
+            Column::Bool(values) => column.typed::<BoolType>().write_batch(values, None, None),
+            Column::Utf8(values) => column.typed::<ByteArrayType>().write_batch(values, None, None),
+        }
+        .map_err(io::Error::other)?;
+        column.close().map_err(io::Error::other)?;
+    }
+    row_group.close().map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+// The tables of `export_sqlite`, one `<table>.parquet` file each in `dir`:
+//
+//   SELECT name, size FROM 'symbols/functions.parquet' ORDER BY size DESC LIMIT 10;
+#[cfg(feature = "parquet")]
+pub fn export_parquet(symbol_file: &SymbolFile, dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let module = symbol_file.module.as_ref();
+    write_parquet_table(
+        &dir.join("module.parquet"),
+        "module",
+        vec![
+            ("os", utf8(iter::once(field(module.map(|module| &module.os))))),
+            ("arch", utf8(iter::once(field(module.map(|module| &module.arch))))),
+            ("debug_id", utf8(iter::once(field(module.map(|module| &module.debug_id))))),
+            ("name", utf8(iter::once(field(module.map(|module| &module.name))))),
+            ("code_id", utf8(iter::once(field(symbol_file.code_id.as_ref())))),
+            ("code_file", utf8(iter::once(field(symbol_file.code_file.as_ref())))),
+        ],
+    )?;
+
+    let mut files: Vec<(&i64, &String)> = symbol_file.files.iter().collect();
+    files.sort();
+    write_parquet_table(
+        &dir.join("files.parquet"),
+        "files",
+        vec![
+            ("id", Column::Int64(files.iter().map(|(id, _)| **id).collect())),
+            ("name", utf8(files.iter().map(|(_, name)| name.as_str()))),
+        ],
+    )?;
+
+    let functions: Vec<_> = symbol_file.functions.values().collect();
+    write_parquet_table(
+        &dir.join("functions.parquet"),
+        "functions",
+        vec![
+            ("address", Column::Int64(functions.iter().map(|function| function.address as i64).collect())),
+            ("size", Column::Int64(functions.iter().map(|function| function.size as i64).collect())),
+            ("stack_param_size", Column::Int64(functions.iter().map(|function| function.stack_param_size).collect())),
+            ("name", utf8(functions.iter().map(|function| function.name.as_str()))),
+            ("multiple", Column::Bool(functions.iter().map(|function| function.is_multiple).collect())),
+        ],
+    )?;
+
+    let loaded_lines = symbol_file.loaded_lines();
+    let lines: Vec<_> = loaded_lines.as_ref().unwrap_or(&symbol_file.lines).values().collect();
+    write_parquet_table(
+        &dir.join("lines.parquet"),
+        "lines",
+        vec![
+            ("address", Column::Int64(lines.iter().map(|line| line.address as i64).collect())),
+            ("size", Column::Int64(lines.iter().map(|line| line.size as i64).collect())),
+            ("line", Column::Int64(lines.iter().map(|line| line.line_number).collect())),
+            ("file_id", Column::Int64(lines.iter().map(|line| line.source_file_id).collect())),
+        ],
+    )?;
+
+    let publics: Vec<_> = symbol_file.public_symbols.values().collect();
+    write_parquet_table(
+        &dir.join("publics.parquet"),
+        "publics",
+        vec![
+            ("address", Column::Int64(publics.iter().map(|public| public.address as i64).collect())),
+            ("stack_param_size", Column::Int64(publics.iter().map(|public| public.stack_param_size).collect())),
+            ("name", utf8(publics.iter().map(|public| public.name.as_str()))),
+            ("multiple", Column::Bool(publics.iter().map(|public| public.is_multiple).collect())),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolFileBuilder;
+
+    fn test_symbol_file() -> SymbolFile {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("mac", "arm64", "0102030405060708090A0B0C0D0E0F100", "UnityFramework")
+            .add_file(1, "Player.cpp")
+            .add_function(0x100, 0x10, 0, "PlayerLoop")
+            .add_function(0x200, 0x40, 0, "PlayerRender")
+            .add_line(0x100, 0x10, 12, 1)
+            .add_public(0x300, 0, "_main");
+        builder.build()
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_export_sqlite() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_export_{}.db", std::process::id()));
+        export_sqlite(&test_symbol_file(), &path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let largest: (String, i64) = connection
+            .query_row("SELECT name, size FROM functions ORDER BY size DESC LIMIT 1", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(largest, ("PlayerRender".to_string(), 0x40));
+        let file: String = connection
+            .query_row("SELECT files.name FROM lines JOIN files ON lines.file_id = files.id WHERE lines.address = 256", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(file, "Player.cpp");
+        let module: String = connection.query_row("SELECT name FROM module", [], |row| row.get(0)).unwrap();
+        assert_eq!(module, "UnityFramework");
+        let publics: i64 = connection.query_row("SELECT COUNT(*) FROM publics", [], |row| row.get(0)).unwrap();
+        assert_eq!(publics, 1);
+
+        drop(connection);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_export_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let dir = std::env::temp_dir().join(format!("addr2line_breakpad_parquet_{}", std::process::id()));
+        export_parquet(&test_symbol_file(), &dir).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(dir.join("functions.parquet")).unwrap()).unwrap();
+        let rows: Vec<(i64, i64, String)> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (row.get_long(0).unwrap(), row.get_long(1).unwrap(), row.get_string(3).unwrap().clone())
+            })
+            .collect();
+        assert_eq!(rows, vec![(0x100, 0x10, "PlayerLoop".to_string()), (0x200, 0x40, "PlayerRender".to_string())]);
+        let reader = SerializedFileReader::new(std::fs::File::open(dir.join("module.parquet")).unwrap()).unwrap();
+        let row = reader.get_row_iter(None).unwrap().next().unwrap().unwrap();
+        assert_eq!(row.get_string(3).unwrap(), "UnityFramework");
+        for table in ["files", "lines", "publics"] {
+            let reader = SerializedFileReader::new(std::fs::File::open(dir.join(format!("{}.parquet", table))).unwrap()).unwrap();
+            assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
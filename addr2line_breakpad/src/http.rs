@@ -0,0 +1,409 @@
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::logging::{info, warn};
+
+use crate::cache::{parse_size, store};
+use crate::observer;
+
+// Limits of symbol downloads, so a dead server can't hang a batch. Read from
+// ADDR2LINE_BREAKPAD_CONNECT_TIMEOUT, ADDR2LINE_BREAKPAD_READ_TIMEOUT and
+// ADDR2LINE_BREAKPAD_DOWNLOAD_TIMEOUT (seconds), ADDR2LINE_BREAKPAD_RETRIES
+// and ADDR2LINE_BREAKPAD_MAX_DOWNLOAD_SIZE (e.g. 2G, 0 for no cap).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FetchSettings {
+    pub connect_timeout: Duration,
+    // Until the server starts answering.
+    pub read_timeout: Duration,
+    // For the whole body.
+    pub download_timeout: Duration,
+    pub retries: u32,
+    // Doubled after every retry.
+    pub backoff: Duration,
+    pub max_size: Option<u64>,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        FetchSettings {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(60),
+            download_timeout: Duration::from_secs(30 * 60),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            max_size: Some(8 << 30),
+        }
+    }
+}
+
+impl FetchSettings {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut settings = FetchSettings::default();
+        let seconds = |name| var(name).and_then(|value| value.trim().parse().ok()).map(Duration::from_secs);
+        if let Some(timeout) = seconds("ADDR2LINE_BREAKPAD_CONNECT_TIMEOUT") {
+            settings.connect_timeout = timeout;
+        }
+        if let Some(timeout) = seconds("ADDR2LINE_BREAKPAD_READ_TIMEOUT") {
+            settings.read_timeout = timeout;
+        }
+        if let Some(timeout) = seconds("ADDR2LINE_BREAKPAD_DOWNLOAD_TIMEOUT") {
+            settings.download_timeout = timeout;
+        }
+        if let Some(retries) = var("ADDR2LINE_BREAKPAD_RETRIES").and_then(|retries| retries.trim().parse().ok()) {
+            settings.retries = retries;
+        }
+        if let Some(size) = var("ADDR2LINE_BREAKPAD_MAX_DOWNLOAD_SIZE").and_then(|size| parse_size(&size)) {
+            settings.max_size = Some(size).filter(|size| *size > 0);
+        }
+        settings
+    }
+
+    fn agent(&self) -> ureq::Agent {
+        ureq::Agent::config_builder()
+            .timeout_connect(Some(self.connect_timeout))
+            .timeout_recv_response(Some(self.read_timeout))
+            .timeout_recv_body(Some(self.download_timeout))
+            .build()
+            .new_agent()
+    }
+}
+
+/// Credentials for a private symbol server.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub bearer_token: Option<String>,
+    /// Username and password, used if there is no token.
+    pub basic: Option<(String, String)>,
+    /// Sent with every request, e.g. an SSO proxy's session header.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Credentials {
+    /// From ADDR2LINE_BREAKPAD_TOKEN, ADDR2LINE_BREAKPAD_USERNAME plus
+    /// ADDR2LINE_BREAKPAD_PASSWORD, and ADDR2LINE_BREAKPAD_HEADERS (`;`
+    /// separated `Name: value` pairs). Used for http(s) symbol stores only,
+    /// so the token isn't sent to other servers.
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let credentials = Credentials {
+            bearer_token: var("ADDR2LINE_BREAKPAD_TOKEN").filter(|token| !token.is_empty()),
+            basic: var("ADDR2LINE_BREAKPAD_USERNAME").map(|username| (username, var("ADDR2LINE_BREAKPAD_PASSWORD").unwrap_or_default())),
+            headers: var("ADDR2LINE_BREAKPAD_HEADERS").map(|headers| parse_headers(&headers)).unwrap_or_default(),
+        };
+        Some(credentials).filter(|credentials| *credentials != Credentials::default())
+    }
+
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = vec![];
+        if let Some(token) = &self.bearer_token {
+            headers.push((String::from("authorization"), format!("Bearer {}", token)));
+        } else if let Some((username, password)) = &self.basic {
+            headers.push((String::from("authorization"), format!("Basic {}", base64(format!("{}:{}", username, password).as_bytes()))));
+        }
+        headers.extend(self.headers.iter().cloned());
+        headers
+    }
+}
+
+fn parse_headers(headers: &str) -> Vec<(String, String)> {
+    headers
+        .split(';')
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// By URL prefix, e.g. from the config file's `server_credentials`.
+static CREDENTIALS: RwLock<Vec<(String, Credentials)>> = RwLock::new(vec![]);
+
+/// Sends `credentials` with every download whose URL starts with
+/// `url_prefix`, symbol stores and debuginfod servers alike, replacing any
+/// other authorization. The longest matching prefix wins.
+pub fn set_credentials(url_prefix: &str, credentials: Credentials) {
+    let mut registered = CREDENTIALS.write().unwrap();
+    registered.retain(|(prefix, _)| prefix != url_prefix);
+    registered.push((url_prefix.to_string(), credentials));
+}
+
+fn with_credentials(url: &str, headers: &[(String, String)]) -> Vec<(String, String)> {
+    let registered = CREDENTIALS.read().unwrap();
+    let credentials = registered.iter().filter(|(prefix, _)| url.starts_with(prefix.as_str())).max_by_key(|(prefix, _)| prefix.len());
+    let mut headers = headers.to_vec();
+    if let Some((_, credentials)) = credentials {
+        let extra = credentials.headers();
+        headers.retain(|(name, _)| !extra.iter().any(|(extra_name, _)| extra_name.eq_ignore_ascii_case(name)));
+        headers.extend(extra);
+    }
+    headers
+}
+
+// A failed attempt, and whether another one might succeed.
+struct Failure {
+    error: io::Error,
+    retry: bool,
+}
+
+fn request_failure(err: ureq::Error) -> Failure {
+    // Client errors, like a 404 for a module the server has no symbols for,
+    // won't go away.
+    let retry = match &err {
+        ureq::Error::StatusCode(status) => *status >= 500 || *status == 429,
+        _ => true,
+    };
+    let kind = match &err {
+        ureq::Error::StatusCode(_) => io::ErrorKind::NotFound,
+        ureq::Error::Timeout(_) => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::Other,
+    };
+    Failure {
+        error: io::Error::new(kind, err.to_string()),
+        retry,
+    }
+}
+
+fn too_large(url: &str, max_size: u64) -> Failure {
+    Failure {
+        error: io::Error::new(io::ErrorKind::InvalidData, format!("{} is larger than the {} byte download cap", url, max_size)),
+        retry: false,
+    }
+}
+
+fn download_once(agent: &ureq::Agent, url: &str, headers: &[(String, String)], settings: &FetchSettings, cache_dir: &Path, cached: &Path) -> Result<PathBuf, Failure> {
+    let mut request = agent.get(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request.call().map_err(request_failure)?;
+    let max_size = settings.max_size.unwrap_or(u64::MAX);
+    if response.body().content_length().is_some_and(|length| length > max_size) {
+        return Err(too_large(url, max_size));
+    }
+    let mut reader = response.into_body().into_with_config().limit(max_size).reader();
+    store(cache_dir, cached, &mut reader).map_err(|error| {
+        let exceeded = error.get_ref().and_then(|inner| inner.downcast_ref::<ureq::Error>()).is_some_and(|inner| matches!(inner, ureq::Error::BodyExceedsLimit(_)));
+        if exceeded {
+            too_large(url, max_size)
+        } else {
+            Failure { error, retry: true }
+        }
+    })
+}
+
+// Downloads `url` into the cache as `cached`, retrying timeouts, broken
+// connections and server errors with backoff.
+pub(crate) fn download(url: &str, headers: &[(String, String)], cache_dir: &Path, cached: &Path) -> io::Result<PathBuf> {
+    download_with_settings(url, headers, cache_dir, cached, &FetchSettings::from_env())
+}
+
+fn download_with_settings(url: &str, headers: &[(String, String)], cache_dir: &Path, cached: &Path, settings: &FetchSettings) -> io::Result<PathBuf> {
+    let agent = settings.agent();
+    let headers = with_credentials(url, headers);
+    with_retries(url, settings, || download_once(&agent, url, &headers, settings, cache_dir, cached))
+}
+
+// Runs `attempt` until it succeeds or fails for good, reporting each try.
+fn with_retries<T>(url: &str, settings: &FetchSettings, mut attempt: impl FnMut() -> Result<T, Failure>) -> io::Result<T> {
+    // Query strings may carry API keys, keep them out of logs and metrics.
+    let url = url.split_once('?').map_or(url, |(url, _)| url);
+    let mut backoff = settings.backoff;
+    for tries in 0.. {
+        info!("fetching {}", url);
+        let started = observer::start();
+        let result = attempt();
+        observer::observe(started, |observer, duration| observer.downloaded(url, duration, result.is_ok()));
+        match result {
+            Ok(value) => return Ok(value),
+            Err(failure) if failure.retry && tries < settings.retries => {
+                warn!("fetching {} failed: {}, retrying in {:?}", url, failure.error, backoff);
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            Err(failure) => return Err(failure.error),
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(any(feature = "appcenter", feature = "upload"))]
+fn parse_json(url: &str, text: &str) -> io::Result<serde_json::Value> {
+    serde_json::from_str(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", url, err)))
+}
+
+// A small JSON API response, not cached.
+#[cfg(any(feature = "appcenter", feature = "upload"))]
+pub(crate) fn get_json(url: &str, headers: &[(String, String)]) -> io::Result<serde_json::Value> {
+    let settings = FetchSettings::from_env();
+    let agent = settings.agent();
+    let headers = with_credentials(url, headers);
+    let text = with_retries(url, &settings, || {
+        let mut request = agent.get(url);
+        for (name, value) in &headers {
+            request = request.header(name, value);
+        }
+        let mut response = request.call().map_err(request_failure)?;
+        response.body_mut().read_to_string().map_err(request_failure)
+    })?;
+    parse_json(url, &text)
+}
+
+// Posts `body` as JSON to an API and returns its JSON response.
+#[cfg(feature = "upload")]
+pub(crate) fn post_json(url: &str, headers: &[(String, String)], body: &serde_json::Value) -> io::Result<serde_json::Value> {
+    let settings = FetchSettings::from_env();
+    let agent = settings.agent();
+    let headers = with_credentials(url, headers);
+    let body = body.to_string();
+    let text = with_retries(url, &settings, || {
+        let mut request = agent.post(url).header("Content-Type", "application/json");
+        for (name, value) in &headers {
+            request = request.header(name, value);
+        }
+        let mut response = request.send(body.as_str()).map_err(request_failure)?;
+        response.body_mut().read_to_string().map_err(request_failure)
+    })?;
+    parse_json(url, &text)
+}
+
+// Uploads `data` with a PUT, e.g. to a signed storage URL.
+#[cfg(feature = "upload")]
+pub(crate) fn put(url: &str, headers: &[(String, String)], data: &[u8]) -> io::Result<()> {
+    let settings = FetchSettings::from_env();
+    let agent = settings.agent();
+    let headers = with_credentials(url, headers);
+    with_retries(url, &settings, || {
+        let mut request = agent.put(url);
+        for (name, value) in &headers {
+            request = request.header(name, value);
+        }
+        request.send(data).map(|_| ()).map_err(request_failure)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    // Answers each connection with the next of `responses`.
+    fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/libfoo.sym", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_download_retries() {
+        let cache_dir = env::temp_dir().join(format!("addr2line_breakpad_http_{}", std::process::id()));
+        let cached = cache_dir.join("libfoo.sym");
+        let settings = FetchSettings {
+            backoff: Duration::from_millis(1),
+            max_size: Some(64),
+            ..FetchSettings::default()
+        };
+
+        let url = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nMODULE",
+        ]);
+        let path = download_with_settings(&url, &[], &cache_dir, &cached, &settings).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "MODULE");
+
+        // Neither a missing file nor one over the cap is asked for again.
+        let url = serve(vec!["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"]);
+        let err = download_with_settings(&url, &[], &cache_dir, &cached, &settings).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let url = serve(vec!["HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n"]);
+        let err = download_with_settings(&url, &[], &cache_dir, &cached, &settings).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let settings = FetchSettings { retries: 1, ..settings };
+        let url = serve(vec!["HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n"; 2]);
+        assert!(download_with_settings(&url, &[], &cache_dir, &cached, &settings).is_err());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_credentials() {
+        assert_eq!(base64(b"ci:s3cret"), "Y2k6czNjcmV0");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(Credentials::from_vars(|_| None), None);
+
+        let credentials = Credentials::from_vars(|name| match name {
+            "ADDR2LINE_BREAKPAD_USERNAME" => Some(String::from("ci")),
+            "ADDR2LINE_BREAKPAD_PASSWORD" => Some(String::from("s3cret")),
+            "ADDR2LINE_BREAKPAD_HEADERS" => Some(String::from("X-Team: mobile; Cookie: sso=abc")),
+            _ => None,
+        })
+        .unwrap();
+        let header = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            credentials.headers(),
+            vec![header("authorization", "Basic Y2k6czNjcmV0"), header("X-Team", "mobile"), header("Cookie", "sso=abc")]
+        );
+
+        let token = Credentials {
+            bearer_token: Some(String::from("t0ken")),
+            ..Credentials::default()
+        };
+        set_credentials("https://symbols.test/private/", token);
+        let signed = vec![header("Authorization", "AWS4-HMAC-SHA256 ..."), header("x-amz-date", "20130524T000000Z")];
+        assert_eq!(
+            with_credentials("https://symbols.test/private/a.sym", &signed),
+            vec![header("x-amz-date", "20130524T000000Z"), header("authorization", "Bearer t0ken")]
+        );
+        assert_eq!(with_credentials("https://symbols.test/public/a.sym", &signed), signed);
+    }
+
+    #[test]
+    fn test_fetch_settings_from_vars() {
+        let settings = FetchSettings::from_vars(|name| match name {
+            "ADDR2LINE_BREAKPAD_CONNECT_TIMEOUT" => Some(String::from("3")),
+            "ADDR2LINE_BREAKPAD_RETRIES" => Some(String::from("0")),
+            "ADDR2LINE_BREAKPAD_MAX_DOWNLOAD_SIZE" => Some(String::from("0")),
+            _ => None,
+        });
+        assert_eq!(settings.connect_timeout, Duration::from_secs(3));
+        assert_eq!(settings.read_timeout, FetchSettings::default().read_timeout);
+        assert_eq!((settings.retries, settings.max_size), (0, None));
+    }
+}
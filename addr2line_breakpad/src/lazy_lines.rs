@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+#[cfg(not(feature = "mmap"))]
+use std::sync::Mutex;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use crate::logging::warn;
+
+use crate::{archive, is_symcache, lossy_line, observer, parse_line_line, Line, ParseError, Parser, ParserOptions, SymbolFile, UnparsedRecords, GZIP_MAGIC, PROGRESS_INTERVAL, ZSTD_MAGIC};
+
+// The symbol file that LINE records are read back from: mapped into memory
+// with the `mmap` feature, read from the file on each lookup without.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+struct LineSource(Mmap);
+
+#[cfg(not(feature = "mmap"))]
+#[derive(Debug)]
+struct LineSource(Mutex<File>);
+
+impl LineSource {
+    #[cfg(feature = "mmap")]
+    fn open(file: &File) -> io::Result<LineSource> {
+        // SAFETY: the map is only read. Symbol files are not expected to
+        // change while loaded; if one is truncated meanwhile, lookups may
+        // fault.
+        Ok(LineSource(unsafe { Mmap::map(file)? }))
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn open(file: &File) -> io::Result<LineSource> {
+        Ok(LineSource(Mutex::new(file.try_clone()?)))
+    }
+
+    #[cfg(feature = "mmap")]
+    fn read(&self, range: Range<usize>) -> io::Result<Cow<'_, [u8]>> {
+        Ok(Cow::Borrowed(&self.0[range]))
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn read(&self, range: Range<usize>) -> io::Result<Cow<'_, [u8]>> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(io::SeekFrom::Start(range.start as u64))?;
+        let mut block = vec![0; range.len()];
+        file.read_exact(&mut block)?;
+        Ok(Cow::Owned(block))
+    }
+}
+
+// The LINE records of a symbol file parsed with `ParserOptions::low_memory`:
+// the file and, for each FUNC, where the LINE records following it are.
+// Lookups parse them again, a few dozen lines per FUNC, instead of the LINE
+// table holding them all, ten times the FUNC table in large modules.
+#[derive(Debug)]
+pub(crate) struct LineIndex {
+    data: Arc<LineSource>,
+    functions: HashMap<u64, Range<usize>>,
+    // FILE ids folded into another by `SymbolFile::dedup_files`.
+    pub(crate) file_aliases: HashMap<i64, i64>,
+}
+
+impl LineIndex {
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.functions.capacity() * std::mem::size_of::<(u64, Range<usize>)>()
+    }
+
+    // The LINE records after the FUNC at `function_address`, in file order.
+    // Malformed ones are left out, as a lenient parse would.
+    pub(crate) fn lines(&self, function_address: u64) -> Vec<Line> {
+        let block = match self.functions.get(&function_address).map(|block| self.data.read(block.clone())) {
+            Some(Ok(block)) => block,
+            Some(Err(err)) => {
+                warn!("can not read LINE records of FUNC {:#x} back: {}", function_address, err);
+                return vec![];
+            }
+            None => return vec![],
+        };
+        block
+            .split(|&byte| byte == b'\n')
+            .filter_map(|line| std::str::from_utf8(line).ok())
+            .filter_map(|line| parse_line_line(line).ok())
+            .map(|mut line| {
+                line.source_file_id = *self.file_aliases.get(&line.source_file_id).unwrap_or(&line.source_file_id);
+                line
+            })
+            .collect()
+    }
+
+    // The LINE record of that FUNC containing `address`, picked the way
+    // `RangeMap::get` picks from the LINE table.
+    pub(crate) fn line_at(&self, function_address: u64, address: u64) -> Option<Line> {
+        let mut nearest: Option<Line> = None;
+        for line in self.lines(function_address) {
+            if line.address <= address && nearest.as_ref().is_none_or(|nearest| line.address >= nearest.address) {
+                nearest = Some(line);
+            }
+        }
+        nearest.filter(|line| address - line.address <= line.size)
+    }
+}
+
+// Parser state of a low memory parse: the FUNC whose LINE records are being
+// skipped and where they are.
+#[derive(Debug)]
+pub(crate) struct LineBlocks {
+    data: Arc<LineSource>,
+    functions: HashMap<u64, Range<usize>>,
+    // The end of the line being parsed, past its `\n`.
+    line_end: usize,
+    open: Option<(u64, Range<usize>)>,
+}
+
+impl LineBlocks {
+    // Whether the LINE record `line` belongs to the FUNC before it and was
+    // taken into its block. INLINE records, which dump_syms writes between a
+    // FUNC and its LINE records, are parsed but leave the block open; any
+    // other record closes it.
+    pub(crate) fn skip_line(&mut self, line: &str) -> bool {
+        if let Some((_, block)) = &mut self.open {
+            if is_line_record(line) {
+                block.end = self.line_end;
+                return true;
+            }
+            if line.starts_with("INLINE ") {
+                return false;
+            }
+        }
+        self.close();
+        false
+    }
+
+    pub(crate) fn open(&mut self, function_address: u64) {
+        self.open = Some((function_address, self.line_end..self.line_end));
+    }
+
+    fn close(&mut self) {
+        if let Some((function_address, block)) = self.open.take() {
+            if !block.is_empty() {
+                self.functions.insert(function_address, block);
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> LineIndex {
+        self.close();
+        self.functions.shrink_to_fit();
+        LineIndex {
+            data: self.data,
+            functions: self.functions,
+            file_aliases: HashMap::new(),
+        }
+    }
+}
+
+// What `parse_record` takes for a LINE record, without parsing it.
+fn is_line_record(line: &str) -> bool {
+    line.starts_with(|c: char| c.is_ascii_hexdigit()) && !line.starts_with("FILE ") && !line.starts_with("FUNC ")
+}
+
+// `path` opened, if it is a plain symbol file rather than a compressed one,
+// an archive or a SymCache.
+pub(crate) fn open_plain_file(path: &Path) -> io::Result<Option<File>> {
+    if archive::split_archive_path(path).1.is_some() {
+        return Ok(None);
+    }
+    let mut file = File::open(path)?;
+    let mut magic = Vec::with_capacity(4);
+    (&file).take(4).read_to_end(&mut magic)?;
+    if magic.is_empty() || magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) || magic.starts_with(archive::ZIP_MAGIC) || is_symcache(&magic) {
+        return Ok(None);
+    }
+    file.rewind()?;
+    Ok(Some(file))
+}
+
+pub(crate) fn parse_plain_file(file: File, filename: &Path, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    let started = observer::start();
+    let result = parse_file_lines(file, &filename.display().to_string(), options);
+    observer::observe(started, |observer, duration| observer.parsed(&filename.display().to_string(), duration, result.is_ok()));
+    result
+}
+
+fn parse_file_lines(file: File, filename: &str, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    let data = Arc::new(LineSource::open(&file)?);
+    let total = file.metadata()?.len();
+    let mut reported: u64 = 0;
+
+    let mut parser = Parser::new(filename.to_string(), options.mode);
+    if options.keep_unparsed_records {
+        parser.symbol_file.unparsed = Some(UnparsedRecords::default());
+    }
+    parser.line_blocks = Some(LineBlocks {
+        data: Arc::clone(&data),
+        functions: HashMap::new(),
+        line_end: 0,
+        open: None,
+    });
+    let mut reader = BufReader::new(file);
+    let mut end = 0;
+    let mut line_number = 0;
+    loop {
+        let mut buf = vec![];
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        end += read;
+        if let Some(blocks) = &mut parser.line_blocks {
+            blocks.line_end = end;
+        }
+        let (line, lossy) = lossy_line(buf);
+        parser.add_line(line_number, line, lossy)?;
+        if let Some(progress) = &options.progress {
+            if end as u64 - reported >= PROGRESS_INTERVAL {
+                progress(end as u64, Some(total));
+                reported = end as u64;
+            }
+        }
+        line_number += 1;
+    }
+    let symbol_file = parser.finish(options.unknown_file_placeholder.as_deref());
+
+    if let Some(progress) = &options.progress {
+        progress(total, Some(total));
+    }
+    Ok(symbol_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lookup_address, lookup_addresses, lookup_range, parse_breakpad_symbol_source_with_options, ParserOptions, SizeGrouping, WriteOptions};
+
+    #[test]
+    fn test_low_memory_lookups() {
+        // FILE 2 repeats FILE 0, a LINE record before any FUNC, CRLF endings.
+        let text = "MODULE Linux arm64 0123456789ABCDEF0123456789ABCDEF0 libfoo.so\r\n\
+                    FILE 0 a.cpp\r\nFILE 1 b.cpp\r\nFILE 2 a.cpp\r\n\
+                    2 4 1 1\r\n\
+                    FUNC 10 20 0 f\r\n10 8 3 0\r\n18 8 4 2\r\n20 10 5 1\r\n\
+                    FUNC 40 10 0 g\r\n\
+                    FUNC m 50 10 0 h\r\n50 10 9 1\r\n\
+                    PUBLIC 80 0 p\r\n";
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_low_memory_{}.sym", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let options = ParserOptions {
+            low_memory: true,
+            ..ParserOptions::default()
+        };
+        let low_memory = parse_breakpad_symbol_source_with_options(&path, None, &options).unwrap();
+        let resident = parse_breakpad_symbol_source_with_options(&path, None, &ParserOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(low_memory.diagnostics().is_empty());
+        assert_eq!(low_memory.lines.len(), 1);
+        assert_eq!(low_memory.line_index.as_ref().unwrap().functions.len(), 2);
+        assert_eq!(low_memory.duplicate_files(), 1);
+
+        let addresses: Vec<u64> = (0..0x90).collect();
+        for &address in &addresses {
+            assert_eq!(lookup_address(&low_memory, address), lookup_address(&resident, address), "{:#x}", address);
+            assert_eq!(low_memory.lookup_line(address), resident.lookup_line(address), "{:#x}", address);
+        }
+        assert_eq!(lookup_addresses(&low_memory, &addresses), lookup_addresses(&resident, &addresses));
+        let source_files = |symbol_file| lookup_range(symbol_file, 0, 0x90).into_iter().map(|function| function.source_file_name).collect::<Vec<_>>();
+        assert_eq!(source_files(&low_memory), source_files(&resident));
+        assert_eq!(low_memory.lines_for_function(0x18), resident.lines_for_function(0x18));
+        assert_eq!(lookup_address(&low_memory, 0x1c).unwrap().source_file_name, "a.cpp");
+    }
+
+    #[test]
+    fn test_low_memory_inline_records_and_writes() {
+        // dump_syms puts INLINE records between a FUNC and its LINE records.
+        let text = "MODULE Linux arm64 0123456789ABCDEF0123456789ABCDEF0 libfoo.so\n\
+                    FILE 0 a.cpp\nFILE 1 b.cpp\nFILE 2 a.cpp\n\
+                    INLINE_ORIGIN 0 inlined()\n\
+                    FUNC 10 20 0 f\nINLINE 0 12 2 0 14 4\n10 8 3 0\n18 8 4 2\n20 10 5 1\n\
+                    FUNC 40 10 0 g\n40 10 6 1\n\
+                    PUBLIC 80 0 p\n\
+                    STACK CFI INIT 10 20 .cfa: sp 0 +\n";
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_low_memory_inline_{}.sym", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let options = |low_memory| ParserOptions {
+            low_memory,
+            keep_unparsed_records: true,
+            ..ParserOptions::default()
+        };
+        let low_memory = parse_breakpad_symbol_source_with_options(&path, None, &options(true)).unwrap();
+        let resident = parse_breakpad_symbol_source_with_options(&path, None, &options(false)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(low_memory.diagnostics().is_empty());
+        assert!(low_memory.lines.is_empty());
+        assert_eq!(low_memory.line_index.as_ref().unwrap().functions.len(), 2);
+        assert_eq!(lookup_address(&low_memory, 0x1c), lookup_address(&resident, 0x1c));
+
+        let write = |symbol_file: &crate::SymbolFile, compact_files| {
+            let mut out = Vec::new();
+            symbol_file.write_breakpad_with_options(&mut out, &WriteOptions { compact_files }).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(write(&low_memory, false), write(&resident, false));
+        assert_eq!(write(&low_memory, true), write(&resident, true));
+        assert!(write(&low_memory, true).contains("FUNC 40 10 0 g\n40 10 6 1\n"));
+        let mut low_memory_symcache = Vec::new();
+        let mut resident_symcache = Vec::new();
+        low_memory.write_symcache(&mut low_memory_symcache).unwrap();
+        resident.write_symcache(&mut resident_symcache).unwrap();
+        assert_eq!(low_memory_symcache, resident_symcache);
+        assert_eq!(low_memory.function_sizes(SizeGrouping::File), resident.function_sizes(SizeGrouping::File));
+    }
+}
@@ -1,11 +1,94 @@
-use std::fs::File;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::prelude::*;
-use std::io::BufReader;
+use std::io::{self, BufReader, Cursor};
+use std::mem;
 use std::ops::Bound::Included;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug)]
+use lazy_lines::{LineBlocks, LineIndex};
+use range_map::NearestCursor;
+
+use logging::{debug, info, trace, warn};
+
+#[cfg(feature = "appcenter")]
+mod appcenter;
+mod archive;
+#[cfg(feature = "async")]
+mod async_load;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "remote")]
+mod cache;
+#[cfg(feature = "config")]
+mod config;
+mod coverage;
+#[cfg(feature = "debuginfod")]
+mod debuginfod;
+mod diff;
+#[cfg(feature = "remote")]
+mod http;
+mod lazy_lines;
+mod lint;
+mod logging;
+#[cfg(any(feature = "sqlite", feature = "parquet"))]
+mod export;
+mod microdump;
+mod names;
+mod observer;
+pub mod range_map;
+#[cfg(feature = "remote")]
+mod remote;
+mod sizes;
+mod source;
+mod source_bundle;
+mod symbol_format;
+mod symcache;
+mod symbolicator;
+#[cfg(feature = "upload")]
+mod upload;
+
+#[cfg(feature = "appcenter")]
+pub use appcenter::{fetch_appcenter_symbols, parse_appcenter_source, AppCenterSource};
+#[cfg(all(feature = "async", feature = "remote"))]
+pub use async_load::fetch_symbol_file_async;
+#[cfg(feature = "remote")]
+pub use cache::{cache_size_limit, clean_cache, parse_size, set_cache_size_limit, CleanStats};
+#[cfg(feature = "config")]
+pub use config::{default_config_path, symbol_path_from_env, Config, OutputConfig, ServerCredentials, SimplifyNamesConfig};
+pub use coverage::{Coverage, Gap};
+#[cfg(feature = "debuginfod")]
+pub use debuginfod::{debuginfod_urls, fetch_debuginfo, parse_debuginfod_source, DwarfModule};
+pub use diff::{diff_symbol_files, FunctionChange, FunctionEntry, SymbolDiff};
+pub use lint::{lint_symbol_file, lint_symbol_reader, LintIssue, LintKind};
+#[cfg(feature = "parquet")]
+pub use export::export_parquet;
+#[cfg(feature = "sqlite")]
+pub use export::export_sqlite;
+pub use microdump::{parse_microdump, FrameTrust, Microdump, MicrodumpFrame, MicrodumpModule, MICRODUMP_BEGIN, MICRODUMP_END};
+pub use names::{strip_parameters, strip_template_arguments, NameSimplification};
+pub use observer::{set_observer, SymbolicationObserver};
+pub use range_map::{RangeCursor, RangeMap};
+#[cfg(feature = "remote")]
+pub use http::{set_credentials, Credentials};
+#[cfg(feature = "remote")]
+pub use remote::{default_cache_dir, fetch_symbol_file, is_remote_source, RemoteSource};
+pub use sizes::{FunctionSizes, SizeBucket, SizeEntry, SizeGrouping};
+pub use source::{find_source_file, read_source_snippet, source_snippet, SourceMap};
+pub use source_bundle::SourceBundle;
+pub use symbol_format::SymbolFormat;
+pub use symcache::{is_symcache, parse_symcache};
+#[cfg(feature = "remote")]
+pub use symbolicator::RemoteSymbolicator;
+pub use symbolicator::{CacheStats, CachingSymbolicator, LookupStats, Module, ModuleId, ModuleSet, Symbolicator};
+#[cfg(feature = "upload")]
+pub use upload::{upload_symbols, UploadStatus};
+
+#[derive(Debug, Clone)]
 struct Line {
     address: u64,
     size: u64,
@@ -30,241 +113,1758 @@ struct PublicSymbol {
     is_multiple: bool,
 }
 
+// Records lookups don't use, kept verbatim with
+// `ParserOptions::keep_unparsed_records` so that writing puts them back.
+// INLINE records are kept by the FUNC they follow.
+#[derive(Debug, Default)]
+struct UnparsedRecords {
+    info: Vec<String>,
+    inline_origins: Vec<String>,
+    inlines: HashMap<u64, Vec<String>>,
+    stack: Vec<String>,
+}
+
+impl UnparsedRecords {
+    fn len(&self) -> usize {
+        self.info.len() + self.inline_origins.len() + self.inlines.values().map(Vec::len).sum::<usize>() + self.stack.len()
+    }
+
+    fn memory_usage(&self) -> usize {
+        let inlines = self.inlines.values().flatten();
+        self.info.iter().chain(&self.inline_origins).chain(inlines).chain(&self.stack).map(String::capacity).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnparsedKind {
+    Info,
+    InlineOrigin,
+    Inline,
+    Stack,
+}
+
+fn unparsed_kind(line: &str) -> Option<UnparsedKind> {
+    if line.starts_with("STACK ") {
+        Some(UnparsedKind::Stack)
+    } else if line.starts_with("INLINE ") {
+        Some(UnparsedKind::Inline)
+    } else if line.starts_with("INLINE_ORIGIN ") {
+        Some(UnparsedKind::InlineOrigin)
+    } else if line.starts_with("INFO ") && !line.starts_with("INFO CODE_ID ") {
+        Some(UnparsedKind::Info)
+    } else {
+        None
+    }
+}
+
+// INLINE <nest_level> <call_site_line> <call_site_file_id> <origin_id> [<address> <size>]+
+fn inline_file_id(line: &str) -> Option<i64> {
+    line.split(' ').nth(3)?.parse().ok()
+}
+
+// The INLINE record `line` with its call site FILE id renumbered by `file_ids`.
+fn remap_inline_file_id<'a>(line: &'a str, file_ids: &HashMap<i64, i64>) -> Cow<'a, str> {
+    let new_id = match inline_file_id(line).and_then(|id| file_ids.get(&id)) {
+        Some(new_id) => new_id,
+        None => return Cow::Borrowed(line),
+    };
+    let tokens: Vec<&str> = line.splitn(5, ' ').collect();
+    let mut remapped = format!("{} {} {} {}", tokens[0], tokens[1], tokens[2], new_id);
+    if let Some(rest) = tokens.get(4) {
+        remapped.push(' ');
+        remapped.push_str(rest);
+    }
+    Cow::Owned(remapped)
+}
+
+/// What an address resolved to. Names are borrowed from the [`SymbolFile`]
+/// where they can be; [`Symbol::into_owned`] detaches them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub function_name: Cow<'a, str>,
+    /// Empty without a LINE record.
+    pub source_file_name: Cow<'a, str>,
+    pub source_file_number: i64,
+    /// The kind of record that matched.
+    pub kind: SymbolKind,
+    /// Bytes of arguments the function pops off the stack, from the FUNC or
+    /// PUBLIC record. Only non-zero for callee-cleanup conventions on x86.
+    pub stack_param_size: i64,
+    /// Start of the matched FUNC or PUBLIC record, relative to the module.
+    pub function_address: Option<u64>,
+    /// Size of the matched FUNC; PUBLIC records have none.
+    pub function_size: Option<u64>,
+}
+
+impl Symbol<'_> {
+    pub fn into_owned(self) -> Symbol<'static> {
+        Symbol {
+            function_name: Cow::Owned(self.function_name.into_owned()),
+            source_file_name: Cow::Owned(self.source_file_name.into_owned()),
+            ..self
+        }
+    }
+
+    /// How far `address` is into the matched function, for `func+offset`.
+    pub fn function_offset(&self, address: u64) -> Option<u64> {
+        address.checked_sub(self.function_address?)
+    }
+
+    /// Whether `address` is in the last `margin` bytes of the matched FUNC or
+    /// right at its end, where a call as the function's last instruction
+    /// returns to. A return address there usually means the caller may have
+    /// been tail-called away.
+    pub fn is_near_function_end(&self, address: u64, margin: u64) -> bool {
+        match (self.function_offset(address), self.function_size) {
+            (Some(offset), Some(size)) => offset <= size && size - offset <= margin,
+            _ => false,
+        }
+    }
+
+    /// Directory part of the FILE record, empty without one.
+    pub fn source_dir(&self) -> &str {
+        source_dir(&self.source_file_name)
+    }
+
+    /// File name part of the FILE record, empty without one.
+    pub fn source_basename(&self) -> &str {
+        source_basename(&self.source_file_name)
+    }
+}
+
+// `function file:line`, as both tools print frames by default.
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = if self.source_file_name.is_empty() { "??" } else { &self.source_file_name };
+        match self.source_file_number {
+            -1 => write!(f, "{} {}:?", self.function_name, file),
+            line => write!(f, "{} {}:{}", self.function_name, file, line),
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct Symbol {
+pub struct FunctionSymbol {
+    pub address: u64,
+    pub size: u64,
     pub function_name: String,
     pub source_file_name: String,
-    pub source_file_number: i64,
 }
 
-#[derive(Debug)]
-pub struct SymbolFile {
-    files: HashMap<i64, String>,
-    functions: RangeMap<Function>,
-    lines: RangeMap<Line>,
-    public_symbols: BTreeMap<u64, PublicSymbol>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Func,
+    Public,
+}
+
+impl SymbolKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymbolKind::Func => "FUNC",
+            SymbolKind::Public => "PUBLIC",
+        }
+    }
+}
+
+// A FUNC or PUBLIC record; PUBLIC records carry no size and report 0.
+#[derive(Debug)]
+pub struct SymbolRecord {
+    pub address: u64,
+    pub size: u64,
+    pub kind: SymbolKind,
+    pub name: String,
+}
+
+/// The FUNC or PUBLIC record an address is in, borrowed from its
+/// [`SymbolFile`], from [`SymbolFile::lookup_function`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionRef<'a> {
+    pub name: &'a str,
+    pub address: u64,
+    /// PUBLIC records have none.
+    pub size: Option<u64>,
+    pub kind: SymbolKind,
+    pub stack_param_size: i64,
+}
+
+/// The LINE record an address is in, from [`SymbolFile::lookup_line`]. The
+/// file name is only allocated for the placeholder of an undefined FILE id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRef<'a> {
+    pub address: u64,
+    pub size: u64,
+    pub line_number: i64,
+    pub source_file_name: Cow<'a, str>,
+}
+
+// A LINE record with its FILE resolved, the placeholder if the id has no FILE
+// record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRecord {
+    pub address: u64,
+    pub size: u64,
+    pub line_number: i64,
+    pub source_file_name: String,
+}
+
+// MODULE <os> <arch> <debug_id> <name>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleRecord {
+    pub os: String,
+    pub arch: String,
+    pub debug_id: String,
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct SymbolFile {
+    module: Option<ModuleRecord>,
+    // INFO CODE_ID <code_id> [<filename>]
+    code_id: Option<String>,
+    code_file: Option<String>,
+    files: HashMap<i64, String>,
+    functions: RangeMap<Function>,
+    lines: RangeMap<Line>,
+    public_symbols: BTreeMap<u64, PublicSymbol>,
+    diagnostics: Vec<Diagnostic>,
+    // Source file name of LINE records whose FILE id is not defined, `{id}`
+    // replaced with the id.
+    unknown_file_placeholder: String,
+    undefined_file_lines: usize,
+    duplicate_files: usize,
+    // Where the LINE records are, instead of `lines`, when parsed with
+    // `ParserOptions::low_memory`.
+    line_index: Option<LineIndex>,
+    unparsed: Option<UnparsedRecords>,
+    // STACK, INLINE, INLINE_ORIGIN and INFO records parsed without keeping
+    // them, which writing would lose.
+    dropped_records: usize,
+}
+
+/// What lookups return as the source file of a LINE record whose FILE record
+/// is missing, as in truncated uploads. `{id}` is replaced with the FILE id.
+pub const DEFAULT_UNKNOWN_FILE_PLACEHOLDER: &str = "<unknown-file-id:{id}>";
+
+/// A line the parser skipped because it could not make sense of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 1-based line number in the symbol file.
+    pub line_number: usize,
+    pub line: String,
+    pub message: String,
+}
+
+impl SymbolFile {
+    fn new() -> Self {
+        SymbolFile {
+            module: None,
+            code_id: None,
+            code_file: None,
+            files: HashMap::new(),
+            functions: RangeMap::new(),
+            lines: RangeMap::new(),
+            public_symbols: BTreeMap::new(),
+            diagnostics: vec![],
+            unknown_file_placeholder: String::from(DEFAULT_UNKNOWN_FILE_PLACEHOLDER),
+            undefined_file_lines: 0,
+            duplicate_files: 0,
+            line_index: None,
+            unparsed: None,
+            dropped_records: 0,
+        }
+    }
+
+    /// One past the highest address the symbol file describes: the end of the
+    /// last FUNC, or the last PUBLIC plus some slack since PUBLIC records have
+    /// no size.
+    pub fn end_address(&self) -> Option<u64> {
+        let function_end = self.functions.end();
+        let public_end = self
+            .public_symbols
+            .last_key_value()
+            .map(|(address, _)| address.saturating_add(PUBLIC_SLACK));
+        function_end.max(public_end)
+    }
+
+    /// Whether `address` lies past everything in the symbol file, i.e. it
+    /// probably belongs to another module or was not rebased.
+    pub fn is_out_of_range(&self, address: u64) -> bool {
+        self.end_address().is_some_and(|end| address >= end)
+    }
+
+    /// `(address, stack_param_size, name)` of every PUBLIC record, by address.
+    /// System libraries often come with nothing else.
+    pub fn public_symbols(&self) -> impl DoubleEndedIterator<Item = (u64, i64, &str)> {
+        self.public_symbols
+            .values()
+            .map(|public| (public.address, public.stack_param_size, public.name.as_str()))
+    }
+
+    /// Start addresses of the FUNC records named `name`, several for static
+    /// functions of different files.
+    pub fn function_addresses(&self, name: &str) -> Vec<u64> {
+        self.functions.values().filter(|function| function.name == name).map(|function| function.address).collect()
+    }
+
+    /// LINE records inside the FUNC containing `address`, by address. Empty
+    /// if no FUNC contains it.
+    pub fn lines_for_function(&self, address: u64) -> Vec<LineRecord> {
+        let function = match self.functions.get(address) {
+            Some(function) => function,
+            None => return vec![],
+        };
+        let line_record = |line: &Line| LineRecord {
+            address: line.address,
+            size: line.size,
+            line_number: line.line_number,
+            source_file_name: self.source_file_name(line.source_file_id),
+        };
+        if let Some(index) = &self.line_index {
+            let mut lines = index.lines(function.address);
+            lines.sort_by_key(|line| line.address);
+            return lines.iter().map(line_record).collect();
+        }
+        self.lines
+            .retrieve_overlapping(function.address, function.address.saturating_add(function.size))
+            .map(|(_, _, line)| line_record(line))
+            .collect()
+    }
+
+    /// A [`LookupCursor`] for many lookups in ascending order.
+    pub fn lookup_cursor(&self) -> LookupCursor<'_> {
+        LookupCursor::new(self)
+    }
+
+    /// Approximate memory held by the symbol tables: the records and their
+    /// names, without allocator and B-tree node overhead.
+    pub fn memory_usage(&self) -> usize {
+        let functions = self.functions.len() * mem::size_of::<(u64, u64, Function)>()
+            + self.functions.values().map(|function| function.name.capacity()).sum::<usize>();
+        let lines = self.lines.len() * mem::size_of::<(u64, u64, Line)>() + self.line_index.as_ref().map_or(0, LineIndex::memory_usage);
+        let public_symbols = self.public_symbols.len() * mem::size_of::<(u64, PublicSymbol)>()
+            + self.public_symbols.values().map(|symbol| symbol.name.capacity()).sum::<usize>();
+        let files = self.files.capacity() * mem::size_of::<(i64, String)>() + self.files.values().map(String::capacity).sum::<usize>();
+        let unparsed = self.unparsed.as_ref().map_or(0, UnparsedRecords::memory_usage);
+        functions + lines + public_symbols + files + unparsed
+    }
+
+    /// Parses uncompressed symbol data held in memory, leniently. Text can
+    /// be parsed with `str::parse` as well.
+    pub fn from_bytes(data: &[u8]) -> Result<SymbolFile, ParseError> {
+        parse_symbol_reader(data, &"<memory>", None, &ParserOptions::default())
+    }
+
+    /// Parses uncompressed symbol data from a network response, an archive
+    /// entry or any other reader, leniently.
+    pub fn from_reader(reader: impl BufRead) -> Result<SymbolFile, ParseError> {
+        SymbolFile::from_reader_with_options(reader, &ParserOptions::default())
+    }
+
+    pub fn from_reader_with_options(reader: impl BufRead, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+        parse_symbol_reader(reader, &"<reader>", None, options)
+    }
+
+    /// Malformed lines skipped while parsing, in file order.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// LINE records whose FILE id no FILE record defines, counted when
+    /// parsing.
+    pub fn undefined_file_lines(&self) -> usize {
+        self.undefined_file_lines
+    }
+
+    /// FILE records dropped when parsing because an earlier one has the same
+    /// path, their LINE records now use the earlier id.
+    pub fn duplicate_files(&self) -> usize {
+        self.duplicate_files
+    }
+
+    /// `(id, path)` of every FILE record, by id. Paths are unique in parsed
+    /// files.
+    pub fn files(&self) -> impl DoubleEndedIterator<Item = (i64, &str)> {
+        let mut files: Vec<(i64, &str)> = self.files.iter().map(|(id, filename)| (*id, filename.as_str())).collect();
+        files.sort();
+        files.into_iter()
+    }
+
+    // Folds FILE records with the same path into the one with the lowest id,
+    // remapping LINE records. Returns how many were dropped. Sym files of
+    // large modules repeat headers under thousands of ids.
+    fn dedup_files(&mut self) -> usize {
+        let mut ids: Vec<i64> = self.files.keys().copied().collect();
+        ids.sort();
+        let mut first_ids: HashMap<&str, i64> = HashMap::new();
+        let mut aliases = HashMap::new();
+        for id in ids {
+            let first_id = *first_ids.entry(self.files[&id].as_str()).or_insert(id);
+            if first_id != id {
+                aliases.insert(id, first_id);
+            }
+        }
+        if aliases.is_empty() {
+            return 0;
+        }
+        for id in aliases.keys() {
+            self.files.remove(id);
+        }
+        self.files.shrink_to_fit();
+        for line in self.lines.values_mut() {
+            if let Some(id) = aliases.get(&line.source_file_id) {
+                line.source_file_id = *id;
+            }
+        }
+        if let Some(unparsed) = &mut self.unparsed {
+            for line in unparsed.inlines.values_mut().flatten() {
+                if let Cow::Owned(remapped) = remap_inline_file_id(line, &aliases) {
+                    *line = remapped;
+                }
+            }
+        }
+        let dropped = aliases.len();
+        if let Some(index) = &mut self.line_index {
+            index.file_aliases = aliases;
+        }
+        dropped
+    }
+
+    // All LINE records of a low memory file, read back from the mapped file
+    // for whole-file operations; `None` when `lines` already holds them.
+    pub(crate) fn loaded_lines(&self) -> Option<RangeMap<Line>> {
+        let index = self.line_index.as_ref()?;
+        let mut lines = RangeMap::new();
+        for (address, size, line) in self.lines.iter() {
+            lines.insert(address, size, line.clone());
+        }
+        for function in self.functions.values() {
+            for line in index.lines(function.address) {
+                lines.insert(line.address, line.size, line);
+            }
+        }
+        Some(lines)
+    }
+
+    // The FILE table of a compact write: the paths `lines` use, each once,
+    // numbered from 0 by their lowest id, and the new id of each old one.
+    // Undefined ids are numbered after them so they stay undefined.
+    fn compact_files(&self, lines: &RangeMap<Line>) -> (Vec<(i64, &str)>, HashMap<i64, i64>) {
+        let mut used: Vec<i64> = lines.values().map(|line| line.source_file_id).collect();
+        let inlines = self.unparsed.iter().flat_map(|unparsed| unparsed.inlines.values().flatten());
+        used.extend(inlines.filter_map(|line| inline_file_id(line)));
+        used.sort();
+        used.dedup();
+        let mut files: Vec<(i64, &str)> = vec![];
+        let mut new_ids: HashMap<&str, i64> = HashMap::new();
+        let mut file_ids = HashMap::new();
+        let mut undefined = vec![];
+        for id in used {
+            match self.files.get(&id) {
+                Some(filename) => {
+                    let next_id = files.len() as i64;
+                    let new_id = *new_ids.entry(filename.as_str()).or_insert_with(|| {
+                        files.push((next_id, filename));
+                        next_id
+                    });
+                    file_ids.insert(id, new_id);
+                }
+                None => undefined.push(id),
+            }
+        }
+        for (offset, id) in undefined.into_iter().enumerate() {
+            file_ids.insert(id, (files.len() + offset) as i64);
+        }
+        (files, file_ids)
+    }
+
+    /// Sets what lookups return for undefined FILE ids, see
+    /// [`DEFAULT_UNKNOWN_FILE_PLACEHOLDER`].
+    pub fn set_unknown_file_placeholder(&mut self, placeholder: &str) {
+        self.unknown_file_placeholder = placeholder.to_string();
+    }
+
+    fn source_file_name(&self, id: i64) -> String {
+        self.source_file_name_ref(id).into_owned()
+    }
+
+    fn source_file_name_ref(&self, id: i64) -> Cow<'_, str> {
+        match self.files.get(&id) {
+            Some(filename) => Cow::Borrowed(filename),
+            None => Cow::Owned(self.unknown_file_placeholder.replace("{id}", &id.to_string())),
+        }
+    }
+
+    /// Only the function part of [`lookup_address`]: the FUNC containing
+    /// `address`, otherwise the nearest PUBLIC before it, without walking the
+    /// LINE records or copying names. For callers that only need names, like
+    /// flame graphs.
+    pub fn lookup_function(&self, address: u64) -> Option<FunctionRef<'_>> {
+        if let Some(function) = self.functions.get(address) {
+            return Some(FunctionRef {
+                name: &function.name,
+                address: function.address,
+                size: Some(function.size),
+                kind: SymbolKind::Func,
+                stack_param_size: function.stack_param_size,
+            });
+        }
+        if self.is_out_of_range(address) {
+            return None;
+        }
+        find_public_symbol_by_address(&self.public_symbols, address).map(|public| FunctionRef {
+            name: &public.name,
+            address: public.address,
+            size: None,
+            kind: SymbolKind::Public,
+            stack_param_size: public.stack_param_size,
+        })
+    }
+
+    /// Only the line part of [`lookup_address`]: the LINE record containing
+    /// `address`, whether or not a FUNC does.
+    pub fn lookup_line(&self, address: u64) -> Option<LineRef<'_>> {
+        let line_ref = |line: &Line| LineRef {
+            address: line.address,
+            size: line.size,
+            line_number: line.line_number,
+            source_file_name: self.source_file_name_ref(line.source_file_id),
+        };
+        match self.lines.get(address) {
+            Some(line) => Some(line_ref(line)),
+            None => self.indexed_line(address).as_ref().map(line_ref),
+        }
+    }
+
+    // With `ParserOptions::low_memory`, the LINE record containing `address`
+    // among those of the FUNC containing it, parsed again.
+    fn indexed_line(&self, address: u64) -> Option<Line> {
+        let index = self.line_index.as_ref()?;
+        let function = self.functions.get(address)?;
+        index.line_at(function.address, address)
+    }
+
+    pub fn module(&self) -> Option<&ModuleRecord> {
+        self.module.as_ref()
+    }
+
+    /// The code id from `INFO CODE_ID`. For ELF modules this is the full GNU
+    /// build id, which is what Android crash reports print.
+    pub fn code_id(&self) -> Option<&str> {
+        self.code_id.as_deref()
+    }
+
+    /// File name of the executable from `INFO CODE_ID`, mostly set for PE.
+    pub fn code_file(&self) -> Option<&str> {
+        self.code_file.as_deref()
+    }
+
+    /// Writes the symbol file in breakpad text format. LINE records are
+    /// emitted in address order directly after the FUNC they belong to; those
+    /// before the first FUNC are dropped.
+    pub fn write_breakpad(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.write_breakpad_with_options(writer, &WriteOptions::default())
+    }
+
+    /// Fails if STACK, INLINE or INFO records were left out when parsing, see
+    /// [`ParserOptions::keep_unparsed_records`].
+    pub fn write_breakpad_with_options(&self, writer: &mut impl Write, options: &WriteOptions) -> io::Result<()> {
+        if self.dropped_records > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} STACK, INLINE or INFO record(s) were not kept when parsing, writing would lose them", self.dropped_records),
+            ));
+        }
+        let no_records = UnparsedRecords::default();
+        let unparsed = self.unparsed.as_ref().unwrap_or(&no_records);
+        if let Some(module) = &self.module {
+            writeln!(writer, "MODULE {} {} {} {}", module.os, module.arch, module.debug_id, module.name)?;
+        }
+        if let Some(code_id) = &self.code_id {
+            match &self.code_file {
+                Some(code_file) => writeln!(writer, "INFO CODE_ID {} {}", code_id, code_file)?,
+                None => writeln!(writer, "INFO CODE_ID {}", code_id)?,
+            }
+        }
+        for info in &unparsed.info {
+            writeln!(writer, "{}", info)?;
+        }
+
+        let loaded_lines = self.loaded_lines();
+        let lines = loaded_lines.as_ref().unwrap_or(&self.lines);
+        let (files, file_ids) = if options.compact_files { self.compact_files(lines) } else { (self.files().collect(), HashMap::new()) };
+        for (id, filename) in files {
+            writeln!(writer, "FILE {} {}", id, filename)?;
+        }
+        for inline_origin in &unparsed.inline_origins {
+            writeln!(writer, "{}", inline_origin)?;
+        }
+        let file_id = |line: &Line| *file_ids.get(&line.source_file_id).unwrap_or(&line.source_file_id);
+
+        let mut lines = lines.values().peekable();
+        // LINE records before the first FUNC belong to no FUNC, readers would
+        // attach them to whatever precedes them.
+        let first_function = self.functions.values().next().map(|function| function.address);
+        let mut orphans = 0;
+        while lines.next_if(|line| first_function.is_none_or(|address| line.address < address)).is_some() {
+            orphans += 1;
+        }
+        if orphans > 0 {
+            warn!("dropped {} LINE record(s) outside any FUNC", orphans);
+        }
+        for function in self.functions.values() {
+            while let Some(line) = lines.next_if(|line| line.address < function.address) {
+                write_line_record(writer, line, file_id(line))?;
+            }
+            let multiple = if function.is_multiple { "m " } else { "" };
+            writeln!(
+                writer,
+                "FUNC {}{:x} {:x} {:x} {}",
+                multiple, function.address, function.size, function.stack_param_size, function.name
+            )?;
+            for inline in unparsed.inlines.get(&function.address).into_iter().flatten() {
+                writeln!(writer, "{}", remap_inline_file_id(inline, &file_ids))?;
+            }
+        }
+        for line in lines {
+            write_line_record(writer, line, file_id(line))?;
+        }
+
+        for public_symbol in self.public_symbols.values() {
+            let multiple = if public_symbol.is_multiple { "m " } else { "" };
+            writeln!(
+                writer,
+                "PUBLIC {}{:x} {:x} {}",
+                multiple, public_symbol.address, public_symbol.stack_param_size, public_symbol.name
+            )?;
+        }
+        for stack in &unparsed.stack {
+            writeln!(writer, "{}", stack)?;
+        }
+        Ok(())
+    }
+
+    /// Merges the records of `other` into `self`.
+    ///
+    /// Records already in `self` take precedence: FUNC and LINE records of
+    /// `other` that overlap an existing range, and PUBLIC records at an
+    /// existing address, are dropped. FILE ids of `other` are remapped when
+    /// they clash with a different path in `self`; identical paths share an id.
+    /// STACK, INLINE and INFO records of `other` are dropped, so the merged
+    /// file can't be written if it had any.
+    pub fn merge(&mut self, other: SymbolFile) {
+        self.dropped_records += other.dropped_records + other.unparsed.as_ref().map_or(0, UnparsedRecords::len);
+        if self.code_id.is_none() {
+            self.code_id = other.code_id;
+            self.code_file = other.code_file;
+        }
+        let mut next_file_id = self.files.keys().max().map_or(0, |id| id + 1);
+        let mut file_ids = HashMap::new();
+        let mut other_files: Vec<(i64, String)> = other.files.into_iter().collect();
+        other_files.sort();
+        // Paths already in `self` by their lowest id, looked up once per FILE
+        // of `other` instead of scanning `self.files` for each.
+        let mut existing_ids: HashMap<String, i64> = HashMap::with_capacity(self.files.len());
+        for (id, filename) in &self.files {
+            let existing_id = existing_ids.entry(filename.clone()).or_insert(*id);
+            *existing_id = (*existing_id).min(*id);
+        }
+        for (id, filename) in other_files {
+            let new_id = match existing_ids.get(&filename) {
+                Some(existing) => *existing,
+                None if !self.files.contains_key(&id) => id,
+                None => next_file_id,
+            };
+            next_file_id = next_file_id.max(new_id + 1);
+            existing_ids.entry(filename.clone()).or_insert(new_id);
+            self.files.entry(new_id).or_insert(filename);
+            file_ids.insert(id, new_id);
+        }
+
+        for (address, size, function) in other.functions {
+            if !self.functions.overlaps(address, size) {
+                self.functions.insert(address, size, function);
+            }
+        }
+        for (address, size, mut line) in other.lines {
+            if !self.lines.overlaps(address, size) {
+                line.source_file_id = *file_ids.get(&line.source_file_id).unwrap_or(&line.source_file_id);
+                self.lines.insert(address, size, line);
+            }
+        }
+        for (address, public_symbol) in other.public_symbols {
+            self.public_symbols.entry(address).or_insert(public_symbol);
+        }
+    }
+}
+
+/// Builds a `SymbolFile` in memory, e.g. for tests or when converting from
+/// other symbol formats.
+///
+/// ```
+/// use addr2line_breakpad::{lookup_address, SymbolFileBuilder};
+///
+/// let mut builder = SymbolFileBuilder::new();
+/// builder
+///     .add_file(0, "main.cpp")
+///     .add_function(0x1000, 0x20, 0, "main")
+///     .add_line(0x1000, 0x20, 42, 0)
+///     .add_public(0x2000, 0, "_start");
+/// let symbol_file = builder.build();
+///
+/// let symbol = lookup_address(&symbol_file, 0x1004).unwrap();
+/// assert_eq!(symbol.function_name, "main");
+/// assert_eq!(symbol.source_file_name, "main.cpp");
+/// assert_eq!(symbol.source_file_number, 42);
+/// ```
+#[derive(Debug)]
+pub struct SymbolFileBuilder {
+    symbol_file: SymbolFile,
+}
+
+impl Default for SymbolFileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolFileBuilder {
+    pub fn new() -> Self {
+        SymbolFileBuilder {
+            symbol_file: SymbolFile::new(),
+        }
+    }
+
+    /// MODULE <os> <arch> <debug_id> <name>
+    pub fn set_module(&mut self, os: impl Into<String>, arch: impl Into<String>, debug_id: impl Into<String>, name: impl Into<String>) -> &mut Self {
+        self.symbol_file.module = Some(ModuleRecord {
+            os: os.into(),
+            arch: arch.into(),
+            debug_id: debug_id.into(),
+            name: name.into(),
+        });
+        self
+    }
+
+    /// INFO CODE_ID <code_id> [<filename>]
+    pub fn set_code_id(&mut self, code_id: impl Into<String>, code_file: Option<&str>) -> &mut Self {
+        self.symbol_file.code_id = Some(code_id.into());
+        self.symbol_file.code_file = code_file.map(String::from);
+        self
+    }
+
+    /// FILE <id> <filename>
+    pub fn add_file(&mut self, id: i64, filename: impl Into<String>) -> &mut Self {
+        self.symbol_file.files.insert(id, filename.into());
+        self
+    }
+
+    /// FUNC <address> <size> <stack_param_size> <name>
+    pub fn add_function(&mut self, address: u64, size: u64, stack_param_size: i64, name: impl Into<String>) -> &mut Self {
+        let function = Function {
+            address,
+            size,
+            stack_param_size,
+            name: name.into(),
+            is_multiple: false,
+        };
+        self.symbol_file.functions.insert(address, size, function);
+        self
+    }
+
+    /// <address> <size> <line number> <source file id>
+    pub fn add_line(&mut self, address: u64, size: u64, line_number: i64, source_file_id: i64) -> &mut Self {
+        let line = Line {
+            address,
+            size,
+            line_number,
+            source_file_id,
+        };
+        self.symbol_file.lines.insert(address, size, line);
+        self
+    }
+
+    /// PUBLIC <address> <stack_param_size> <name>
+    pub fn add_public(&mut self, address: u64, stack_param_size: i64, name: impl Into<String>) -> &mut Self {
+        let public_symbol = PublicSymbol {
+            address,
+            stack_param_size,
+            name: name.into(),
+            is_multiple: false,
+        };
+        self.symbol_file.public_symbols.insert(address, public_symbol);
+        self
+    }
+
+    pub fn build(self) -> SymbolFile {
+        self.symbol_file
+    }
+}
+
+fn write_line_record(writer: &mut impl Write, line: &Line, source_file_id: i64) -> io::Result<()> {
+    writeln!(writer, "{:x} {:x} {} {}", line.address, line.size, line.line_number, source_file_id)
+}
+
+fn find_public_symbol_by_address(
+    map: &BTreeMap<u64, PublicSymbol>,
+    address: u64,
+) -> Option<&PublicSymbol> {
+    if let Some(greater_one) = map.range((Included(&0), Included(&address))).next_back() {
+        let target_address = *greater_one.0;
+        let target_item = greater_one.1;
+        trace!("nearest PUBLIC {:#x} for {:#x}", target_address, address);
+        if target_address <= address {
+            return Some(target_item);
+        }
+    }
+    None
+}
+
+// How far past the last PUBLIC record an address may still belong to it.
+const PUBLIC_SLACK: u64 = 0x10000;
+
+pub fn lookup_address(symbol_file: &SymbolFile, address: u64) -> Option<Symbol<'_>> {
+    resolve_address(
+        symbol_file,
+        address,
+        symbol_file.functions.get(address),
+        || symbol_file.lines.get(address),
+        || find_public_symbol_by_address(&symbol_file.public_symbols, address),
+    )
+}
+
+/// Which record wins when an address is inside a FUNC and a PUBLIC record
+/// starts between that FUNC and the address, as with app symbols merged with
+/// system ones. A PUBLIC starting before the FUNC never competes with it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchPreference {
+    #[default]
+    Func,
+    Public,
+}
+
+/// Knobs for [`lookup_address_with_options`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LookupOptions {
+    pub prefer: MatchPreference,
+    /// Also return the other record of such a pair, after the preferred one.
+    pub both: bool,
+}
+
+// Like `lookup_address`, but with control over FUNC and PUBLIC records
+// matching the same address. Default options give the same answer.
+pub fn lookup_address_with_options<'a>(symbol_file: &'a SymbolFile, address: u64, options: &LookupOptions) -> Vec<Symbol<'a>> {
+    let function_record = match symbol_file.functions.get(address) {
+        Some(function_record) => function_record,
+        None => return lookup_address(symbol_file, address).into_iter().collect(),
+    };
+    let mut symbols = vec![function_symbol(symbol_file, address, function_record, symbol_file.lines.get(address))];
+    let public_record = find_public_symbol_by_address(&symbol_file.public_symbols, address)
+        .filter(|public_record| public_record.address >= function_record.address);
+    if let Some(public_record) = public_record {
+        let symbol = public_symbol(address, public_record);
+        match options.prefer {
+            MatchPreference::Func => symbols.push(symbol),
+            MatchPreference::Public => symbols.insert(0, symbol),
+        }
+    }
+    if !options.both {
+        symbols.truncate(1);
+    }
+    symbols
+}
+
+// Looks up many addresses at once, in the order given. Sorted input is
+// resolved with a single pass over the FUNC, LINE and PUBLIC records, which
+// is much cheaper than a `lookup_address` call per address; unsorted input
+// still works, just without the speedup.
+pub fn lookup_addresses<'a>(symbol_file: &'a SymbolFile, sorted: &[u64]) -> Vec<Option<Symbol<'a>>> {
+    let mut cursor = symbol_file.lookup_cursor();
+    sorted.iter().map(|&address| cursor.lookup(address)).collect()
+}
+
+/// Lookups that carry on from where the previous one ended in the FUNC, LINE
+/// and PUBLIC tables instead of searching them from the root, for ascending
+/// addresses like sorted stack frames or profiler samples. Going backwards
+/// costs one full search; the answers are those of [`lookup_address`] in any
+/// order.
+pub struct LookupCursor<'a> {
+    symbol_file: &'a SymbolFile,
+    functions: RangeCursor<'a, Function>,
+    lines: RangeCursor<'a, Line>,
+    publics: NearestCursor<'a, PublicSymbol>,
+}
+
+impl<'a> LookupCursor<'a> {
+    pub fn new(symbol_file: &'a SymbolFile) -> Self {
+        LookupCursor {
+            symbol_file,
+            functions: symbol_file.functions.cursor(),
+            lines: symbol_file.lines.cursor(),
+            publics: NearestCursor::new(&symbol_file.public_symbols),
+        }
+    }
+
+    pub fn lookup(&mut self, address: u64) -> Option<Symbol<'a>> {
+        let function = self.functions.seek(address);
+        let lines = &mut self.lines;
+        let publics = &mut self.publics;
+        resolve_address(
+            self.symbol_file,
+            address,
+            function,
+            || lines.seek(address),
+            || publics.seek(address).map(|(_, public)| public),
+        )
+    }
+}
+
+fn resolve_address<'a>(
+    symbol_file: &'a SymbolFile,
+    address: u64,
+    function: Option<&'a Function>,
+    line: impl FnOnce() -> Option<&'a Line>,
+    public: impl FnOnce() -> Option<&'a PublicSymbol>,
+) -> Option<Symbol<'a>> {
+    if let Some(function_record) = function {
+        Some(function_symbol(symbol_file, address, function_record, line()))
+    } else if symbol_file.is_out_of_range(address) {
+        // The nearest PUBLIC would be a misleading match.
+        warn!(
+            "{:#x} is beyond the end of {} ({:#x}), it probably belongs to another module or needs rebasing",
+            address,
+            symbol_file.module.as_ref().map_or("the module", |module| module.name.as_str()),
+            symbol_file.end_address().unwrap()
+        );
+        None
+    } else if let Some(public_record) = public() {
+        Some(public_symbol(address, public_record))
+    } else {
+        debug!("{:#x}: no FUNC or PUBLIC record", address);
+        None
+    }
+}
+
+fn function_symbol<'a>(symbol_file: &'a SymbolFile, address: u64, function_record: &'a Function, line: Option<&Line>) -> Symbol<'a> {
+    let mut symbol = Symbol {
+        function_name: Cow::Borrowed(&function_record.name),
+        source_file_name: Cow::Borrowed(""),
+        source_file_number: -1,
+        kind: SymbolKind::Func,
+        stack_param_size: function_record.stack_param_size,
+        function_address: Some(function_record.address),
+        function_size: Some(function_record.size),
+    };
+
+    let indexed_line;
+    let line = match (line, &symbol_file.line_index) {
+        (None, Some(index)) => {
+            indexed_line = index.line_at(function_record.address, address);
+            indexed_line.as_ref()
+        }
+        (line, _) => line,
+    };
+    if let Some(line) = line {
+        symbol.source_file_number = line.line_number;
+        symbol.source_file_name = symbol_file.source_file_name_ref(line.source_file_id);
+        debug!(
+            "{:#x}: FUNC {:x} {:x} {}, LINE {:x} {:x} {} {}",
+            address, function_record.address, function_record.size, function_record.name,
+            line.address, line.size, line.line_number, line.source_file_id
+        );
+    } else {
+        debug!(
+            "{:#x}: FUNC {:x} {:x} {}, no LINE",
+            address, function_record.address, function_record.size, function_record.name
+        );
+    }
+    symbol
+}
+
+fn public_symbol(address: u64, public_record: &PublicSymbol) -> Symbol<'_> {
+    debug!("{:#x}: PUBLIC {:x} {}", address, public_record.address, public_record.name);
+    Symbol {
+        function_name: Cow::Borrowed(&public_record.name),
+        source_file_name: Cow::Borrowed(""),
+        source_file_number: -1,
+        kind: SymbolKind::Public,
+        stack_param_size: public_record.stack_param_size,
+        function_address: Some(public_record.address),
+        function_size: None,
+    }
+}
+
+pub fn lookup_range(symbol_file: &SymbolFile, start: u64, end: u64) -> Vec<FunctionSymbol> {
+    symbol_file
+        .functions
+        .retrieve_overlapping(start, end)
+        .map(|(_, _, function)| {
+            let mut symbol = FunctionSymbol {
+                address: function.address,
+                size: function.size,
+                function_name: function.name.clone(),
+                source_file_name: String::from(""),
+            };
+            let indexed_line = symbol_file.indexed_line(function.address);
+            if let Some(line) = symbol_file.lines.get(function.address).or(indexed_line.as_ref()) {
+                symbol.source_file_name = symbol_file.source_file_name(line.source_file_id);
+            }
+            symbol
+        })
+        .collect()
+}
+
+// All FUNC and PUBLIC records sorted by address, FUNC first on ties.
+pub fn all_symbols(symbol_file: &SymbolFile) -> Vec<SymbolRecord> {
+    let functions = symbol_file.functions.values().map(|function| SymbolRecord {
+        address: function.address,
+        size: function.size,
+        kind: SymbolKind::Func,
+        name: function.name.clone(),
+    });
+    let publics = symbol_file.public_symbols().map(|(address, _, name)| SymbolRecord {
+        address,
+        size: 0,
+        kind: SymbolKind::Public,
+        name: name.to_string(),
+    });
+    let mut records: Vec<SymbolRecord> = functions.chain(publics).collect();
+    records.sort_by_key(|record| record.address);
+    records
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// Symbol stores keep `.sym.gz` / `.sym.zst`; sniff the magic bytes rather than
+// trusting the extension and decompress on the fly.
+#[cfg(feature = "compression")]
+fn decompressing_reader<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))))
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Ok(Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompressing_reader<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "compressed symbol file, built without the compression feature"));
+    }
+    Ok(Box::new(reader))
+}
+
+/// Whether `path` names an existing symbol file, or an entry of an existing
+/// archive (`symbols.zip!arm64-v8a/libunity.sym`).
+pub fn symbol_file_exists(path: &Path) -> bool {
+    archive::split_archive_path(path).0.exists()
+}
+
+/// Whether `path`, or the archive entry it selects as [`open_symbol_file`]
+/// would, is an ELF file rather than breakpad symbols, like the per-ABI
+/// `.so.sym` and `.so.dbg` files of a Google Play native debug symbols zip.
+pub fn is_elf_file(path: &Path, module_hint: Option<&str>) -> bool {
+    let (path, entry) = archive::split_archive_path(path);
+    let mut magic = [0u8; 4];
+    let read = match File::open(&path).and_then(|mut file| file.read(&mut magic)) {
+        Ok(read) => read,
+        Err(_) => return false,
+    };
+    if entry.is_some() || magic[..read].starts_with(archive::ZIP_MAGIC) {
+        return archive::zip_entry_magic(&path, entry.as_deref(), module_hint).is_ok_and(|magic| magic.starts_with(archive::ELF_MAGIC));
+    }
+    magic[..read].starts_with(archive::ELF_MAGIC)
+}
+
+/// Breakpad symbol store key: the `.pdb`/`.dll` style extension is dropped from
+/// the file name but kept in the directory, as symupload does.
+pub fn breakpad_store_key(module_name: &str, debug_id: &str) -> String {
+    let stem = module_name
+        .strip_suffix(".pdb")
+        .or_else(|| module_name.strip_suffix(".dll"))
+        .unwrap_or(module_name);
+    format!("{}/{}/{}.sym", module_name, debug_id, stem)
+}
+
+// Breakpad derives the module id of an ELF file from the first 16 bytes of
+// its build id, read as a little endian GUID, with a zero age appended.
+pub fn build_id_to_debug_id(build_id: &str) -> String {
+    let mut bytes: Vec<u8> = (0..build_id.len() / 2)
+        .filter_map(|i| u8::from_str_radix(&build_id[i * 2..i * 2 + 2], 16).ok())
+        .take(16)
+        .collect();
+    bytes.resize(16, 0);
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    let mut debug_id: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    debug_id.push('0');
+    debug_id
+}
+
+/// Searches `symbol_dirs` in order for the symbol file of `name`: a store laid
+/// out as `<module>/<debug_id>/<module>.sym` when the debug id is known, then
+/// `<name>` and `<name>.sym` directly inside the directory.
+pub fn find_symbol_file(symbol_dirs: &[PathBuf], name: &str, debug_id: Option<&str>) -> Option<PathBuf> {
+    for dir in symbol_dirs {
+        let mut candidates = vec![];
+        if let Some(debug_id) = debug_id {
+            candidates.push(dir.join(breakpad_store_key(name, debug_id)));
+        }
+        candidates.push(dir.join(name));
+        candidates.push(dir.join(format!("{}.sym", name)));
+        if let Some(path) = candidates.into_iter().find(|path| path.is_file()) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Like [`find_symbol_file`], for symbol directories holding a library for
+/// several ABIs: files in ABI subdirectories (`arm64-v8a/libil2cpp.so.sym`)
+/// are candidates too, and the first whose MODULE record is of `arch` wins.
+/// Fails naming the architectures found if there are only others.
+pub fn find_symbol_file_for_arch(symbol_dirs: &[PathBuf], name: &str, debug_id: Option<&str>, arch: &str) -> Result<Option<PathBuf>, String> {
+    let arch = normalize_arch(arch);
+    let mut mismatched = vec![];
+    for dir in symbol_dirs {
+        let mut candidates = vec![];
+        if let Some(debug_id) = debug_id {
+            candidates.push(dir.join(breakpad_store_key(name, debug_id)));
+        }
+        let mut subdirs: Vec<PathBuf> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        subdirs.sort();
+        for dir in std::iter::once(dir).chain(&subdirs) {
+            candidates.push(dir.join(name));
+            candidates.push(dir.join(format!("{}.sym", name)));
+        }
+        for path in candidates.into_iter().filter(|path| path.is_file()) {
+            // Files without a MODULE record, like ELF debug info, are taken
+            // as they are.
+            match read_module_arch(&path).map(|module_arch| normalize_arch(&module_arch)) {
+                Some(module_arch) if module_arch != arch => {
+                    debug!("skipping {}: {} symbols", path.display(), module_arch);
+                    mismatched.push(module_arch);
+                }
+                _ => return Ok(Some(path)),
+            }
+        }
+    }
+    if mismatched.is_empty() {
+        return Ok(None);
+    }
+    mismatched.sort();
+    mismatched.dedup();
+    Err(format!("symbol files for {} are only for {}, not {}", name, mismatched.join(", "), arch))
+}
+
+// The arch of the MODULE record a symbol file starts with.
+fn read_module_arch(path: &Path) -> Option<String> {
+    let mut line = String::new();
+    open_symbol_file(path, None).ok()?.read_line(&mut line).ok()?;
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("MODULE") {
+        return None;
+    }
+    fields.nth(1).map(String::from)
+}
+
+/// One name per architecture for those of MODULE records, Android ABIs,
+/// ips `cpuType`/`Code Type` and tombstone `ABI` lines: `arm64-v8a`,
+/// `ARM-64 (Native)` and `aarch64` are all `arm64`. Unknown ones are only
+/// lowercased.
+pub fn normalize_arch(arch: &str) -> String {
+    let arch = arch.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+    match arch.as_str() {
+        "arm64" | "arm64e" | "arm-64" | "aarch64" | "arm64-v8a" => String::from("arm64"),
+        "x86_64" | "x86-64" | "amd64" | "x64" => String::from("x86_64"),
+        "x86" | "i386" | "i686" | "ia32" => String::from("x86"),
+        _ if is_arm32(&arch) => String::from("arm"),
+        _ => arch,
+    }
+}
+
+/// Opens a symbol file for reading, decompressing gzip/zstd and looking inside
+/// zip archives. `module_hint` (a module name or ABI directory) picks the entry
+/// when an archive holds several symbol files and none was named explicitly.
+pub fn open_symbol_file(path: &Path, module_hint: Option<&str>) -> io::Result<Box<dyn BufRead>> {
+    let (path, entry) = archive::split_archive_path(path);
+    let mut reader = BufReader::new(File::open(&path)?);
+    if entry.is_some() || reader.fill_buf()?.starts_with(archive::ZIP_MAGIC) {
+        let data = archive::open_zip_entry(&path, entry.as_deref(), module_hint)?;
+        return decompressing_reader(Cursor::new(data));
+    }
+    decompressing_reader(reader)
+}
+
+pub fn parse_breakpad_symbol_file(filename: &Path) -> SymbolFile {
+    parse_breakpad_symbol_source(filename, None)
+}
+
+/// Called with the number of bytes parsed so far and the total, when known
+/// (plain, uncompressed files).
+pub type ProgressCallback = Box<dyn Fn(u64, Option<u64>)>;
+
+/// Knobs for [`SymbolFile::write_breakpad_with_options`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Write each FILE path once and only those LINE records use, with ids
+    /// renumbered from 0, to shrink stored symbol files.
+    pub compact_files: bool,
+}
+
+/// How the parser treats malformed records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail on the first malformed record, for validating symbol pipelines.
+    Strict,
+    /// Skip malformed records, listing them in `SymbolFile::diagnostics`, to
+    /// salvage partially corrupted files.
+    #[default]
+    Lenient,
+}
+
+/// Knobs for parsing a symbol file.
+#[derive(Default)]
+pub struct ParserOptions {
+    pub mode: ParseMode,
+    /// Invoked about every megabyte and once at the end, so embedders can
+    /// surface progress of multi-GB parses.
+    pub progress: Option<ProgressCallback>,
+    /// Replaces [`DEFAULT_UNKNOWN_FILE_PLACEHOLDER`].
+    pub unknown_file_placeholder: Option<String>,
+    /// Don't keep LINE records in memory: remember where the LINE records
+    /// of each FUNC are in the file, mapped into memory with the `mmap`
+    /// feature, and parse them again on lookup. Cuts
+    /// memory use several times for large modules, at the cost of slower
+    /// lookups. Only affects plain files on disk; LINE records are then not
+    /// validated when parsing, and whole-file operations (writing, exports,
+    /// sizes by file) read them all back into memory.
+    pub low_memory: bool,
+    /// Keep STACK, INLINE, INLINE_ORIGIN and INFO records, which lookups
+    /// don't use, so that [`SymbolFile::write_breakpad`] writes them back. It
+    /// fails on files that had any and were parsed without.
+    pub keep_unparsed_records: bool,
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    /// A malformed record, in strict mode.
+    Malformed(Diagnostic),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "{}", err),
+            ParseError::Malformed(diagnostic) => {
+                write!(f, "line {}: {}: {}", diagnostic.line_number, diagnostic.message, diagnostic.line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+impl FromStr for SymbolFile {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<SymbolFile, ParseError> {
+        SymbolFile::from_bytes(text.as_bytes())
+    }
+}
+
+const PROGRESS_INTERVAL: u64 = 1 << 20;
+
+// Size of the text to parse, known only when the file is read as-is.
+fn uncompressed_size(path: &Path) -> Option<u64> {
+    if archive::split_archive_path(path).1.is_some() {
+        return None;
+    }
+    let mut magic = [0u8; 4];
+    let mut file = File::open(path).ok()?;
+    let read = file.read(&mut magic).ok()?;
+    let magic = &magic[..read];
+    if magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) || magic.starts_with(archive::ZIP_MAGIC) {
+        return None;
+    }
+    Some(file.metadata().ok()?.len())
+}
+
+// Like `BufRead::lines`, but invalid UTF-8 (seen in symbol names from third
+// party SDKs) is replaced with U+FFFD instead of failing the whole parse. The
+// flag tells whether anything was replaced.
+struct LossyLines<R> {
+    reader: R,
+}
+
+impl<R: BufRead> LossyLines<R> {
+    fn new(reader: R) -> Self {
+        LossyLines { reader }
+    }
+}
+
+impl<R: BufRead> Iterator for LossyLines<R> {
+    type Item = io::Result<(String, bool)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // `read_until`, finding the newline with the vectorized memchr
+        let mut buf = Vec::new();
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+            if available.is_empty() {
+                break;
+            }
+            let (used, done) = match memchr::memchr(b'\n', available) {
+                Some(index) => (index + 1, true),
+                None => (available.len(), false),
+            };
+            buf.extend_from_slice(&available[..used]);
+            self.reader.consume(used);
+            if done {
+                break;
+            }
+        }
+        if buf.is_empty() {
+            return None;
+        }
+        Some(Ok(lossy_line(buf)))
+    }
+}
+
+// A line read up to and including its `\n`, without it.
+fn lossy_line(mut buf: Vec<u8>) -> (String, bool) {
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+    }
+    match String::from_utf8(buf) {
+        Ok(line) => (line, false),
+        Err(err) => (String::from_utf8_lossy(err.as_bytes()).into_owned(), true),
+    }
+}
+
+pub fn parse_breakpad_symbol_source(filename: &Path, module_hint: Option<&str>) -> SymbolFile {
+    parse_breakpad_symbol_source_with_options(filename, module_hint, &ParserOptions::default()).unwrap()
+}
+
+pub fn parse_breakpad_symbol_source_with_options(filename: &Path, module_hint: Option<&str>, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    info!("loading symbol file {}", filename.display());
+    if options.low_memory {
+        match lazy_lines::open_plain_file(filename)? {
+            Some(file) => return lazy_lines::parse_plain_file(file, filename, options),
+            None => warn!("{}: not a plain symbol file, keeping LINE records in memory", filename.display()),
+        }
+    }
+    let (path, entry) = archive::split_archive_path(filename);
+    let mut reader = BufReader::new(File::open(&path)?);
+    if entry.is_some() || reader.fill_buf()?.starts_with(archive::ZIP_MAGIC) {
+        return archive::stream_zip_entry(&path, entry.as_deref(), module_hint, |file| {
+            Ok(parse_symbol_source(decompressing_reader(BufReader::new(file))?, filename, module_hint, None, options))
+        })?;
+    }
+    let total = options.progress.as_ref().and_then(|_| uncompressed_size(filename));
+    parse_symbol_source(decompressing_reader(reader)?, filename, module_hint, total, options)
+}
+
+// A symbol file or SymCache, decompressed.
+fn parse_symbol_source(mut reader: impl BufRead, filename: &Path, module_hint: Option<&str>, total: Option<u64>, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    if is_symcache(reader.fill_buf()?) {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        let name = module_hint.or_else(|| filename.file_stem()?.to_str()).unwrap_or("");
+        return parse_symcache(&data, name).map_err(|err| ParseError::Io(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", filename.display(), err))));
+    }
+    parse_symbol_reader(reader, &filename.display(), total, options)
+}
+
+fn parse_symbol_reader(reader: impl BufRead, filename: &dyn fmt::Display, total: Option<u64>, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    let started = observer::start();
+    let result = parse_symbol_lines(reader, filename, total, options);
+    observer::observe(started, |observer, duration| observer.parsed(&filename.to_string(), duration, result.is_ok()));
+    result
+}
+
+fn parse_symbol_lines(reader: impl BufRead, filename: &dyn fmt::Display, total: Option<u64>, options: &ParserOptions) -> Result<SymbolFile, ParseError> {
+    let mut parsed: u64 = 0;
+    let mut reported: u64 = 0;
+
+    let mut parser = Parser::new(filename.to_string(), options.mode);
+    if options.keep_unparsed_records {
+        parser.symbol_file.unparsed = Some(UnparsedRecords::default());
+    }
+    for (line_number, line) in LossyLines::new(reader).enumerate() {
+        let (line, lossy) = line?;
+        if let Some(progress) = &options.progress {
+            parsed += line.len() as u64 + 1;
+            if parsed - reported >= PROGRESS_INTERVAL {
+                progress(parsed, total);
+                reported = parsed;
+            }
+        }
+        parser.add_line(line_number, line, lossy)?;
+    }
+    let symbol_file = parser.finish(options.unknown_file_placeholder.as_deref());
+
+    if let Some(progress) = &options.progress {
+        progress(total.unwrap_or(parsed), total);
+    }
+    Ok(symbol_file)
+}
+
+// The state of a parse between lines, so blocking and async readers can
+// feed it.
+struct Parser {
+    symbol_file: SymbolFile,
+    filename: String,
+    mode: ParseMode,
+    lossy_lines: usize,
+    // Set for low memory parses.
+    line_blocks: Option<LineBlocks>,
+    // The FUNC that INLINE records belong to.
+    last_function: Option<u64>,
+}
+
+impl Parser {
+    fn new(filename: String, mode: ParseMode) -> Self {
+        Parser {
+            symbol_file: SymbolFile::new(),
+            filename,
+            mode,
+            lossy_lines: 0,
+            line_blocks: None,
+            last_function: None,
+        }
+    }
+
+    // `line_number` is 0-based.
+    fn add_line(&mut self, line_number: usize, mut line: String, lossy: bool) -> Result<(), ParseError> {
+        if lossy {
+            debug!("{}:{}: invalid UTF-8 replaced", self.filename, line_number + 1);
+            self.lossy_lines += 1;
+        }
+        normalize_line(line_number, &mut line);
+        if self.line_blocks.as_mut().is_some_and(|blocks| blocks.skip_line(&line)) {
+            return Ok(());
+        }
+        let result = match parse_record(&line) {
+            Ok(Record::Unparsed(UnparsedKind::Inline)) if self.last_function.is_none() => Err(String::from("INLINE record outside any FUNC")),
+            Ok(Record::Unparsed(kind)) => {
+                self.symbol_file.add_unparsed(kind, self.last_function, line);
+                return Ok(());
+            }
+            Ok(record) => {
+                if let Record::Func(function) = &record {
+                    self.last_function = Some(function.address);
+                    if let Some(blocks) = self.line_blocks.as_mut() {
+                        blocks.open(function.address);
+                    }
+                }
+                self.symbol_file.add_record(record);
+                Ok(())
+            }
+            Err(message) => Err(message),
+        };
+        if let Err(message) = result {
+            debug!("{}:{}: {}: {}", self.filename, line_number + 1, message, line);
+            let diagnostic = Diagnostic {
+                line_number: line_number + 1,
+                line,
+                message,
+            };
+            if self.mode == ParseMode::Strict {
+                return Err(ParseError::Malformed(diagnostic));
+            }
+            self.symbol_file.diagnostics.push(diagnostic);
+        }
+        Ok(())
+    }
+
+    fn finish(self, unknown_file_placeholder: Option<&str>) -> SymbolFile {
+        let Parser {
+            mut symbol_file,
+            filename,
+            lossy_lines,
+            line_blocks,
+            ..
+        } = self;
+        symbol_file.line_index = line_blocks.map(LineBlocks::finish);
+        if lossy_lines > 0 {
+            warn!("{}: replaced invalid UTF-8 in {} line(s)", filename, lossy_lines);
+        }
+        if !symbol_file.diagnostics.is_empty() {
+            warn!(
+                "{}: skipped {} malformed line(s), first at line {}",
+                filename,
+                symbol_file.diagnostics.len(),
+                symbol_file.diagnostics[0].line_number
+            );
+        }
+
+        if let Some(placeholder) = unknown_file_placeholder {
+            symbol_file.set_unknown_file_placeholder(placeholder);
+        }
+        symbol_file.duplicate_files = symbol_file.dedup_files();
+        if symbol_file.duplicate_files > 0 {
+            debug!("{}: folded {} duplicate FILE record(s)", filename, symbol_file.duplicate_files);
+        }
+        symbol_file.undefined_file_lines = symbol_file.lines.values().filter(|line| !symbol_file.files.contains_key(&line.source_file_id)).count();
+        if symbol_file.undefined_file_lines > 0 {
+            warn!("{}: {} LINE record(s) reference undefined FILE ids", filename, symbol_file.undefined_file_lines);
+        }
+
+        debug!(
+            "loaded {} ({}): {} FILE, {} FUNC, {} LINE, {} PUBLIC",
+            filename,
+            symbol_file.module.as_ref().map_or("no MODULE", |module| module.debug_id.as_str()),
+            symbol_file.files.len(),
+            symbol_file.functions.len(),
+            symbol_file.lines.len(),
+            symbol_file.public_symbols.len()
+        );
+        symbol_file
+    }
 }
 
-#[derive(Debug)]
-struct RangeItem<T> {
-    item: T,
-    size: u64,
+// Sym files written on Windows may start with a UTF-8 BOM and end lines with
+// `\r\n`.
+fn normalize_line(line_number: usize, line: &mut String) {
+    if line_number == 0 && line.starts_with('\u{feff}') {
+        line.drain(..'\u{feff}'.len_utf8());
+    }
+    if line.ends_with('\r') {
+        line.pop();
+    }
 }
 
-#[derive(Debug)]
-struct RangeMap<T> {
-    map: BTreeMap<u64, RangeItem<T>>,
+// One line of a symbol file.
+enum Record {
+    Module(ModuleRecord),
+    CodeId(String, Option<String>),
+    File(i64, String),
+    Func(Function),
+    Line(Line),
+    Public(PublicSymbol),
+    // STACK, INLINE, INLINE_ORIGIN and other INFO records, only kept as text
+    // for writing back. Lookups report the outermost function of inlined
+    // call frames.
+    Unparsed(UnparsedKind),
+    // Blank lines.
+    Ignored,
 }
 
-impl<T> RangeMap<T> {
-    pub fn new() -> Self {
-        RangeMap {
-            map: BTreeMap::new(),
-        }
+fn parse_record(line: &str) -> Result<Record, String> {
+    if let Some(kind) = unparsed_kind(line) {
+        Ok(Record::Unparsed(kind))
+    } else if line.starts_with("FILE ") {
+        parse_file_line(line)
+    } else if line.starts_with("FUNC ") {
+        parse_func_line(line).map(Record::Func)
+    } else if line.starts_with("PUBLIC ") {
+        parse_public_line(line).map(Record::Public)
+    } else if line.starts_with("MODULE ") {
+        parse_module_line(line).map(Record::Module)
+    } else if line.starts_with("INFO CODE_ID ") {
+        parse_code_id_line(line)
+    } else if line.trim().is_empty() {
+        Ok(Record::Ignored)
+    } else if line.starts_with(|c: char| c.is_ascii_hexdigit()) {
+        parse_line_line(line).map(Record::Line)
+    } else {
+        Err(String::from("unknown record type"))
     }
+}
 
-    pub fn insert(&mut self, address: u64, size: u64, item: T) {
-        self.map.insert(address, RangeItem { item, size });
+impl SymbolFile {
+    fn add_unparsed(&mut self, kind: UnparsedKind, function: Option<u64>, line: String) {
+        let unparsed = match &mut self.unparsed {
+            Some(unparsed) => unparsed,
+            None => {
+                self.dropped_records += 1;
+                return;
+            }
+        };
+        match (kind, function) {
+            (UnparsedKind::Info, _) => unparsed.info.push(line),
+            (UnparsedKind::InlineOrigin, _) => unparsed.inline_origins.push(line),
+            (UnparsedKind::Inline, Some(function)) => unparsed.inlines.entry(function).or_default().push(line),
+            (UnparsedKind::Inline, None) => self.dropped_records += 1,
+            (UnparsedKind::Stack, _) => unparsed.stack.push(line),
+        }
     }
 
-    pub fn retrieve_range<'a>(&'a self, address: u64) -> Option<&'a T> {
-        if let Some(greater_one) = self
-            .map
-            .range((Included(&0), Included(&address)))
-            .next_back()
-        {
-            let target_address = *greater_one.0;
-            let range_item = greater_one.1;
-            let target_size = range_item.size;
-            //println!("Found item {:#x},", target_address);
-            if target_address <= address && address <= target_address + target_size {
-                return Some(&range_item.item);
+    fn add_record(&mut self, record: Record) {
+        match record {
+            Record::Module(module) => self.module = Some(module),
+            Record::CodeId(code_id, code_file) => {
+                self.code_id = Some(code_id);
+                self.code_file = code_file;
+            }
+            Record::File(id, filename) => {
+                self.files.insert(id, filename);
             }
+            Record::Func(function) => {
+                self.functions.insert(function.address, function.size, function);
+            }
+            Record::Line(line) => {
+                self.lines.insert(line.address, line.size, line);
+            }
+            Record::Public(public_symbol) => {
+                self.public_symbols.insert(public_symbol.address, public_symbol);
+            }
+            Record::Unparsed(_) | Record::Ignored => {}
         }
-        None
     }
 }
 
-fn find_public_symbol_by_address<'a>(
-    map: &'a BTreeMap<u64, PublicSymbol>,
-    address: u64,
-) -> Option<&'a PublicSymbol> {
-    if let Some(greater_one) = map.range((Included(&0), Included(&address))).next_back() {
-        let target_address = *greater_one.0;
-        let target_item = greater_one.1;
-        //println!("Found address {:#x}", target_address);
-        if target_address <= address {
-            return Some(&target_item);
-        }
-    }
-    None
+fn token<'a>(tokens: &[&'a str], index: usize, field: &str) -> Result<&'a str, String> {
+    tokens.get(index).copied().ok_or_else(|| format!("missing {}", field))
 }
 
-pub fn lookup_address(symbol_file: &SymbolFile, address: u64) -> Option<Symbol> {
-    if let Some(function_record) = symbol_file.functions.retrieve_range(address) {
-        let mut symbol = Symbol {
-            function_name: function_record.name.clone(),
-            source_file_name: String::from(""),
-            source_file_number: -1,
-        };
+fn invalid(field: &str, value: &str) -> String {
+    format!("invalid {} {:?}", field, value)
+}
 
-        if let Some(line) = symbol_file.lines.retrieve_range(address) {
-            symbol.source_file_number = line.line_number as i64;
-            if let Some(filename) = symbol_file.files.get(&line.source_file_id) {
-                symbol.source_file_name = filename.to_string();
-            }
-        }
-        Some(symbol)
-    } else if let Some(public_record) =
-    find_public_symbol_by_address(&symbol_file.public_symbols, address)
-    {
-        let symbol = Symbol {
-            function_name: public_record.name.clone(),
-            source_file_name: String::from(""),
-            source_file_number: -1,
-        };
-        Some(symbol)
-    } else {
-        None
-    }
+fn parse_module_line(line: &str) -> Result<ModuleRecord, String> {
+    // MODULE <os> <arch> <debug_id> <name>
+    assert!(line.starts_with("MODULE "));
+    let line = &line[7..]; // skip prefix
+    let line = line.trim();
+
+    let tokens: Vec<&str> = tokenize(line, " ", 4);
+    let os = token(&tokens, 0, "os")?;
+    let arch = token(&tokens, 1, "arch")?;
+    let debug_id = token(&tokens, 2, "debug id")?;
+    let name = token(&tokens, 3, "name")?;
+
+    Ok(ModuleRecord {
+        os: String::from(os),
+        arch: String::from(arch),
+        debug_id: String::from(debug_id),
+        name: String::from(name),
+    })
 }
 
-pub fn parse_breakpad_symbol_file(filename: &Path) -> SymbolFile {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    let mut symbol_file = SymbolFile {
-        files: HashMap::new(),
-        functions: RangeMap::new(),
-        lines: RangeMap::new(),
-        public_symbols: BTreeMap::new(),
-    };
+fn parse_code_id_line(line: &str) -> Result<Record, String> {
+    // INFO CODE_ID <code_id> [<filename>]
+    assert!(line.starts_with("INFO CODE_ID "));
+    let line = &line[13..]; // skip prefix
+    let line = line.trim();
 
-    for line in reader.lines() {
-        let line = line.unwrap(); // Ignore errors.
-        //println!("{:?}", line);
-        if line.starts_with("FILE ") {
-            parse_file_line(&mut symbol_file, &line);
-        } else if line.starts_with("STACK ") {
-            // pass
-        } else if line.starts_with("FUNC ") {
-            parse_func_line(&mut symbol_file, &line);
-        } else if line.starts_with("PUBLIC ") {
-            parse_public_line(&mut symbol_file, &line);
-        } else if line.starts_with("MODULE ") {
-            // MODULE <guid> <age> <filename>
-            // pass
-        } else if line.starts_with("INFO ") {
-            // INFO CODE_ID <code id> <filename>
-            // pass
-        } else {
-            // LINE
-            parse_line_line(&mut symbol_file, &line);
-        }
-    }
-
-    //println!("{:?}", symbol_file);
-    symbol_file
+    let tokens: Vec<&str> = tokenize(line, " ", 2);
+    let code_id = token(&tokens, 0, "code id")?;
+    Ok(Record::CodeId(String::from(code_id), tokens.get(1).map(|code_file| String::from(*code_file))))
 }
 
-fn parse_line_line(symbol: &mut SymbolFile, line: &str) {
+fn parse_line_line(line: &str) -> Result<Line, String> {
     // <address> <size> <line number> <source file id>
     let line = line.trim();
 
     let tokens: Vec<&str> = tokenize(line, " ", 4);
-    let address = tokens.get(0).unwrap();
-    let size = tokens.get(1).unwrap();
-    let line_number = tokens.get(2).unwrap();
-    let source_file_id = tokens.get(3).unwrap();
-
-    //println!("address={:?}, size={:?}, line_number={:?} source_file_id={:?}", address, size, line_number, source_file_id);
-    let address: u64 = u64::from_str_radix(address, 16).unwrap();
-    let size: u64 = u64::from_str_radix(size, 16).unwrap();
-    let line_number: i64 = i64::from_str_radix(line_number, 10).unwrap();
-    let source_file_id: i64 = i64::from_str_radix(source_file_id, 10).unwrap();
-
-    let line = Line {
+    let address = token(&tokens, 0, "address")?;
+    let size = token(&tokens, 1, "size")?;
+    let line_number = token(&tokens, 2, "line number")?;
+    let source_file_id = token(&tokens, 3, "source file id")?;
+
+    trace!("LINE address={:?}, size={:?}, line_number={:?} source_file_id={:?}", address, size, line_number, source_file_id);
+    let address: u64 = hex_u64(address).ok_or_else(|| invalid("address", address))?;
+    let size: u64 = hex_u64(size).ok_or_else(|| invalid("size", size))?;
+    let line_number: i64 = line_number.parse().map_err(|_| invalid("line number", line_number))?;
+    let source_file_id: i64 = source_file_id.parse().map_err(|_| invalid("source file id", source_file_id))?;
+
+    Ok(Line {
         address,
         size,
         line_number,
         source_file_id,
-    };
-    symbol.lines.insert(address, size, line);
+    })
 }
 
-fn parse_public_line(symbol: &mut SymbolFile, line: &str) {
+fn parse_public_line(line: &str) -> Result<PublicSymbol, String> {
     // PUBLIC [<multiple>] <address> <stack_param_size> <name>
-    assert_eq!(line.starts_with("PUBLIC "), true);
+    assert!(line.starts_with("PUBLIC "));
     let line = &line[7..]; // skip prefix
     let line = line.trim();
 
     let tokens: Vec<&str> = tokenize_with_optional_field(line, "m", " ", 4);
-    let is_multiple = tokens.len() >= 5 && *tokens.get(0).unwrap() == "m";
-    let mut offset = 0;
-    if is_multiple {
-        offset = 1;
-    }
-    let address = tokens.get(offset + 0).unwrap();
-    let stack_param_size = tokens.get(offset + 1).unwrap();
-    let name = tokens.get(offset + 2).unwrap();
+    let is_multiple = tokens.len() >= 5 && tokens.first() == Some(&"m");
+    let offset = if is_multiple { 1 } else { 0 };
+    let address = token(&tokens, offset, "address")?;
+    let stack_param_size = token(&tokens, offset + 1, "stack param size")?;
+    let name = token(&tokens, offset + 2, "name")?;
 
-    //println!("name={:?} address={:?}, stack_param_size={:?}", name, address, stack_param_size);
-    let address: u64 = u64::from_str_radix(address, 16).unwrap();
-    let stack_param_size: i64 = i64::from_str_radix(stack_param_size, 16).unwrap();
+    trace!("PUBLIC name={:?} address={:?}, stack_param_size={:?}", name, address, stack_param_size);
+    let address: u64 = hex_u64(address).ok_or_else(|| invalid("address", address))?;
+    let stack_param_size: i64 = hex_i64(stack_param_size).ok_or_else(|| invalid("stack param size", stack_param_size))?;
 
-    let public_symbol = PublicSymbol {
+    Ok(PublicSymbol {
         address,
         stack_param_size,
-        name: String::from(*name),
+        name: String::from(name),
         is_multiple,
-    };
-    symbol.public_symbols.insert(address, public_symbol);
+    })
 }
 
-fn parse_func_line(symbol: &mut SymbolFile, line: &str) {
+fn parse_func_line(line: &str) -> Result<Function, String> {
     // FUNC [<multiple>] <address> <size> <stack_param_size> <name>
-    assert_eq!(line.starts_with("FUNC "), true);
+    assert!(line.starts_with("FUNC "));
     let line = &line[5..]; // skip prefix
     let line = line.trim();
 
     let tokens: Vec<&str> = tokenize_with_optional_field(line, "m", " ", 5);
-    let is_multiple = tokens.len() >= 5 && *tokens.get(0).unwrap() == "m";
-    let mut offset = 0;
-    if is_multiple {
-        offset = 1;
-    }
-    let address = tokens.get(offset + 0).unwrap();
-    let size = tokens.get(offset + 1).unwrap();
-    let stack_param_size = tokens.get(offset + 2).unwrap();
-    let name = tokens.get(offset + 3).unwrap();
-
-    //println!("address={:?}, size={:?}", address, size);
-    let address: u64 = u64::from_str_radix(address, 16).unwrap();
-    let size: u64 = u64::from_str_radix(size, 16).unwrap();
-    let stack_param_size: i64 = i64::from_str_radix(stack_param_size, 16).unwrap();
-
-    let function = Function {
+    let is_multiple = tokens.len() >= 5 && tokens.first() == Some(&"m");
+    let offset = if is_multiple { 1 } else { 0 };
+    let address = token(&tokens, offset, "address")?;
+    let size = token(&tokens, offset + 1, "size")?;
+    let stack_param_size = token(&tokens, offset + 2, "stack param size")?;
+    let name = token(&tokens, offset + 3, "name")?;
+
+    trace!("FUNC address={:?}, size={:?}, name={:?}", address, size, name);
+    let address: u64 = hex_u64(address).ok_or_else(|| invalid("address", address))?;
+    let size: u64 = hex_u64(size).ok_or_else(|| invalid("size", size))?;
+    let stack_param_size: i64 = hex_i64(stack_param_size).ok_or_else(|| invalid("stack param size", stack_param_size))?;
+
+    Ok(Function {
         address,
         size,
-        name: String::from(*name),
+        name: String::from(name),
         is_multiple,
         stack_param_size,
-    };
-    symbol.functions.insert(address, size, function);
+    })
 }
 
-fn parse_file_line(symbol: &mut SymbolFile, line: &str) {
+fn parse_file_line(line: &str) -> Result<Record, String> {
     // FILE <id> <filename>
-    assert_eq!(line.starts_with("FILE "), true);
+    assert!(line.starts_with("FILE "));
     let line = &line[5..]; // skip prefix
     let line = line.trim();
 
     let tokens: Vec<&str> = tokenize(line, " ", 2);
-    let id = tokens.get(0).unwrap();
-    let filename = tokens.get(1).unwrap();
-    let id: i64 = i64::from_str_radix(id, 10).unwrap();
-    //println!("id={}, filename={}", id, filename);
-    symbol.files.insert(id, String::from(*filename));
+    let id = token(&tokens, 0, "id")?;
+    let filename = token(&tokens, 1, "filename")?;
+    let id: i64 = id.parse().map_err(|_| invalid("id", id))?;
+    trace!("FILE id={}, filename={}", id, filename);
+    Ok(Record::File(id, String::from(filename)))
 }
 
 fn tokenize_with_optional_field<'a>(line: &'a str, optional_field: &str, token: &str, max_tokens: usize) -> Vec<&'a str> {
@@ -272,9 +1872,9 @@ fn tokenize_with_optional_field<'a>(line: &'a str, optional_field: &str, token:
     // the optional field, additionally tokenize the last token into two tokens
     let mut tokens = tokenize(line, token, max_tokens - 1);
 
-    let first = *tokens.get(0).unwrap_or(&"");
+    let first = *tokens.first().unwrap_or(&"");
     if first == optional_field {
-        let last = *tokens.get(tokens.len() - 1).unwrap_or(&"");
+        let last = *tokens.last().unwrap_or(&"");
         let sub_tokens = tokenize(last, token, 2);
         tokens.remove(tokens.len() - 1);
         return [tokens, sub_tokens].concat();
@@ -288,36 +1888,172 @@ fn tokenize<'a>(line: &'a str, token: &str, max_tokens: usize) -> Vec<&'a str> {
     let mut remaining = max_tokens - 1;
     let mut txt = line;
 
-    let mut tmp = txt.splitn(2, token);
-    let mut part_a = tmp.next().unwrap_or("");
-    txt = tmp.next().unwrap_or("");
-    //println!("tokenize txt={}, token={}, max_tokens={}", line, token, max_tokens);
-    while part_a != "" && remaining > 0 {
+    let (mut part_a, rest) = split_token(txt, token);
+    txt = rest;
+    while !part_a.is_empty() && remaining > 0 {
         result.push(part_a);
-        //println!("remaining={}, part_a={}, part_b={}", remaining, part_a, txt);
         if remaining > 1 {
-            tmp = txt.splitn(2, token);
-            part_a = tmp.next().unwrap_or("");
-            txt = tmp.next().unwrap_or("");
+            let (next, rest) = split_token(txt, token);
+            part_a = next;
+            txt = rest;
         }
         remaining -= 1;
     }
 
-    if remaining == 0 && txt.len() > 0 {
-        //println!("remaining={}, part_a={}, part_b={}", remaining, txt, "");
+    if remaining == 0 && !txt.is_empty() {
         result.push(txt);
     }
 
     result
 }
 
+// `txt.splitn(2, token)`, but searching with memchr, which is vectorized.
+fn split_token<'a>(txt: &'a str, token: &str) -> (&'a str, &'a str) {
+    let found = match token.as_bytes() {
+        [byte] => memchr::memchr(*byte, txt.as_bytes()),
+        needle => memchr::memmem::find(txt.as_bytes(), needle),
+    };
+    match found {
+        Some(index) => (&txt[..index], &txt[index + token.len()..]),
+        None => (txt, ""),
+    }
+}
+
+// Digit values for every byte, 0x80 or more for anything not a hex digit.
+const HEX_DIGITS: [u8; 256] = {
+    let mut digits = [0xff; 256];
+    let mut i = 0;
+    while i < 16 {
+        digits[b"0123456789abcdef"[i] as usize] = i as u8;
+        digits[b"0123456789ABCDEF"[i] as usize] = i as u8;
+        i += 1;
+    }
+    digits
+};
+
+/// Decodes up to 16 hex digits without a prefix, the way addresses and sizes
+/// are written in symbol files. Unlike `u64::from_str_radix` it doesn't branch
+/// per digit, invalid digits are only checked for at the end.
+pub fn parse_hex(digits: &str) -> Option<u64> {
+    let digits = digits.as_bytes();
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    let mut value = 0u64;
+    let mut invalid = 0u8;
+    for &digit in digits {
+        let digit = HEX_DIGITS[digit as usize];
+        invalid |= digit;
+        value = value << 4 | u64::from(digit & 0xf);
+    }
+    if invalid & 0x80 == 0 {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+// Hex fields of records: the fast path, then `from_str_radix` for what it
+// declines, like a `+` sign or leading zeros past 16 digits.
+fn hex_u64(digits: &str) -> Option<u64> {
+    parse_hex(digits).or_else(|| u64::from_str_radix(digits, 16).ok())
+}
+
+fn hex_i64(digits: &str) -> Option<i64> {
+    parse_hex(digits).and_then(|value| i64::try_from(value).ok()).or_else(|| i64::from_str_radix(digits, 16).ok())
+}
+
 pub fn parse_address(address: &str) -> Option<u64> {
-    let addr = if address.starts_with("0x") { &address[2..] } else { address };
+    let addr = address.strip_prefix("0x").unwrap_or(address);
+
+    hex_u64(addr)
+}
+
+// "0x1234", or "libil2cpp.so+0x1234" the way Android tombstones and logging
+// frameworks print code locations. The module may be given as a path.
+pub fn parse_module_address(address: &str) -> Option<(Option<&str>, u64)> {
+    match address.rsplit_once('+') {
+        Some(("", _)) => None,
+        Some((module, offset)) => Some((Some(module), parse_address(offset)?)),
+        None => Some((None, parse_address(address)?)),
+    }
+}
 
-    return match u64::from_str_radix(addr, 16) {
-        Ok(result) => Some(result),
-        Err(_) => None
+// 32-bit ARM, as MODULE records, Android ABIs and Apple slices name it.
+fn is_arm32(arch: &str) -> bool {
+    let arch = arch.to_ascii_lowercase();
+    matches!(arch.as_str(), "arm" | "armeabi" | "armeabi-v7a" | "armv6" | "armv7" | "armv7s" | "armv7k" | "armv8l" | "thumb")
+}
+
+// Code addresses of ARM32 Thumb functions, return addresses included, have
+// bit 0 set; the instruction is at the even address below. Clearing it keeps
+// lookups from landing a byte past the call, or in the next function at a
+// boundary. Other architectures are left alone.
+pub fn normalize_code_address(arch: &str, address: u64) -> u64 {
+    if is_arm32(arch) {
+        address & !1
+    } else {
+        address
+    }
+}
+
+// Return addresses point past their call, which may be the last instruction
+// of a function or of a line. Looking up an address inside the call instead
+// names the caller and its line: on arm64 the call is 4 bytes before, on arm
+// (Thumb or not, after clearing the Thumb bit) at least 2; variable length
+// x86 instructions only guarantee 1.
+pub fn caller_address(arch: &str, return_address: u64) -> u64 {
+    let arch = normalize_arch(arch);
+    let delta = match arch.as_str() {
+        "arm64" => 4,
+        "arm" => 2,
+        _ => 1,
+    };
+    normalize_code_address(&arch, return_address).saturating_sub(delta)
+}
+
+// Strips directory components from a FILE path; build machines may use either
+// separator regardless of the host running the lookup.
+pub fn source_basename(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+pub fn source_dir(path: &str) -> &str {
+    path.rfind(['/', '\\']).map_or("", |i| &path[..i])
+}
+
+/// A frame as `libil2cpp.so!Namespace::Func+0x1c4 (file.cpp:88)`, the same
+/// whatever the input, for log pipelines normalizing crashes from many
+/// platforms. Module and source file are reduced to file names, unknown
+/// parts are left out, and a frame without `symbol` is `module+0x<rel_addr>`.
+pub fn canonical_frame(module: &str, rel_addr: u64, symbol: Option<&Symbol>, simplify: NameSimplification) -> String {
+    let module = source_basename(module);
+    let symbol = match symbol {
+        Some(symbol) => symbol,
+        None => return format!("{}+{:#x}", module, rel_addr),
     };
+    let mut frame = format!("{}!{}", module, simplify.simplify(&symbol.function_name));
+    if let Some(offset) = symbol.function_offset(rel_addr) {
+        frame.push_str(&format!("+{:#x}", offset));
+    }
+    match (symbol.source_basename(), symbol.source_file_number) {
+        ("", _) => {}
+        (file, -1) => frame.push_str(&format!(" ({})", file)),
+        (file, line) => frame.push_str(&format!(" ({}:{})", file, line)),
+    }
+    frame
+}
+
+// "0x10000..0x20000", end exclusive
+pub fn parse_address_range(range: &str) -> Option<(u64, u64)> {
+    let mut parts = range.splitn(2, "..");
+    let start = parse_address(parts.next()?)?;
+    let end = parse_address(parts.next()?)?;
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -334,26 +2070,57 @@ mod tests {
         //    println!("token: {}", token);
         //}
         assert_eq!(tokens.len(), 3);
-        assert_eq!(*tokens.get(0).unwrap(), "c1d11c");
+        assert_eq!(*tokens.first().unwrap(), "c1d11c");
         assert_eq!(*tokens.get(1).unwrap(), "0");
         assert_eq!(*tokens.get(2).unwrap(), "bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>(void const*) const");
 
         let tokens = tokenize_with_optional_field("m c1d11c 0 bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>(void const*) const", "m", " ", 4);
         assert_eq!(tokens.len(), 4);
-        assert_eq!(*tokens.get(0).unwrap(), "m");
+        assert_eq!(*tokens.first().unwrap(), "m");
         assert_eq!(*tokens.get(1).unwrap(), "c1d11c");
         assert_eq!(*tokens.get(2).unwrap(), "0");
         assert_eq!(*tokens.get(3).unwrap(), "bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>(void const*) const");
     }
 
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("c1d11c"), Some(0xc1d11c));
+        assert_eq!(parse_hex("C1D11C"), Some(0xc1d11c));
+        assert_eq!(parse_hex("0"), Some(0));
+        assert_eq!(parse_hex("ffffffffffffffff"), Some(u64::MAX));
+        assert_eq!(parse_hex(""), None);
+        assert_eq!(parse_hex("10000000000000000"), None);
+        assert_eq!(parse_hex("c1g1"), None);
+        assert_eq!(parse_hex("+1f"), None);
+        assert_eq!(parse_hex("1f\u{e9}"), None);
+        // the record parsers fall back to from_str_radix for these
+        assert_eq!(hex_u64("+1f"), Some(0x1f));
+        assert_eq!(hex_u64("00000000000000001f"), Some(0x1f));
+        assert_eq!(hex_i64("-8"), Some(-8));
+        assert_eq!(hex_i64("ffffffffffffffff"), None);
+
+        assert_eq!(tokenize("1f  2", " ", 3), vec!["1f"]);
+        assert_eq!(tokenize("1f::2::rest::of", "::", 3), vec!["1f", "2", "rest::of"]);
+    }
+
     #[test]
     fn test_find_function_by_address() {
         println!("test_find_function_by_address");
         let mut symbol_file = SymbolFile {
+            module: None,
+            code_id: None,
+            code_file: None,
             files: HashMap::new(),
             functions: RangeMap::new(),
             public_symbols: BTreeMap::new(),
             lines: RangeMap::new(),
+            diagnostics: vec![],
+            unknown_file_placeholder: String::from(DEFAULT_UNKNOWN_FILE_PLACEHOLDER),
+            undefined_file_lines: 0,
+            duplicate_files: 0,
+            line_index: None,
+            unparsed: None,
+            dropped_records: 0,
         };
 
         symbol_file.functions.insert(
@@ -403,13 +2170,730 @@ mod tests {
         ); // 7-8
 
         let address = 3;
-        let result = symbol_file.functions.retrieve_range(address);
-        assert_eq!(result.is_none(), false);
+        let result = symbol_file.functions.get(address);
+        assert!(result.is_some());
         let target = result.unwrap();
         assert_eq!(target.address, 3);
 
         let address = 5;
-        let result = symbol_file.functions.retrieve_range(address);
-        assert_eq!(result.is_none(), true);
+        let result = symbol_file.functions.get(address);
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_lookup_range() {
+        let mut builder = SymbolFileBuilder::new();
+        for (address, size) in [(0x10, 0x10), (0x20, 0x8), (0x30, 0x10), (0x50, 0x4)] {
+            builder.add_function(address, size, 0, "");
+        }
+        let symbol_file = builder.build();
+
+        let result = lookup_range(&symbol_file, 0x18, 0x38);
+        let addresses: Vec<u64> = result.iter().map(|f| f.address).collect();
+        assert_eq!(addresses, vec![0x10, 0x20, 0x30]);
+
+        // 0x28..0x30 is a gap between functions
+        assert!(lookup_range(&symbol_file, 0x28, 0x30).is_empty());
+
+        assert_eq!(parse_address_range("0x10000..0x20000"), Some((0x10000, 0x20000)));
+        assert_eq!(parse_address_range("0x20000..0x10000"), None);
+        assert_eq!(parse_address_range("0x20000"), None);
+    }
+
+    #[test]
+    fn test_source_basename() {
+        assert_eq!(source_basename("/Users/builduser/unity/Runtime/Misc/Player.cpp"), "Player.cpp");
+        assert_eq!(source_basename("C:\\build\\Runtime\\Player.cpp"), "Player.cpp");
+        assert_eq!(source_basename("Player.cpp"), "Player.cpp");
+        assert_eq!(source_basename(""), "");
+        assert_eq!(source_dir("/Users/builduser/unity/Runtime/Misc/Player.cpp"), "/Users/builduser/unity/Runtime/Misc");
+        assert_eq!(source_dir("C:\\build\\Runtime\\Player.cpp"), "C:\\build\\Runtime");
+        assert_eq!(source_dir("Player.cpp"), "");
+    }
+
+    #[test]
+    fn test_canonical_frame() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "/buildslave/il2cpp/os/Thread.cpp")
+            .add_function(0x100, 0x200, 0, "il2cpp::os::Thread::RunWrapper(void*)")
+            .add_line(0x100, 0x200, 88, 1)
+            .add_public(0x400, 0, "start");
+        let symbol_file = builder.build();
+        let frame = |module, address, simplify| canonical_frame(module, address, lookup_address(&symbol_file, address).as_ref(), simplify);
+        assert_eq!(frame("/data/app/lib/arm64/libil2cpp.so", 0x2c4, NameSimplification::None), "libil2cpp.so!il2cpp::os::Thread::RunWrapper(void*)+0x1c4 (Thread.cpp:88)");
+        assert_eq!(frame("libil2cpp.so", 0x2c4, NameSimplification::Parameters), "libil2cpp.so!il2cpp::os::Thread::RunWrapper+0x1c4 (Thread.cpp:88)");
+        assert_eq!(frame("libil2cpp.so", 0x408, NameSimplification::None), "libil2cpp.so!start+0x8");
+        assert_eq!(frame("libil2cpp.so", 0x50, NameSimplification::None), "libil2cpp.so+0x50");
+    }
+
+    #[test]
+    fn test_all_symbols() {
+        let mut symbol_file = SymbolFile::new();
+        symbol_file.add_record(parse_record("FUNC 20 10 0 b").unwrap());
+        symbol_file.add_record(parse_record("FUNC 40 8 0 d").unwrap());
+        symbol_file.add_record(parse_record("PUBLIC 30 0 c").unwrap());
+        symbol_file.add_record(parse_record("PUBLIC 10 0 a").unwrap());
+
+        let records = all_symbols(&symbol_file);
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        assert_eq!(records[0].kind, SymbolKind::Public);
+        assert_eq!(records[0].size, 0);
+        assert_eq!(records[1].kind, SymbolKind::Func);
+        assert_eq!(records[1].size, 0x10);
+
+        symbol_file.add_record(parse_record("PUBLIC 18 8 e").unwrap());
+        let publics: Vec<_> = symbol_file.public_symbols().collect();
+        assert_eq!(publics, vec![(0x10, 0, "a"), (0x18, 8, "e"), (0x30, 0, "c")]);
+        assert_eq!(symbol_file.public_symbols().next_back(), Some((0x30, 0, "c")));
+    }
+
+    #[test]
+    fn test_symbol_file_builder() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "a.cpp")
+            .add_function(0x100, 0x10, 4, "foo")
+            .add_line(0x100, 0x8, 10, 1)
+            .add_line(0x108, 0x8, 11, 1)
+            .add_public(0x200, 8, "bar");
+        let symbol_file = builder.build();
+
+        let symbol = lookup_address(&symbol_file, 0x10a).unwrap();
+        assert_eq!(symbol.function_name, "foo");
+        assert_eq!(symbol.source_file_name, "a.cpp");
+        assert_eq!(symbol.source_file_number, 11);
+        assert_eq!((symbol.source_dir(), symbol.source_basename()), ("", "a.cpp"));
+        assert_eq!(symbol.stack_param_size, 4);
+        assert_eq!((symbol.function_address, symbol.function_size), (Some(0x100), Some(0x10)));
+        assert_eq!(symbol.function_offset(0x10a), Some(0xa));
+        assert!(!symbol.is_near_function_end(0x10a, 4));
+        assert!(symbol.is_near_function_end(0x10c, 4));
+        assert!(symbol.is_near_function_end(0x110, 4));
+        assert!(!symbol.is_near_function_end(0x111, 4));
+
+        let symbol = lookup_address(&symbol_file, 0x204).unwrap();
+        assert_eq!(symbol.function_name, "bar");
+        assert_eq!(symbol.source_file_number, -1);
+        assert_eq!(symbol.stack_param_size, 8);
+        assert_eq!((symbol.function_address, symbol.function_size), (Some(0x200), None));
+        assert!(!symbol.is_near_function_end(0x204, 4));
+        assert_eq!(symbol.kind, SymbolKind::Public);
+        assert_eq!(lookup_address(&symbol_file, 0x10a).unwrap().kind, SymbolKind::Func);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(0, "app.cpp")
+            .add_function(0x100, 0x20, 0, "app_func")
+            .add_line(0x100, 0x20, 7, 0);
+        let mut symbol_file = builder.build();
+
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(0, "plt.s")
+            .add_file(1, "app.cpp")
+            .add_function(0x110, 0x10, 0, "shadowed")
+            .add_function(0x200, 0x10, 0, "system_func")
+            .add_line(0x200, 0x10, 3, 0)
+            .add_public(0x100, 0, "app_func_public")
+            .add_public(0x300, 0, "plt_stub");
+        symbol_file.merge(builder.build());
+
+        let symbol = lookup_address(&symbol_file, 0x118).unwrap();
+        assert_eq!(symbol.function_name, "app_func");
+        assert_eq!(symbol.source_file_name, "app.cpp");
+
+        let symbol = lookup_address(&symbol_file, 0x204).unwrap();
+        assert_eq!(symbol.function_name, "system_func");
+        assert_eq!(symbol.source_file_name, "plt.s");
+        assert_eq!(symbol.source_file_number, 3);
+
+        assert_eq!(lookup_address(&symbol_file, 0x304).unwrap().function_name, "plt_stub");
+        assert_eq!(symbol_file.public_symbols.get(&0x100).unwrap().name, "app_func_public");
+        assert_eq!(symbol_file.files.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_module_line() {
+        let mut symbol_file = SymbolFile::new();
+        symbol_file.add_record(parse_record("MODULE mac arm64 EE27598B6A283E389075D1014679C8B00 Unity Framework").unwrap());
+        let module = symbol_file.module().unwrap();
+        assert_eq!(module.os, "mac");
+        assert_eq!(module.arch, "arm64");
+        assert_eq!(module.debug_id, "EE27598B6A283E389075D1014679C8B00");
+        assert_eq!(module.name, "Unity Framework");
+
+        symbol_file.add_record(parse_record("INFO CODE_ID B4B2BD4FB3AC2B43F6AD8E4A87BA9D4BB8C8D8A1").unwrap());
+        assert_eq!(symbol_file.code_id(), Some("B4B2BD4FB3AC2B43F6AD8E4A87BA9D4BB8C8D8A1"));
+        assert_eq!(symbol_file.code_file(), None);
+        symbol_file.add_record(parse_record("INFO CODE_ID 5F1A2B3C1000 UnityPlayer.dll").unwrap());
+        assert_eq!(symbol_file.code_id(), Some("5F1A2B3C1000"));
+        assert_eq!(symbol_file.code_file(), Some("UnityPlayer.dll"));
+    }
+
+    #[test]
+    fn test_write_breakpad() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm", "0123456789ABCDEF0123456789ABCDEF0", "libunity.so")
+            .set_code_id("67452301AB89EFCD0123456789ABCDEF01234567", None)
+            .add_file(1, "b.cpp")
+            .add_file(0, "a.cpp")
+            .add_function(0x200, 0x10, 0, "bar")
+            .add_line(0x200, 0x10, 20, 1)
+            .add_line(0x80, 0x10, 5, 1)
+            .add_function(0x100, 0x20, 4, "foo(int)")
+            .add_line(0x110, 0x10, 11, 0)
+            .add_line(0x100, 0x10, 10, 0)
+            .add_public(0x300, 0, "baz");
+        let symbol_file = builder.build();
+
+        let mut out = Vec::new();
+        symbol_file.write_breakpad(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "MODULE Linux arm 0123456789ABCDEF0123456789ABCDEF0 libunity.so\n\
+             INFO CODE_ID 67452301AB89EFCD0123456789ABCDEF01234567\n\
+             FILE 0 a.cpp\n\
+             FILE 1 b.cpp\n\
+             FUNC 100 20 4 foo(int)\n\
+             100 10 10 0\n\
+             110 10 11 0\n\
+             FUNC 200 10 0 bar\n\
+             200 10 20 1\n\
+             PUBLIC 300 0 baz\n"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_files() {
+        let symbol_file: SymbolFile = "FILE 3 a.cpp\nFILE 7 b.cpp\nFILE 9 a.cpp\nFILE 12 unused.h\n\
+                                       FUNC 100 30 0 foo\n100 10 1 9\n110 10 2 7\n120 10 3 42\n"
+            .parse()
+            .unwrap();
+        assert_eq!(symbol_file.duplicate_files(), 1);
+        assert_eq!(symbol_file.files().collect::<Vec<_>>(), vec![(3, "a.cpp"), (7, "b.cpp"), (12, "unused.h")]);
+        assert_eq!(symbol_file.lookup_line(0x100).unwrap().source_file_name, "a.cpp");
+
+        let mut out = Vec::new();
+        symbol_file.write_breakpad_with_options(&mut out, &WriteOptions { compact_files: true }).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "FILE 0 a.cpp\n\
+             FILE 1 b.cpp\n\
+             FUNC 100 30 0 foo\n\
+             100 10 1 0\n\
+             110 10 2 1\n\
+             120 10 3 2\n"
+        );
+    }
+
+    #[test]
+    fn test_write_breakpad_unparsed_records() {
+        let text = "MODULE Linux arm64 0123456789ABCDEF0123456789ABCDEF0 libfoo.so\n\
+                    INFO CODE_ID ABCD\n\
+                    INFO GENERATOR dump_syms 2.3.0\n\
+                    FILE 0 a.cpp\nFILE 1 unused.h\nFILE 2 a.cpp\nFILE 3 inl.h\n\
+                    INLINE_ORIGIN 0 inlined()\n\
+                    FUNC 100 20 0 foo\n\
+                    INLINE 0 12 2 0 108 8\n\
+                    INLINE 1 30 3 0 10c 4\n\
+                    100 10 10 0\n110 10 11 3\n\
+                    PUBLIC 200 0 public\n\
+                    STACK CFI INIT 100 20 .cfa: sp 0 + .ra: lr\n\
+                    STACK CFI 104 .cfa: sp 16 +\n\
+                    STACK WIN 4 200 10 0 0 0 0 0 0 1 $eip 4 + ^ =\n";
+        let options = ParserOptions {
+            keep_unparsed_records: true,
+            ..ParserOptions::default()
+        };
+        let compact = |symbol_file: &SymbolFile| {
+            let mut out = Vec::new();
+            symbol_file.write_breakpad_with_options(&mut out, &WriteOptions { compact_files: true }).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let expected = "MODULE Linux arm64 0123456789ABCDEF0123456789ABCDEF0 libfoo.so\n\
+                        INFO CODE_ID ABCD\n\
+                        INFO GENERATOR dump_syms 2.3.0\n\
+                        FILE 0 a.cpp\nFILE 1 inl.h\n\
+                        INLINE_ORIGIN 0 inlined()\n\
+                        FUNC 100 20 0 foo\n\
+                        INLINE 0 12 0 0 108 8\n\
+                        INLINE 1 30 1 0 10c 4\n\
+                        100 10 10 0\n110 10 11 1\n\
+                        PUBLIC 200 0 public\n\
+                        STACK CFI INIT 100 20 .cfa: sp 0 + .ra: lr\n\
+                        STACK CFI 104 .cfa: sp 16 +\n\
+                        STACK WIN 4 200 10 0 0 0 0 0 0 1 $eip 4 + ^ =\n";
+
+        let symbol_file = SymbolFile::from_reader_with_options(text.as_bytes(), &options).unwrap();
+        assert!(symbol_file.diagnostics().is_empty());
+        assert_eq!(compact(&symbol_file), expected);
+        let written = SymbolFile::from_reader_with_options(expected.as_bytes(), &options).unwrap();
+        assert_eq!(compact(&written), expected);
+
+        // Without keeping them, writing fails rather than losing them.
+        let symbol_file: SymbolFile = text.parse().unwrap();
+        let err = symbol_file.write_breakpad(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_decompressing_reader() {
+        use std::io::Cursor;
+
+        let text = "MODULE mac arm64 0 a\nFUNC 10 4 0 f\n";
+        let read_all = |bytes: Vec<u8>| {
+            let mut out = String::new();
+            decompressing_reader(Cursor::new(bytes)).unwrap().read_to_string(&mut out).unwrap();
+            out
+        };
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(text.as_bytes()).unwrap();
+        assert_eq!(read_all(gz.finish().unwrap()), text);
+
+        let zst = zstd::stream::encode_all(text.as_bytes(), 0).unwrap();
+        assert_eq!(read_all(zst), text);
+
+        assert_eq!(read_all(text.as_bytes().to_vec()), text);
+    }
+
+    #[test]
+    fn test_build_id_to_debug_id() {
+        assert_eq!(
+            build_id_to_debug_id("b4b2bd4fb3ac2b43f6ad8e4a87ba9d4bb8c8d8a1"),
+            "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0"
+        );
+        // Short build ids are zero padded.
+        assert_eq!(build_id_to_debug_id("01020304"), "040302010000000000000000000000000");
+    }
+
+    #[test]
+    fn test_find_symbol_file() {
+        let root = std::env::temp_dir().join(format!("addr2line_breakpad_symbol_path_{}", std::process::id()));
+        let (ci, shared) = (root.join("ci"), root.join("shared"));
+        let store = shared.join("libunity.so/4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0");
+        std::fs::create_dir_all(&ci).unwrap();
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::write(store.join("libunity.so.sym"), "").unwrap();
+        std::fs::write(shared.join("libunity.so.sym"), "").unwrap();
+        std::fs::write(ci.join("libmain.so.sym"), "").unwrap();
+
+        let dirs = vec![ci.clone(), shared.clone()];
+        assert_eq!(find_symbol_file(&dirs, "libmain.so", None), Some(ci.join("libmain.so.sym")));
+        assert_eq!(find_symbol_file(&dirs, "libunity.so", None), Some(shared.join("libunity.so.sym")));
+        assert_eq!(
+            find_symbol_file(&dirs, "libunity.so", Some("4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0")),
+            Some(store.join("libunity.so.sym"))
+        );
+        assert_eq!(find_symbol_file(&dirs, "libil2cpp.so", None), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_symbol_file_for_arch() {
+        let root = std::env::temp_dir().join(format!("addr2line_breakpad_symbol_arch_{}", std::process::id()));
+        let (arm64, arm) = (root.join("arm64-v8a"), root.join("armeabi-v7a"));
+        std::fs::create_dir_all(&arm64).unwrap();
+        std::fs::create_dir_all(&arm).unwrap();
+        std::fs::write(arm.join("libil2cpp.so.sym"), "MODULE Linux arm 0102 libil2cpp.so\n").unwrap();
+        std::fs::write(arm64.join("libil2cpp.so.sym"), "MODULE Linux arm64 0304 libil2cpp.so\n").unwrap();
+        std::fs::write(arm.join("libunity.so.sym"), "MODULE Linux arm 0506 libunity.so\n").unwrap();
+
+        let dirs = vec![root.clone()];
+        assert_eq!(find_symbol_file_for_arch(&dirs, "libil2cpp.so", None, "arm64"), Ok(Some(arm64.join("libil2cpp.so.sym"))));
+        assert_eq!(find_symbol_file_for_arch(&dirs, "libil2cpp.so", None, "armeabi-v7a"), Ok(Some(arm.join("libil2cpp.so.sym"))));
+        assert_eq!(
+            find_symbol_file_for_arch(&dirs, "libunity.so", None, "ARM-64 (Native)"),
+            Err(String::from("symbol files for libunity.so are only for arm, not arm64"))
+        );
+        assert_eq!(find_symbol_file_for_arch(&dirs, "libmain.so", None, "arm64"), Ok(None));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_arch() {
+        assert_eq!(normalize_arch("ARM-64 (Native)"), "arm64");
+        assert_eq!(normalize_arch("arm64-v8a"), "arm64");
+        assert_eq!(normalize_arch("armeabi-v7a"), "arm");
+        assert_eq!(normalize_arch("X86-64"), "x86_64");
+        assert_eq!(normalize_arch("ppc"), "ppc");
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_progress_{}.sym", std::process::id()));
+        let mut text = String::from("MODULE mac arm64 EE27598B6A283E389075D1014679C8B00 UnityFramework\n");
+        for i in 0..40000 {
+            text.push_str(&format!("FUNC {:x} 10 0 function_{}\n", i * 0x10, i));
+        }
+        std::fs::write(&path, &text).unwrap();
+
+        let calls = Rc::new(RefCell::new(vec![]));
+        let recorded = calls.clone();
+        let options = ParserOptions {
+            progress: Some(Box::new(move |parsed, total| recorded.borrow_mut().push((parsed, total)))),
+            ..ParserOptions::default()
+        };
+        let symbol_file = parse_breakpad_symbol_source_with_options(&path, None, &options).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(symbol_file.functions.len(), 40000);
+        let calls = calls.borrow();
+        let total = text.len() as u64;
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(*calls.last().unwrap(), (total, Some(total)));
+    }
+
+    #[test]
+    fn test_malformed_line_diagnostics() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_malformed_{}.sym", std::process::id()));
+        std::fs::write(
+            &path,
+            "MODULE Linux arm64 4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0 libunity.so\n\
+             FILE 0 a.cpp\n\
+             FUNC 100 10 0 good\n\
+             100 10 7 0\n\
+             FUNC zz 10 0 bad_address\n\
+             INLINE_ORIGIN 0 inlined\n\
+             SYMBOL 0 unknown\n\
+             110 10\n\
+             \n\
+             PUBLIC 200 0 public\n",
+        )
+        .unwrap();
+        let symbol_file = parse_breakpad_symbol_file(&path);
+        let strict = ParserOptions {
+            mode: ParseMode::Strict,
+            ..ParserOptions::default()
+        };
+        let error = parse_breakpad_symbol_source_with_options(&path, None, &strict).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.to_string(), "line 5: invalid address \"zz\": FUNC zz 10 0 bad_address");
+
+        let diagnostics = symbol_file.diagnostics();
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line_number, 5);
+        assert_eq!(diagnostics[0].message, "invalid address \"zz\"");
+        assert_eq!(diagnostics[1].line, "SYMBOL 0 unknown");
+        assert_eq!(diagnostics[1].message, "unknown record type");
+        assert_eq!(diagnostics[2].line_number, 8);
+        assert_eq!(diagnostics[2].message, "missing line number");
+
+        assert_eq!(lookup_address(&symbol_file, 0x104).unwrap().source_file_number, 7);
+        assert_eq!(lookup_address(&symbol_file, 0x204).unwrap().function_name, "public");
+    }
+
+    #[test]
+    fn test_strict_parse_dump_syms_output() {
+        // Every record kind current dump_syms writes.
+        let text = "MODULE Linux arm64 4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0 libunity.so\n\
+                    INFO CODE_ID B4B2BD4FB3AC2B43F6AD8E4A87BA9D4B\n\
+                    INFO GENERATOR dump_syms 2.3.3\n\
+                    FILE 0 a.cpp\n\
+                    INLINE_ORIGIN 0 inlined_helper()\n\
+                    FUNC 100 20 0 outer()\n\
+                    INLINE 0 12 0 0 108 8\n\
+                    100 8 10 0\n\
+                    108 8 3 0\n\
+                    110 10 14 0\n\
+                    PUBLIC 200 0 public\n\
+                    STACK CFI INIT 100 20 .cfa: sp 0 + .ra: x30\n\
+                    STACK CFI 104 .cfa: sp 16 +\n\
+                    STACK WIN 4 100 20 0 0 0 0 0 0 1 $eip 4 + ^ =\n";
+        let strict = ParserOptions {
+            mode: ParseMode::Strict,
+            ..ParserOptions::default()
+        };
+        let symbol_file = SymbolFile::from_reader_with_options(text.as_bytes(), &strict).unwrap();
+        assert!(symbol_file.diagnostics().is_empty());
+        assert_eq!(lookup_address(&symbol_file, 0x10c).unwrap().source_file_number, 3);
+        assert_eq!(lookup_address(&symbol_file, 0x204).unwrap().function_name, "public");
+    }
+
+    #[test]
+    fn test_lookup_address_with_options() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_public(0x80, 0, "before_foo")
+            .add_function(0x100, 0x40, 0, "foo")
+            .add_public(0x100, 0, "_foo")
+            .add_function(0x200, 0x10, 0, "bar");
+        let symbol_file = builder.build();
+        let names = |address, prefer, both| {
+            let options = LookupOptions { prefer, both };
+            lookup_address_with_options(&symbol_file, address, &options)
+                .into_iter()
+                .map(|symbol| symbol.function_name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(0x104, MatchPreference::Func, false), vec!["foo"]);
+        assert_eq!(names(0x104, MatchPreference::Public, false), vec!["_foo"]);
+        assert_eq!(names(0x104, MatchPreference::Public, true), vec!["_foo", "foo"]);
+        assert_eq!(names(0x104, MatchPreference::Func, true), vec!["foo", "_foo"]);
+        // _foo starts before bar, so it doesn't compete with it.
+        assert_eq!(names(0x204, MatchPreference::Public, true), vec!["bar"]);
+        assert_eq!(names(0x180, MatchPreference::Func, true), vec!["_foo"]);
+        assert_eq!(names(0x90, MatchPreference::Public, true), vec!["before_foo"]);
+        assert_eq!(lookup_address(&symbol_file, 0x104).unwrap().function_name, "foo");
+    }
+
+    #[test]
+    fn test_lookup_addresses() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "a.cpp")
+            .add_function(0x100, 0x10, 0, "foo")
+            .add_line(0x100, 0x8, 10, 1)
+            .add_line(0x108, 0x8, 11, 1)
+            .add_function(0x180, 0x20, 0, "baz")
+            .add_public(0x50, 0, "start")
+            .add_public(0x200, 0, "bar");
+        let symbol_file = builder.build();
+
+        let describe = |symbol: Option<Symbol>| symbol.map(|s| (s.function_name.into_owned(), s.source_file_number));
+        let sorted = [0x0, 0x60, 0x100, 0x100, 0x10a, 0x140, 0x190, 0x204, 0x30000];
+        let unsorted = [0x204, 0x10a, 0x0, 0x190, 0x100];
+        for addresses in [&sorted[..], &unsorted[..]] {
+            let expected: Vec<_> = addresses.iter().map(|&a| describe(lookup_address(&symbol_file, a))).collect();
+            let batch: Vec<_> = lookup_addresses(&symbol_file, addresses).into_iter().map(describe).collect();
+            assert_eq!(batch, expected);
+        }
+        assert_eq!(describe(lookup_addresses(&symbol_file, &sorted).remove(4)), Some(("foo".to_string(), 11)));
+
+        // Nearby addresses going back and forth, as the frames of a stack.
+        let mut cursor = symbol_file.lookup_cursor();
+        for address in [0x10a, 0x100, 0x104, 0x18c, 0x60, 0x204, 0x20] {
+            assert_eq!(describe(cursor.lookup(address)), describe(lookup_address(&symbol_file, address)), "{:#x}", address);
+        }
+    }
+
+    #[test]
+    fn test_lookup_function_and_line() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "a.cpp")
+            .add_function(0x100, 0x10, 4, "foo")
+            .add_line(0x100, 0x8, 10, 1)
+            .add_line(0x108, 0x8, 11, 2)
+            .add_public(0x200, 0, "bar");
+        let symbol_file = builder.build();
+
+        let foo = symbol_file.lookup_function(0x10a).unwrap();
+        assert_eq!((foo.name, foo.address, foo.size, foo.kind, foo.stack_param_size), ("foo", 0x100, Some(0x10), SymbolKind::Func, 4));
+        let bar = symbol_file.lookup_function(0x204).unwrap();
+        assert_eq!((bar.name, bar.size, bar.kind), ("bar", None, SymbolKind::Public));
+        assert_eq!(symbol_file.lookup_function(0x50), None);
+        assert_eq!(symbol_file.lookup_function(0x30000), None);
+
+        let line = symbol_file.lookup_line(0x104).unwrap();
+        assert_eq!((line.line_number, line.source_file_name), (10, Cow::Borrowed("a.cpp")));
+        assert!(matches!(symbol_file.lookup_line(0x10a).unwrap().source_file_name, Cow::Owned(name) if name == "<unknown-file-id:2>"));
+        assert_eq!(symbol_file.lookup_line(0x204), None);
+        for address in [0x104, 0x10a, 0x204] {
+            let symbol = lookup_address(&symbol_file, address).unwrap();
+            assert_eq!(symbol_file.lookup_function(address).unwrap().name, symbol.function_name);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut builder = SymbolFileBuilder::new();
+        builder.add_function(0x100, 0x10, 0, "f").add_public(0x1000, 0, "last_public");
+        let symbol_file = builder.build();
+
+        assert_eq!(symbol_file.end_address(), Some(0x1000 + PUBLIC_SLACK));
+        assert_eq!(lookup_address(&symbol_file, 0x1100).unwrap().function_name, "last_public");
+        assert!(symbol_file.is_out_of_range(0x20000));
+        assert!(lookup_address(&symbol_file, 0x20000).is_none());
+
+        let mut builder = SymbolFileBuilder::new();
+        builder.add_function(0x100, 0x10, 0, "f");
+        let symbol_file = builder.build();
+        assert!(!symbol_file.is_out_of_range(0x10f));
+        assert!(symbol_file.is_out_of_range(0x110));
+        assert!(!SymbolFileBuilder::new().build().is_out_of_range(0x110));
+
+        // A FUNC running past the end of the address space.
+        let symbol_file = SymbolFile::from_bytes(b"FUNC ffffffffffffff00 1000 0 f\n").unwrap();
+        assert_eq!(symbol_file.end_address(), Some(u64::MAX));
+        assert_eq!(lookup_address(&symbol_file, 0xffffffffffffff10).unwrap().function_name, "f");
+        assert!(!symbol_file.is_out_of_range(0x10));
+        // The last FUNC is not the one ending last.
+        let mut builder = SymbolFileBuilder::new();
+        builder.add_function(0x100, 0x1000, 0, "f").add_function(0x200, 0x10, 0, "g");
+        assert_eq!(builder.build().end_address(), Some(0x1100));
+    }
+
+    #[test]
+    fn test_lines_for_function() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(0, "a.cpp")
+            .add_file(1, "a.h")
+            .add_function(0x100, 0x20, 0, "f")
+            .add_line(0x110, 0x8, 12, 0)
+            .add_line(0x100, 0x10, 10, 0)
+            .add_line(0x118, 0x8, 3, 1)
+            .add_function(0x120, 0x10, 0, "g")
+            .add_line(0x120, 0x10, 20, 0)
+            .add_function(0x200, 0x10, 0, "no_lines");
+        let symbol_file = builder.build();
+
+        let lines = symbol_file.lines_for_function(0x11c);
+        let lines: Vec<_> = lines.iter().map(|line| (line.address, line.line_number, line.source_file_name.as_str())).collect();
+        assert_eq!(lines, vec![(0x100, 10, "a.cpp"), (0x110, 12, "a.cpp"), (0x118, 3, "a.h")]);
+        assert_eq!(symbol_file.lines_for_function(0x120)[0].line_number, 20);
+        assert!(symbol_file.lines_for_function(0x204).is_empty());
+        assert!(symbol_file.lines_for_function(0x300).is_empty());
+        assert_eq!(symbol_file.function_addresses("g"), vec![0x120]);
+        assert!(symbol_file.function_addresses("h").is_empty());
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let empty = SymbolFileBuilder::new().build().memory_usage();
+        let mut builder = SymbolFileBuilder::new();
+        builder.add_file(0, "a.c").add_function(0x100, 0x10, 0, "f").add_line(0x100, 0x10, 1, 0).add_public(0x1000, 0, "last_public");
+        let symbol_file = builder.build();
+        assert!(symbol_file.memory_usage() > empty + "a.c".len() + "f".len() + "last_public".len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_from_bytes() {
+        let symbol_file = SymbolFile::from_bytes(b"FILE 0 a.c\nFUNC 10 8 0 f\n10 8 3 0\nFUNC zz\n").unwrap();
+        assert_eq!(lookup_address(&symbol_file, 0x14).unwrap().source_file_number, 3);
+        assert_eq!(symbol_file.diagnostics()[0].line_number, 4);
+        assert!(SymbolFile::from_bytes(b"").unwrap().module().is_none());
+
+        let text = "MODULE Linux arm64 0102 libfoo.so\nFUNC 10 8 0 f\n";
+        let symbol_file: SymbolFile = text.parse().unwrap();
+        assert_eq!(symbol_file.module().unwrap().name, "libfoo.so");
+        let symbol_file = SymbolFile::from_reader(io::BufReader::new(text.as_bytes())).unwrap();
+        assert_eq!(lookup_address(&symbol_file, 0x14).unwrap().function_name, "f");
+        let strict = ParserOptions {
+            mode: ParseMode::Strict,
+            ..ParserOptions::default()
+        };
+        let error = SymbolFile::from_reader_with_options(&b"FUNC 10 8 0 f\nFUNC zz\n"[..], &strict).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: missing size: FUNC zz");
+    }
+
+    #[test]
+    fn test_parse_module_address() {
+        assert_eq!(parse_module_address("0x1234"), Some((None, 0x1234)));
+        assert_eq!(parse_module_address("libil2cpp.so+0x1234"), Some((Some("libil2cpp.so"), 0x1234)));
+        assert_eq!(
+            parse_module_address("/data/app/com.example/lib/arm64/libunity.so+1a0"),
+            Some((Some("/data/app/com.example/lib/arm64/libunity.so"), 0x1a0))
+        );
+        assert_eq!(parse_module_address("libfoo.so+"), None);
+        assert_eq!(parse_module_address("+0x10"), None);
+        assert_eq!(parse_module_address("libfoo.so+0xzz"), None);
+    }
+
+    #[test]
+    fn test_normalize_code_address() {
+        assert_eq!(normalize_code_address("arm", 0x1235), 0x1234);
+        assert_eq!(normalize_code_address("armeabi-v7a", 0x1235), 0x1234);
+        assert_eq!(normalize_code_address("arm64", 0x1235), 0x1235);
+        assert_eq!(normalize_code_address("x86", 0x1235), 0x1235);
+
+        // A Thumb function pointer to `b` is b+0, not b+1.
+        let mut builder = SymbolFileBuilder::new();
+        builder.set_module("Linux", "arm", "0102", "libfoo.so").add_function(0x100, 0x10, 0, "a").add_function(0x110, 0x10, 0, "b");
+        let symbol_file = builder.build();
+        let address = normalize_code_address(&symbol_file.module().unwrap().arch, 0x111);
+        let symbol = lookup_address(&symbol_file, address).unwrap();
+        assert_eq!((symbol.function_name.as_ref(), symbol.function_offset(address)), ("b", Some(0)));
+    }
+
+    #[test]
+    fn test_caller_address() {
+        assert_eq!(caller_address("arm64", 0x110), 0x10c);
+        assert_eq!(caller_address("arm", 0x111), 0x10e);
+        assert_eq!(caller_address("x86_64", 0x110), 0x10f);
+        assert_eq!(caller_address("", 0), 0);
+        for arch in ["arm64-v8a", "ARM-64 (Native)", "AArch64"] {
+            assert_eq!(caller_address(arch, 0x110), 0x10c, "{}", arch);
+        }
+        assert_eq!(caller_address("ARM (Native)", 0x111), 0x10e);
+
+        // A call as the last instruction of `a` returns to the start of `b`.
+        let mut builder = SymbolFileBuilder::new();
+        builder.set_module("mac", "arm64", "0102", "foo").add_function(0x100, 0x10, 0, "a").add_function(0x110, 0x10, 0, "b");
+        let symbol_file = builder.build();
+        assert_eq!(lookup_address(&symbol_file, caller_address("arm64", 0x110)).unwrap().function_name, "a");
+    }
+
+    #[test]
+    fn test_unknown_file_placeholder() {
+        // FILE 42 was lost, as in a truncated upload.
+        let text = b"FILE 0 a.c\nFUNC 10 8 0 f\n10 4 3 0\n14 4 5 42\nFUNC 20 8 0 g\n20 8 7 42\n";
+        let mut symbol_file = SymbolFile::from_bytes(text).unwrap();
+        assert_eq!(symbol_file.undefined_file_lines(), 2);
+        assert!(symbol_file.diagnostics().is_empty());
+        assert_eq!(lookup_address(&symbol_file, 0x10).unwrap().source_file_name, "a.c");
+        let symbol = lookup_address(&symbol_file, 0x14).unwrap();
+        assert_eq!((symbol.source_file_name.as_ref(), symbol.source_file_number), ("<unknown-file-id:42>", 5));
+        assert_eq!(lookup_range(&symbol_file, 0x20, 0x28)[0].source_file_name, "<unknown-file-id:42>");
+
+        symbol_file.set_unknown_file_placeholder("");
+        assert_eq!(symbol_file.lines_for_function(0x14)[1].source_file_name, "");
+
+        let options = ParserOptions {
+            unknown_file_placeholder: Some(String::from("missing FILE {id}")),
+            ..ParserOptions::default()
+        };
+        let symbol_file = parse_symbol_reader(&text[..], &"<memory>", None, &options).unwrap();
+        assert_eq!(lookup_address(&symbol_file, 0x24).unwrap().source_file_name, "missing FILE 42");
+    }
+
+    #[test]
+    fn test_parse_crlf_and_bom() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_crlf_{}.sym", std::process::id()));
+        std::fs::write(
+            &path,
+            "\u{feff}MODULE windows x86_64 5F1A2B3C4D5E6F708192A3B4C5D6E7F81 UnityPlayer.pdb\r\n\
+             FILE 0 C:\\unity\\Runtime\\Player.cpp\r\n\
+             FUNC 1000 20 0 PlayerLoop()\r\n\
+             1000 20 42 0\r\n\
+             PUBLIC 2000 0 PlayerMain\r",
+        )
+        .unwrap();
+        let symbol_file = parse_breakpad_symbol_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(symbol_file.diagnostics().is_empty());
+        assert_eq!(symbol_file.module().unwrap().name, "UnityPlayer.pdb");
+        let symbol = lookup_address(&symbol_file, 0x1004).unwrap();
+        assert_eq!(symbol.function_name, "PlayerLoop()");
+        assert_eq!(symbol.source_file_name, "C:\\unity\\Runtime\\Player.cpp");
+        assert_eq!(lookup_address(&symbol_file, 0x2004).unwrap().function_name, "PlayerMain");
+    }
+
+    #[test]
+    fn test_parse_invalid_utf8() {
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_utf8_{}.sym", std::process::id()));
+        let mut data = b"MODULE Linux arm64 4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0 libsdk.so\nFUNC 100 10 0 sdk_".to_vec();
+        data.extend_from_slice(&[0xff, 0xfe]);
+        data.extend_from_slice(b"_init\nFUNC 200 10 0 sdk_shutdown\n");
+        std::fs::write(&path, data).unwrap();
+        let symbol_file = parse_breakpad_symbol_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(lookup_address(&symbol_file, 0x104).unwrap().function_name, "sdk_\u{fffd}\u{fffd}_init");
+        assert_eq!(lookup_address(&symbol_file, 0x204).unwrap().function_name, "sdk_shutdown");
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::{normalize_line, open_symbol_file, parse_record, LossyLines, Record};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "snake_case"))]
+pub enum LintKind {
+    /// No MODULE record, or not on the first line.
+    MissingModule,
+    /// A line the parser skips.
+    Malformed,
+    /// A LINE record naming a FILE id no FILE record defines.
+    UndefinedFile,
+    /// A LINE record not inside the FUNC it follows.
+    LineOutsideFunction,
+    /// A FUNC starting inside another one.
+    OverlappingFunction,
+    /// A FUNC, PUBLIC, or LINE of one FUNC, below the one before it.
+    UnsortedAddress,
+}
+
+impl LintKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintKind::MissingModule => "missing_module",
+            LintKind::Malformed => "malformed",
+            LintKind::UndefinedFile => "undefined_file",
+            LintKind::LineOutsideFunction => "line_outside_function",
+            LintKind::OverlappingFunction => "overlapping_function",
+            LintKind::UnsortedAddress => "unsorted_address",
+        }
+    }
+}
+
+/// A structural problem of a symbol file found by [`lint_symbol_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LintIssue {
+    pub kind: LintKind,
+    /// 1-based line number, None for problems of the whole file.
+    pub line_number: Option<usize>,
+    pub message: String,
+}
+
+struct FuncExtent {
+    line_number: usize,
+    address: u64,
+    end: u64,
+}
+
+// The problems that matter when the file is read in order, like the dump_syms
+// output it should be. Issues come in line order, file-level ones first.
+pub fn lint_symbol_reader(reader: impl BufRead) -> io::Result<Vec<LintIssue>> {
+    let mut issues = vec![];
+    let mut issue = |kind, line_number, message| {
+        issues.push(LintIssue {
+            kind,
+            line_number,
+            message,
+        })
+    };
+    let mut saw_module = false;
+    let mut file_ids = HashSet::new();
+    // LINE records naming ids not defined yet; FILE records may come later.
+    let mut file_references: Vec<(usize, i64)> = vec![];
+    let mut functions: Vec<FuncExtent> = vec![];
+    let mut last_public: Option<u64> = None;
+    let mut last_line: Option<u64> = None;
+    let mut first_record = true;
+    for (line_number, line) in LossyLines::new(reader).enumerate() {
+        let (mut line, _) = line?;
+        normalize_line(line_number, &mut line);
+        let line_number = line_number + 1;
+        let record = match parse_record(&line) {
+            Ok(Record::Unparsed(_) | Record::Ignored) => continue,
+            Ok(record) => record,
+            Err(message) => {
+                issue(LintKind::Malformed, Some(line_number), format!("{}: {}", message, line));
+                continue;
+            }
+        };
+        match record {
+            Record::Module(_) if !first_record => {
+                saw_module = true;
+                issue(LintKind::MissingModule, Some(line_number), String::from("MODULE record is not the first record"));
+            }
+            Record::Module(_) => saw_module = true,
+            Record::File(id, _) => {
+                file_ids.insert(id);
+            }
+            Record::Func(function) => {
+                let end = function.address.saturating_add(function.size);
+                if let Some(previous) = functions.last().filter(|previous| function.address < previous.address) {
+                    issue(
+                        LintKind::UnsortedAddress,
+                        Some(line_number),
+                        format!("FUNC {:#x} follows FUNC {:#x} (line {})", function.address, previous.address, previous.line_number),
+                    );
+                }
+                functions.push(FuncExtent {
+                    line_number,
+                    address: function.address,
+                    end,
+                });
+                last_line = None;
+            }
+            Record::Line(record) => {
+                if !file_ids.contains(&record.source_file_id) {
+                    file_references.push((line_number, record.source_file_id));
+                }
+                match functions.last() {
+                    Some(function) if record.address >= function.address && record.address.saturating_add(record.size) <= function.end => {}
+                    Some(function) => issue(
+                        LintKind::LineOutsideFunction,
+                        Some(line_number),
+                        format!(
+                            "LINE {:#x}..{:#x} is outside FUNC {:#x}..{:#x} (line {})",
+                            record.address,
+                            record.address.saturating_add(record.size),
+                            function.address,
+                            function.end,
+                            function.line_number
+                        ),
+                    ),
+                    None => issue(LintKind::LineOutsideFunction, Some(line_number), format!("LINE {:#x} comes before any FUNC", record.address)),
+                }
+                if let Some(last_line) = last_line.filter(|last_line| record.address < *last_line) {
+                    issue(LintKind::UnsortedAddress, Some(line_number), format!("LINE {:#x} follows LINE {:#x}", record.address, last_line));
+                }
+                last_line = Some(record.address);
+            }
+            Record::Public(public_symbol) => {
+                if let Some(last_public) = last_public.filter(|last_public| public_symbol.address < *last_public) {
+                    issue(LintKind::UnsortedAddress, Some(line_number), format!("PUBLIC {:#x} follows PUBLIC {:#x}", public_symbol.address, last_public));
+                }
+                last_public = Some(public_symbol.address);
+            }
+            Record::CodeId(..) | Record::Unparsed(_) | Record::Ignored => {}
+        }
+        first_record = false;
+    }
+
+    for (line_number, id) in file_references {
+        if !file_ids.contains(&id) {
+            issue(LintKind::UndefinedFile, Some(line_number), format!("LINE references undefined FILE {}", id));
+        }
+    }
+
+    // By address, so overlaps are found however the file is ordered.
+    functions.sort_by_key(|function| (function.address, function.line_number));
+    let mut covering: Option<&FuncExtent> = None;
+    for function in &functions {
+        match covering {
+            Some(outer) if function.address < outer.end => {
+                issue(
+                    LintKind::OverlappingFunction,
+                    Some(function.line_number),
+                    format!("FUNC {:#x}..{:#x} overlaps FUNC {:#x}..{:#x} (line {})", function.address, function.end, outer.address, outer.end, outer.line_number),
+                );
+                if function.end > outer.end {
+                    covering = Some(function);
+                }
+            }
+            _ => covering = Some(function),
+        }
+    }
+
+    if !saw_module {
+        issue(LintKind::MissingModule, None, String::from("no MODULE record"));
+    }
+    issues.sort_by_key(|issue| issue.line_number);
+    Ok(issues)
+}
+
+/// Checks a symbol file, compressed or in an archive as for
+/// [`open_symbol_file`], for structural problems.
+pub fn lint_symbol_file(path: &Path) -> io::Result<Vec<LintIssue>> {
+    lint_symbol_reader(open_symbol_file(path, None)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(text: &str) -> Vec<(LintKind, Option<usize>)> {
+        lint_symbol_reader(text.as_bytes()).unwrap().into_iter().map(|issue| (issue.kind, issue.line_number)).collect()
+    }
+
+    #[test]
+    fn test_lint_symbol_file() {
+        let clean = "MODULE Linux arm64 0102 libfoo.so\nINFO CODE_ID 0102\nINFO GENERATOR dump_syms\nFILE 0 a.c\nINLINE_ORIGIN 0 i\n\
+                     FUNC 10 20 0 f\nINLINE 0 1 0 0 10 4\n10 10 1 0\n20 10 2 0\nFUNC 30 8 0 g\nPUBLIC 40 0 h\nSTACK CFI INIT 10 20 .cfa: sp 0 +\n";
+        assert!(lint(clean).is_empty());
+        assert_eq!(lint("FILE 0 a.c\r\nFUNC 10 20 0 f\r\n"), vec![(LintKind::MissingModule, None)]);
+
+        let broken = "\
+FILE 0 a.c
+MODULE Linux arm64 0102 libfoo.so
+FUNC 100 20 0 f
+100 10 1 0
+90 10 2 7
+FUNC 110 8 0 inside_f
+FUNC zz
+FUNC 40 8 0 before_f
+PUBLIC 200 0 p
+PUBLIC 180 0 q
+FILE 7 late.c
+";
+        assert_eq!(
+            lint(broken),
+            vec![
+                (LintKind::MissingModule, Some(2)),
+                (LintKind::LineOutsideFunction, Some(5)),
+                (LintKind::UnsortedAddress, Some(5)),
+                (LintKind::OverlappingFunction, Some(6)),
+                (LintKind::Malformed, Some(7)),
+                (LintKind::UnsortedAddress, Some(8)),
+                (LintKind::UnsortedAddress, Some(10)),
+            ]
+        );
+        assert_eq!(lint("MODULE Linux arm64 0102 libfoo.so\nFUNC 10 20 0 f\n10 10 1 3\n"), vec![(LintKind::UndefinedFile, Some(3))]);
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_value(LintKind::LineOutsideFunction).unwrap(), LintKind::LineOutsideFunction.as_str());
+    }
+}
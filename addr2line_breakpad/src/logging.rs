@@ -0,0 +1,17 @@
+// The log macros: tracing's with the `tracing` feature, otherwise no-ops
+// that still type-check their arguments.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{debug, info, trace, warn};
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! discard {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use {discard as debug, discard as info, discard as trace, discard as warn};
@@ -0,0 +1,415 @@
+use std::fmt;
+
+use crate::logging::debug;
+
+use crate::{caller_address, normalize_code_address, ModuleId, Symbol, SymbolKind, Symbolicator};
+
+pub const MICRODUMP_BEGIN: &str = "-----BEGIN BREAKPAD MICRODUMP-----";
+pub const MICRODUMP_END: &str = "-----END BREAKPAD MICRODUMP-----";
+
+// Deeper stacks are truncated, scanning gets noisy far from the crash.
+const MAX_SCANNED_FRAMES: usize = 64;
+
+/// An `M` record: a module mapped into the crashed process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicrodumpModule {
+    pub start: u64,
+    pub size: u64,
+    pub debug_id: String,
+    pub name: String,
+}
+
+// Chrome writes microdumps to logcat when a minidump can't be written:
+//
+//   -----BEGIN BREAKPAD MICRODUMP-----
+//   V Chrome_Android:54.0.2790.0
+//   O A arm64 08 aarch64 google/bullhead/bullhead:7.0/NRD90M/3067211:user/release-keys
+//   S 0 7FC3E2B670 7FC3E2B000 4000
+//   S 7FC3E2B000 0000000000000000...
+//   M 0000007F8E3A1000 0000000000000000 0000000000A8C000 2D8C3B3C8B9C4F1E8C9D6C5E8F9A7B6C0 libchrome.so
+//   C 0100000000000000...
+//   -----END BREAKPAD MICRODUMP-----
+//
+// Every line may carry a logcat prefix.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Microdump {
+    pub product: Option<String>,
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub modules: Vec<MicrodumpModule>,
+    pub stack_pointer: u64,
+    pub stack_start: u64,
+    pub stack: Vec<u8>,
+    /// Raw CPU context as dumped by the writer.
+    pub context: Vec<u8>,
+}
+
+fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len() / 2).map(|i| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()).collect()
+}
+
+fn hex_u64(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+// The logcat prefix of the BEGIN line ends in a tag like `chromium: ` or
+// `( 1234): `; the rest of the dump carries the same tag.
+fn strip_logcat_prefix<'a>(line: &'a str, tag: &str) -> &'a str {
+    if tag.is_empty() {
+        return line.trim();
+    }
+    match line.find(tag) {
+        Some(i) => line[i + tag.len()..].trim(),
+        None => line.trim(),
+    }
+}
+
+pub fn parse_microdump(input: &str) -> Option<Microdump> {
+    let mut lines = input.lines();
+    let begin = lines.by_ref().find(|line| line.contains(MICRODUMP_BEGIN))?;
+    let prefix = begin[..begin.find(MICRODUMP_BEGIN).unwrap()].trim_end();
+    let tag = prefix.rsplit(char::is_whitespace).next().unwrap_or("");
+
+    let mut dump = Microdump::default();
+    for line in lines {
+        let line = strip_logcat_prefix(line, tag);
+        if line.contains(MICRODUMP_END) {
+            return Some(dump);
+        }
+        let (record, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let tokens: Vec<&str> = rest.split_whitespace().collect();
+        match record {
+            "V" => dump.product = Some(rest.trim().to_string()),
+            "O" => {
+                dump.os = tokens.first().map(|os| os.to_string());
+                dump.arch = tokens.get(1).map(|arch| arch.to_string());
+            }
+            "M" if tokens.len() >= 5 => {
+                let (start, size) = match (hex_u64(tokens[0]), hex_u64(tokens[2])) {
+                    (Some(start), Some(size)) => (start, size),
+                    _ => continue,
+                };
+                let name = tokens[4..].join(" ");
+                dump.modules.push(MicrodumpModule {
+                    start,
+                    size,
+                    debug_id: tokens[3].to_string(),
+                    name: name.rsplit('/').next().unwrap_or(&name).to_string(),
+                });
+            }
+            // S 0 <sp> <stack start> [<size>]
+            "S" if tokens.first() == Some(&"0") && tokens.len() >= 3 => {
+                dump.stack_pointer = hex_u64(tokens[1]).unwrap_or(0);
+                dump.stack_start = hex_u64(tokens[2]).unwrap_or(0);
+            }
+            // S <address> <bytes>
+            "S" if tokens.len() == 2 => {
+                if let (Some(address), Some(bytes)) = (hex_u64(tokens[0]), hex_bytes(tokens[1])) {
+                    let offset = match address.checked_sub(dump.stack_start) {
+                        Some(offset) if offset < 1 << 24 => offset as usize,
+                        _ => continue,
+                    };
+                    if dump.stack.len() < offset + bytes.len() {
+                        dump.stack.resize(offset + bytes.len(), 0);
+                    }
+                    dump.stack[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+            "C" => {
+                if let Some(bytes) = hex_bytes(tokens.concat().as_str()) {
+                    dump.context.extend(bytes);
+                }
+            }
+            _ => {}
+        }
+    }
+    debug!("microdump without an END marker");
+    Some(dump)
+}
+
+/// How a frame of a microdump was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameTrust {
+    /// The program counter of the CPU context.
+    Context,
+    /// The link register of the CPU context.
+    LinkRegister,
+    /// A return address saved in an arm64 frame record. Only as good as the
+    /// frame pointers the code was built to keep.
+    FramePointer,
+    /// A stack word pointing into a function, possibly a stale one.
+    Scan,
+}
+
+impl fmt::Display for FrameTrust {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FrameTrust::Context => "context",
+            FrameTrust::LinkRegister => "lr",
+            FrameTrust::FramePointer => "fp",
+            FrameTrust::Scan => "scan",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MicrodumpFrame {
+    pub address: u64,
+    /// Module the address falls in and the address relative to it.
+    pub module: Option<(String, u64)>,
+    pub symbol: Option<Symbol<'static>>,
+    pub trust: FrameTrust,
+}
+
+impl Microdump {
+    fn word_size(&self) -> usize {
+        match self.arch.as_deref() {
+            Some("arm") | Some("x86") | Some("mips") => 4,
+            _ => 8,
+        }
+    }
+
+    fn read_word(bytes: &[u8], offset: usize, size: usize) -> Option<u64> {
+        let word = bytes.get(offset..offset + size)?;
+        Some(word.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64))
+    }
+
+    // pc and lr from the raw context: MDRawContextARM64_Old has a u64 flags
+    // field and then x0..x30, sp, pc; MDRawContextARM a u32 flags field and
+    // then r0..r15. Other architectures only get stack scanning.
+    pub fn pc_and_lr(&self) -> (Option<u64>, Option<u64>) {
+        let (pc, lr, size) = match self.arch.as_deref() {
+            Some("arm64") => (8 + 32 * 8, 8 + 30 * 8, 8),
+            Some("arm") => (4 + 15 * 4, 4 + 14 * 4, 4),
+            _ => return (None, None),
+        };
+        (Self::read_word(&self.context, pc, size), Self::read_word(&self.context, lr, size))
+    }
+
+    // Return addresses from the x29/x30 frame records on the stack. Symbol
+    // files carry no STACK CFI here, so following x29 is the best unwinding
+    // there is; the walk stops at the first record that isn't strictly above
+    // the previous one inside the dumped stack.
+    fn frame_pointer_chain(&self) -> Vec<u64> {
+        let mut addresses = vec![];
+        if self.arch.as_deref() != Some("arm64") {
+            return addresses;
+        }
+        let mut fp = match Self::read_word(&self.context, 8 + 29 * 8, 8) {
+            Some(fp) => fp,
+            None => return addresses,
+        };
+        let mut floor = self.stack_pointer;
+        while addresses.len() < MAX_SCANNED_FRAMES && fp >= floor && fp % 8 == 0 {
+            let offset = match fp.checked_sub(self.stack_start) {
+                Some(offset) => offset as usize,
+                None => break,
+            };
+            let (caller_fp, return_address) =
+                match (Self::read_word(&self.stack, offset, 8), Self::read_word(&self.stack, offset + 8, 8)) {
+                    (Some(caller_fp), Some(return_address)) => (caller_fp, return_address),
+                    _ => break,
+                };
+            if self.module_for_address(return_address).is_none() {
+                break;
+            }
+            addresses.push(return_address);
+            floor = match fp.checked_add(16) {
+                Some(floor) => floor,
+                None => break,
+            };
+            fp = caller_fp;
+        }
+        addresses
+    }
+
+    pub fn module_for_address(&self, address: u64) -> Option<&MicrodumpModule> {
+        self.modules
+            .iter()
+            .find(|module| module.start <= address && address - module.start < module.size)
+    }
+
+    fn frame(&self, address: u64, symbolicator: &dyn Symbolicator, trust: FrameTrust) -> MicrodumpFrame {
+        let module = self.module_for_address(address);
+        // Thumb return addresses on arm have bit 0 set. Frames other than the
+        // crashing one are return addresses, looked up at their call.
+        let arch = self.arch.as_deref().unwrap_or("");
+        let code_address = match trust {
+            FrameTrust::Context => normalize_code_address(arch, address),
+            _ => caller_address(arch, address),
+        };
+        let symbol = module.and_then(|module| symbolicator.lookup(&ModuleId::new(&module.name).with_debug_id(&module.debug_id), code_address.checked_sub(module.start)?).map(Symbol::into_owned));
+        MicrodumpFrame {
+            address,
+            module: module.map(|module| (module.name.clone(), address - module.start)),
+            symbol,
+            trust,
+        }
+    }
+
+    // Frame 0 from the context, then the frame pointer chain on arm64. Without
+    // one, every stack word above the stack pointer that points into a
+    // function we have symbols for.
+    pub fn symbolicate(&self, symbolicator: &dyn Symbolicator) -> Vec<MicrodumpFrame> {
+        let mut frames = vec![];
+        let (pc, lr) = self.pc_and_lr();
+        if let Some(pc) = pc {
+            frames.push(self.frame(pc, symbolicator, FrameTrust::Context));
+        }
+        let lr = lr.filter(|lr| self.module_for_address(*lr).is_some());
+        if let Some(lr) = lr {
+            frames.push(self.frame(lr, symbolicator, FrameTrust::LinkRegister));
+        }
+        let chain = self.frame_pointer_chain();
+        if !chain.is_empty() {
+            // Past its prologue a non-leaf frame 0 saved lr in its own record.
+            let skip = usize::from(lr.is_some() && lr == chain.first().copied());
+            for address in &chain[skip..] {
+                frames.push(self.frame(*address, symbolicator, FrameTrust::FramePointer));
+            }
+            return frames;
+        }
+        let word_size = self.word_size();
+        let start = self.stack_pointer.saturating_sub(self.stack_start) as usize;
+        let mut offset = start - start % word_size;
+        while offset + word_size <= self.stack.len() && frames.len() < MAX_SCANNED_FRAMES {
+            let word = Self::read_word(&self.stack, offset, word_size).unwrap();
+            offset += word_size;
+            if self.module_for_address(word).is_none() {
+                continue;
+            }
+            let frame = self.frame(word, symbolicator, FrameTrust::Scan);
+            if frame.symbol.as_ref().is_some_and(|symbol| symbol.kind == SymbolKind::Func) {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Module, ModuleSet, SymbolFileBuilder};
+
+    #[test]
+    fn test_microdump() {
+        let mut context = String::from("0100000000000000");
+        for i in 0..33u64 {
+            // lr = 0x1000 + 0x210, pc = 0x1000 + 0x104
+            let value: u64 = match i {
+                30 => 0x1210,
+                32 => 0x1104,
+                _ => i,
+            };
+            context.extend(value.to_le_bytes().iter().map(|b| format!("{:02x}", b)));
+        }
+        let input = format!(
+            "I/chromium( 1234): {}\n\
+             I/chromium( 1234): V Chrome_Android:54.0.2790.0\n\
+             I/chromium( 1234): O A arm64 08 aarch64 google/bullhead:7.0\n\
+             I/chromium( 1234): S 0 100 100 20\n\
+             I/chromium( 1234): S 100 00000000000000003013000000000000\n\
+             I/chromium( 1234): S 110 1000000000000000ffffffff00000000\n\
+             I/chromium( 1234): M 0000000000001000 0000000000000000 0000000000001000 0102030405060708090A0B0C0D0E0F100 /system/lib64/libtest.so\n\
+             I/chromium( 1234): C {}\n\
+             I/chromium( 1234): {}\n",
+            MICRODUMP_BEGIN, context, MICRODUMP_END
+        );
+        let dump = parse_microdump(&input).unwrap();
+        assert_eq!(dump.product.as_deref(), Some("Chrome_Android:54.0.2790.0"));
+        assert_eq!(dump.arch.as_deref(), Some("arm64"));
+        assert_eq!(dump.modules[0].name, "libtest.so");
+        assert_eq!(dump.stack.len(), 0x20);
+        assert_eq!(dump.pc_and_lr(), (Some(0x1104), Some(0x1210)));
+
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "0102030405060708090A0B0C0D0E0F100", "libtest.so")
+            .add_function(0x100, 0x10, 0, "crash")
+            .add_function(0x200, 0x20, 0, "caller")
+            .add_function(0x300, 0x40, 0, "main");
+        let mut symbolicator = ModuleSet::new();
+        symbolicator.add_module(Module::new(builder.build()));
+
+        let frames = dump.symbolicate(&symbolicator);
+        let names: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.symbol.as_ref().unwrap().function_name.as_ref(), frame.trust))
+            .collect();
+        assert_eq!(names, vec![("crash", FrameTrust::Context), ("caller", FrameTrust::LinkRegister), ("main", FrameTrust::Scan)]);
+        assert_eq!(frames[2].module, Some(("libtest.so".to_string(), 0x330)));
+
+        assert!(parse_microdump("no dump here").is_none());
+    }
+
+    #[test]
+    fn test_microdump_frame_pointer() {
+        let mut context = String::from("0100000000000000");
+        for i in 0..33u64 {
+            let value: u64 = match i {
+                29 => 0x110,
+                30 => 0x1210,
+                32 => 0x1104,
+                _ => 0,
+            };
+            context.extend(value.to_le_bytes().iter().map(|b| format!("{:02x}", b)));
+        }
+        // A stale pointer into `crash` below two frame records, the first
+        // holding lr again.
+        let input = format!(
+            "{}\n\
+             O L arm64 01 aarch64\n\
+             S 0 100 100 30\n\
+             S 100 08110000000000000000000000000000\n\
+             S 110 20010000000000001012000000000000\n\
+             S 120 00000000000000003013000000000000\n\
+             M 0000000000001000 0000000000000000 0000000000001000 0102030405060708090A0B0C0D0E0F100 libtest.so\n\
+             C {}\n\
+             {}\n",
+            MICRODUMP_BEGIN, context, MICRODUMP_END
+        );
+        let dump = parse_microdump(&input).unwrap();
+
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "0102030405060708090A0B0C0D0E0F100", "libtest.so")
+            .add_function(0x100, 0x10, 0, "crash")
+            .add_function(0x200, 0x20, 0, "caller")
+            .add_function(0x300, 0x40, 0, "main");
+        let mut symbolicator = ModuleSet::new();
+        symbolicator.add_module(Module::new(builder.build()));
+
+        let frames = dump.symbolicate(&symbolicator);
+        let names: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.symbol.as_ref().unwrap().function_name.as_ref(), frame.trust))
+            .collect();
+        assert_eq!(names, vec![("crash", FrameTrust::Context), ("caller", FrameTrust::LinkRegister), ("main", FrameTrust::FramePointer)]);
+        assert_eq!(FrameTrust::FramePointer.to_string(), "fp");
+    }
+
+    #[test]
+    fn test_microdump_frame_pointer_at_top_of_address_space() {
+        let mut context = String::from("0100000000000000");
+        for i in 0..33u64 {
+            let value: u64 = if i == 29 { 0xffff_ffff_ffff_fff0 } else { 0 };
+            context.extend(value.to_le_bytes().iter().map(|b| format!("{:02x}", b)));
+        }
+        let input = format!(
+            "{}\n\
+             O L arm64 01 aarch64\n\
+             S 0 fffffffffffffff0 fffffffffffffff0 10\n\
+             S fffffffffffffff0 f0ffffffffffffff1012000000000000\n\
+             M 0000000000001000 0000000000000000 0000000000001000 0102030405060708090A0B0C0D0E0F100 libtest.so\n\
+             C {}\n\
+             {}\n",
+            MICRODUMP_BEGIN, context, MICRODUMP_END
+        );
+        let dump = parse_microdump(&input).unwrap();
+        assert_eq!(dump.frame_pointer_chain(), vec![0x1210]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+/// How much of a C++ function name to keep for display, e.g. of
+/// `bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>(void const*) const`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "lowercase"))]
+pub enum NameSimplification {
+    /// The name as in the symbol file.
+    #[default]
+    None,
+    /// `bool UnityDefaultAllocator::AllocationPage(void const*) const`
+    Templates,
+    /// `bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>`
+    Parameters,
+    /// `bool UnityDefaultAllocator::AllocationPage`
+    All,
+}
+
+impl NameSimplification {
+    pub const VARIANTS: [&'static str; 4] = ["none", "templates", "parameters", "all"];
+
+    pub fn simplify<'a>(self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            NameSimplification::None => Cow::Borrowed(name),
+            NameSimplification::Templates => strip_template_arguments(name),
+            NameSimplification::Parameters => strip_parameters(name),
+            NameSimplification::All => match strip_parameters(name) {
+                Cow::Borrowed(name) => strip_template_arguments(name),
+                Cow::Owned(name) => Cow::Owned(strip_template_arguments(&name).into_owned()),
+            },
+        }
+    }
+}
+
+impl FromStr for NameSimplification {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(NameSimplification::None),
+            "templates" => Ok(NameSimplification::Templates),
+            "parameters" => Ok(NameSimplification::Parameters),
+            "all" => Ok(NameSimplification::All),
+            _ => Err(format!("invalid name simplification({}), expected one of {}", s, NameSimplification::VARIANTS.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for NameSimplification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(NameSimplification::VARIANTS[*self as usize])
+    }
+}
+
+// Longest first, so `operator<<` isn't taken for `operator<`.
+const BRACKET_OPERATORS: [&str; 12] = [
+    "operator<=>",
+    "operator<<=",
+    "operator>>=",
+    "operator->*",
+    "operator<<",
+    "operator>>",
+    "operator<=",
+    "operator>=",
+    "operator->",
+    "operator()",
+    "operator<",
+    "operator>",
+];
+
+// Length of an operator name at `i` whose brackets aren't nesting, 0 if there
+// is none.
+fn bracket_operator_len(name: &str, i: usize) -> usize {
+    let rest = &name[i..];
+    if !rest.starts_with("operator") || name[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return 0;
+    }
+    BRACKET_OPERATORS
+        .iter()
+        .find(|operator| rest.starts_with(*operator))
+        .map_or(0, |operator| operator.len())
+}
+
+// `-[UnityAppController(Rendering) repaintDisplayLink]` has neither.
+fn is_objc_method(name: &str) -> bool {
+    name.starts_with("-[") || name.starts_with("+[")
+}
+
+pub fn strip_template_arguments(name: &str) -> Cow<'_, str> {
+    if !name.contains('<') || is_objc_method(name) {
+        return Cow::Borrowed(name);
+    }
+    let mut stripped = String::with_capacity(name.len());
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < name.len() {
+        let operator_len = bracket_operator_len(name, i);
+        if operator_len > 0 {
+            if depth == 0 {
+                stripped.push_str(&name[i..i + operator_len]);
+            }
+            i += operator_len;
+            continue;
+        }
+        let c = name[i..].chars().next().unwrap();
+        match c {
+            '<' => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    // `<name omitted>` and the like are whole names.
+    if stripped.trim().is_empty() {
+        return Cow::Borrowed(name);
+    }
+    Cow::Owned(stripped)
+}
+
+// Drops the parameter list and whatever follows it (`const`, `&&`,
+// `[clone .cold]`). Groups like `(anonymous namespace)::` are part of the
+// name, not parameters.
+pub fn strip_parameters(name: &str) -> Cow<'_, str> {
+    if is_objc_method(name) {
+        return Cow::Borrowed(name);
+    }
+    let mut parameters = None;
+    let (mut angles, mut parens, mut braces) = (0usize, 0usize, 0usize);
+    let mut group_start = 0;
+    let mut i = 0;
+    while i < name.len() {
+        let operator_len = bracket_operator_len(name, i);
+        if operator_len > 0 {
+            i += operator_len;
+            continue;
+        }
+        match name.as_bytes()[i] {
+            b'<' => angles += 1,
+            b'>' => angles = angles.saturating_sub(1),
+            b'{' => braces += 1,
+            b'}' => braces = braces.saturating_sub(1),
+            b'(' => {
+                if parens == 0 {
+                    group_start = i;
+                }
+                parens += 1;
+            }
+            b')' if parens > 0 => {
+                parens -= 1;
+                if parens == 0 && angles == 0 && braces == 0 && group_start > 0 && !name[i + 1..].starts_with("::") {
+                    parameters = Some(group_start);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    match parameters {
+        Some(start) => Cow::Owned(name[..start].trim_end().to_string()),
+        None => Cow::Borrowed(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify_names() {
+        let name = "bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>(void const*) const";
+        assert_eq!(NameSimplification::None.simplify(name), name);
+        assert_eq!(NameSimplification::Templates.simplify(name), "bool UnityDefaultAllocator::AllocationPage(void const*) const");
+        assert_eq!(NameSimplification::Parameters.simplify(name), "bool UnityDefaultAllocator<LowLevelAllocator>::AllocationPage<(RequestType)0>");
+        assert_eq!(NameSimplification::All.simplify(name), "bool UnityDefaultAllocator::AllocationPage");
+
+        assert_eq!(NameSimplification::All.simplify("main"), "main");
+        assert_eq!(NameSimplification::All.simplify("std::vector<int, std::allocator<int> >::push_back(int&&)"), "std::vector::push_back");
+        assert_eq!(NameSimplification::All.simplify("(anonymous namespace)::Flush(Stream<char>*)"), "(anonymous namespace)::Flush");
+        assert_eq!(NameSimplification::Parameters.simplify("(anonymous namespace)::Flush"), "(anonymous namespace)::Flush");
+        assert_eq!(
+            NameSimplification::All.simplify("Job::Run(int)::{lambda(int)#1}::operator()(int) const"),
+            "Job::Run(int)::{lambda(int)#1}::operator()"
+        );
+        assert_eq!(NameSimplification::All.simplify("Vector3f::operator<(Vector3f const&) const"), "Vector3f::operator<");
+        assert_eq!(NameSimplification::Templates.simplify("operator<<<char>(std::ostream&, Foo<char> const&)"), "operator<<(std::ostream&, Foo const&)");
+        assert_eq!(NameSimplification::Parameters.simplify("Profiler::Flush(bool) [clone .cold]"), "Profiler::Flush");
+        assert_eq!(NameSimplification::All.simplify("-[UnityAppController(Rendering) repaintDisplayLink]"), "-[UnityAppController(Rendering) repaintDisplayLink]");
+        assert_eq!(NameSimplification::All.simplify("<name omitted>"), "<name omitted>");
+
+        assert_eq!("parameters".parse(), Ok(NameSimplification::Parameters));
+        assert!("short".parse::<NameSimplification>().is_err());
+        assert_eq!(NameSimplification::Templates.to_string(), "templates");
+    }
+}
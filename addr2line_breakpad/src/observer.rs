@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Told the outcome of every lookup, parse and download, so embedders can
+/// export hit rates and latencies to their metrics systems. Installed
+/// process-wide with [`set_observer`]; every method does nothing by default.
+pub trait SymbolicationObserver: Send + Sync {
+    /// A lookup in `module`, the module's name, resolved or not.
+    fn lookup(&self, _module: &str, _rel_addr: u64, _hit: bool, _duration: Duration) {}
+
+    /// A symbol file parsed from `source`, a path or the name given to a
+    /// reader.
+    fn parsed(&self, _source: &str, _duration: Duration, _ok: bool) {}
+
+    /// A download from `url`; cache hits are not reported.
+    fn downloaded(&self, _url: &str, _duration: Duration, _ok: bool) {}
+}
+
+// Checked before timing anything, so lookups don't pay for an observer
+// nobody installed.
+static OBSERVED: AtomicBool = AtomicBool::new(false);
+static OBSERVER: RwLock<Option<Arc<dyn SymbolicationObserver>>> = RwLock::new(None);
+
+/// Installs `observer` for the whole process, replacing the previous one;
+/// None removes it.
+pub fn set_observer(observer: Option<Arc<dyn SymbolicationObserver>>) {
+    let mut current = OBSERVER.write().unwrap();
+    OBSERVED.store(observer.is_some(), Ordering::Relaxed);
+    *current = observer;
+}
+
+// When the operation about to start began, if anyone is observing.
+pub(crate) fn start() -> Option<Instant> {
+    OBSERVED.load(Ordering::Relaxed).then(Instant::now)
+}
+
+pub(crate) fn observe(started: Option<Instant>, notify: impl FnOnce(&dyn SymbolicationObserver, Duration)) {
+    let started = match started {
+        Some(started) => started,
+        None => return,
+    };
+    if let Some(observer) = OBSERVER.read().unwrap().as_ref() {
+        notify(observer.as_ref(), started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_breakpad_symbol_source_with_options, Module, ParserOptions, SymbolFileBuilder};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl SymbolicationObserver for Recorder {
+        fn lookup(&self, module: &str, rel_addr: u64, hit: bool, _: Duration) {
+            // Tests in other threads look up other modules meanwhile.
+            if module == "observed.so" {
+                self.events.lock().unwrap().push(format!("lookup {:#x} {}", rel_addr, hit));
+            }
+        }
+
+        fn parsed(&self, source: &str, _: Duration, ok: bool) {
+            if source.ends_with("observed.sym") {
+                self.events.lock().unwrap().push(format!("parsed {}", ok));
+            }
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let recorder = Arc::new(Recorder::default());
+        set_observer(Some(recorder.clone()));
+
+        let mut builder = SymbolFileBuilder::new();
+        builder.set_module("Linux", "arm64", "0102", "observed.so").add_function(0x100, 0x10, 0, "f");
+        let module = Module::new(builder.build());
+        module.lookup(0x104);
+        module.lookup_addresses(&[0x100, 0x200]);
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_{}_observed.sym", std::process::id()));
+        std::fs::write(&path, "MODULE Linux arm64 0102 observed.so\n").unwrap();
+        parse_breakpad_symbol_source_with_options(&path, None, &ParserOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        set_observer(None);
+        module.lookup(0x104);
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec!["lookup 0x104 true", "lookup 0x100 true", "lookup 0x200 false", "parsed true"]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::{btree_map, BTreeMap};
+use std::iter::Peekable;
+use std::ops::Bound::{Excluded, Unbounded};
+
+use crate::logging::trace;
+
+/// Items keyed by address ranges `[address, address + size)`, like the FUNC
+/// and LINE records of a symbol file.
+///
+/// Ranges may overlap. A point lookup with [`RangeMap::get`] only considers
+/// the range starting closest below the address, and accepts an address
+/// right at its end: a return address just past a function's last call
+/// still resolves to that function. When two ranges touch, the one starting
+/// at the address wins. Overlap queries ([`RangeMap::retrieve_overlapping`],
+/// [`RangeMap::overlaps`]) treat ranges as half-open and see every range.
+#[derive(Debug)]
+pub struct RangeMap<T> {
+    map: BTreeMap<u64, RangeItem<T>>,
+    // Largest size ever inserted: how far before a window a range reaching
+    // into it can start.
+    max_size: u64,
+}
+
+#[derive(Debug)]
+struct RangeItem<T> {
+    item: T,
+    size: u64,
+}
+
+impl<T> Default for RangeMap<T> {
+    fn default() -> Self {
+        RangeMap::new()
+    }
+}
+
+impl<T> RangeMap<T> {
+    pub fn new() -> Self {
+        RangeMap {
+            map: BTreeMap::new(),
+            max_size: 0,
+        }
+    }
+
+    /// Adds `item` for `[address, address + size)`. Returns the item that
+    /// started at `address` before, which it replaces.
+    pub fn insert(&mut self, address: u64, size: u64, item: T) -> Option<T> {
+        self.max_size = self.max_size.max(size);
+        self.map.insert(address, RangeItem { item, size }).map(|range_item| range_item.item)
+    }
+
+    /// Removes the range starting at `address`, returning its size and item.
+    pub fn remove(&mut self, address: u64) -> Option<(u64, T)> {
+        self.map.remove(&address).map(|range_item| (range_item.size, range_item.item))
+    }
+
+    /// The item of the range starting closest below or at `address`, if
+    /// `address` is inside it or right at its end.
+    pub fn get(&self, address: u64) -> Option<&T> {
+        let (target_address, range_item) = self.map.range(..=address).next_back()?;
+        trace!("nearest item {:#x} size {:#x} for {:#x}", target_address, range_item.size, address);
+        if address - target_address <= range_item.size {
+            Some(&range_item.item)
+        } else {
+            None
+        }
+    }
+
+    /// The end of the range reaching furthest, saturating at `u64::MAX`.
+    pub fn end(&self) -> Option<u64> {
+        // Only ranges starting within `max_size` of the last one can reach
+        // past it.
+        let (&last, _) = self.map.last_key_value()?;
+        self.map
+            .range(last.saturating_sub(self.max_size)..)
+            .map(|(address, range_item)| address.saturating_add(range_item.size))
+            .max()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// `(address, size, item)` of every range, by address.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (u64, u64, &T)> {
+        self.map
+            .iter()
+            .map(|(address, range_item)| (*address, range_item.size, &range_item.item))
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.map.values().map(|range_item| &range_item.item)
+    }
+
+    /// The items, by address, for changes that keep their ranges.
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> {
+        self.map.values_mut().map(|range_item| &mut range_item.item)
+    }
+
+    /// Whether any range intersects `[address, address + size)`. An empty
+    /// range is treated as one byte, so it overlaps what contains its start.
+    pub fn overlaps(&self, address: u64, size: u64) -> bool {
+        self.retrieve_overlapping(address, address.saturating_add(size.max(1))).next().is_some()
+    }
+
+    /// `(address, size, item)` of every range intersecting `[start, end)`, by
+    /// address, including ranges that start before `start` and nested ones.
+    /// Ranges starting in the window count even if they are empty.
+    pub fn retrieve_overlapping(&self, start: u64, end: u64) -> impl Iterator<Item = (u64, u64, &T)> {
+        let end = end.max(start);
+        self.map
+            .range(start.saturating_sub(self.max_size)..end)
+            .filter(move |(address, range_item)| **address >= start || address.saturating_add(range_item.size) > start)
+            .map(|(address, range_item)| (*address, range_item.size, &range_item.item))
+    }
+
+    /// Same answers as [`RangeMap::get`], but cheaper for ascending addresses.
+    pub fn cursor(&self) -> RangeCursor<'_, T> {
+        RangeCursor {
+            nearest: NearestCursor::new(&self.map),
+        }
+    }
+}
+
+impl<T> IntoIterator for RangeMap<T> {
+    type Item = (u64, u64, T);
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self.map.into_iter())
+    }
+}
+
+/// `(address, size, item)` of every range of a [`RangeMap`], by address.
+pub struct IntoIter<T>(btree_map::IntoIter<u64, RangeItem<T>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = (u64, u64, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(address, range_item)| (address, range_item.size, range_item.item))
+    }
+}
+
+/// Point lookups into a [`RangeMap`], from [`RangeMap::cursor`].
+pub struct RangeCursor<'a, T> {
+    nearest: NearestCursor<'a, RangeItem<T>>,
+}
+
+impl<'a, T> RangeCursor<'a, T> {
+    pub fn seek(&mut self, address: u64) -> Option<&'a T> {
+        let (target_address, range_item) = self.nearest.seek(address)?;
+        if address - target_address <= range_item.size {
+            Some(&range_item.item)
+        } else {
+            None
+        }
+    }
+}
+
+// Walks a map forward for ascending addresses instead of searching it from
+// the root each time. Going backwards restarts the walk with a search.
+pub(crate) struct NearestCursor<'a, V> {
+    map: &'a BTreeMap<u64, V>,
+    iter: Peekable<btree_map::Range<'a, u64, V>>,
+    nearest: Option<(&'a u64, &'a V)>,
+    last_address: u64,
+}
+
+impl<'a, V> NearestCursor<'a, V> {
+    pub(crate) fn new(map: &'a BTreeMap<u64, V>) -> Self {
+        NearestCursor {
+            map,
+            iter: map.range(..).peekable(),
+            nearest: None,
+            last_address: 0,
+        }
+    }
+
+    // The entry with the greatest key <= `address`.
+    pub(crate) fn seek(&mut self, address: u64) -> Option<(u64, &'a V)> {
+        if address < self.last_address {
+            self.nearest = self.map.range(..=address).next_back();
+            self.iter = self.map.range((Excluded(address), Unbounded)).peekable();
+        } else {
+            while let Some(entry) = self.iter.next_if(|(key, _)| **key <= address) {
+                self.nearest = Some(entry);
+            }
+        }
+        self.last_address = address;
+        self.nearest.map(|(key, value)| (*key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_map() {
+        let mut map = RangeMap::new();
+        assert!(map.is_empty());
+        map.insert(0x10, 0x10, "a");
+        map.insert(0x20, 0x8, "b");
+        map.insert(0x40, 0, "empty");
+        assert_eq!(map.insert(0x50, 0x4, "c"), None);
+        assert_eq!(map.insert(0x50, 0x8, "d"), Some("c"));
+        assert_eq!(map.len(), 4);
+
+        assert_eq!(map.get(0xf), None);
+        assert_eq!(map.get(0x10), Some(&"a"));
+        assert_eq!(map.get(0x1f), Some(&"a"));
+        // Touching ranges: the one starting at the address wins, the end of
+        // the last one is still part of it.
+        assert_eq!(map.get(0x20), Some(&"b"));
+        assert_eq!(map.get(0x28), Some(&"b"));
+        assert_eq!(map.get(0x29), None);
+        assert_eq!(map.get(0x40), Some(&"empty"));
+        assert_eq!(map.get(0x41), None);
+
+        let overlapping: Vec<_> = map.retrieve_overlapping(0x18, 0x41).map(|(_, _, item)| item).collect();
+        assert_eq!(overlapping, vec![&"a", &"b", &"empty"]);
+        assert_eq!(map.retrieve_overlapping(0x28, 0x40).count(), 0);
+        assert_eq!(map.retrieve_overlapping(0x30, 0x10).count(), 0);
+        assert!(map.overlaps(0x1f, 1));
+        assert!(!map.overlaps(0x28, 0x18));
+        assert!(map.overlaps(0x40, 0));
+        assert_eq!(map.end(), Some(0x58));
+        assert_eq!(RangeMap::<()>::new().end(), None);
+
+        let mut cursor = map.cursor();
+        for address in [0x0, 0x10, 0x28, 0x29, 0x52, 0x20, 0x100] {
+            assert_eq!(cursor.seek(address), map.get(address), "{:#x}", address);
+        }
+
+        assert_eq!(map.remove(0x20), Some((0x8, "b")));
+        assert_eq!(map.remove(0x20), None);
+        assert_eq!(map.get(0x20), Some(&"a"));
+        let ranges: Vec<_> = map.iter().map(|(address, size, _)| (address, size)).collect();
+        assert_eq!(ranges, vec![(0x10, 0x10), (0x40, 0), (0x50, 0x8)]);
+        let items: Vec<_> = map.into_iter().map(|(_, _, item)| item).collect();
+        assert_eq!(items, vec!["a", "empty", "d"]);
+    }
+
+    #[test]
+    fn test_retrieve_overlapping() {
+        // An outer range with nested ones, as inlined code or a FUNC covering
+        // a stripped region.
+        let mut map = RangeMap::new();
+        map.insert(0x1000, 0x1000, "outer");
+        map.insert(0x1100, 0x10, "inner");
+        map.insert(0x1800, 0x100, "late");
+        map.insert(0x3000, 0x10, "after");
+
+        let window = |start, end| map.retrieve_overlapping(start, end).map(|(address, size, item)| (address, size, *item)).collect::<Vec<_>>();
+        assert_eq!(window(0x1200, 0x1300), vec![(0x1000, 0x1000, "outer")]);
+        assert_eq!(window(0x1108, 0x1900), vec![(0x1000, 0x1000, "outer"), (0x1100, 0x10, "inner"), (0x1800, 0x100, "late")]);
+        assert_eq!(window(0x1fff, 0x3001), vec![(0x1000, 0x1000, "outer"), (0x3000, 0x10, "after")]);
+        assert!(window(0x2000, 0x3000).is_empty());
+        assert!(map.overlaps(0x1f00, 0x10));
+        assert!(!map.overlaps(0x2000, 0x1000));
+    }
+}
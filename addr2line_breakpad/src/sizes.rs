@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::{strip_parameters, strip_template_arguments, SymbolFile};
+
+/// What [`SymbolFile::function_sizes`] adds FUNC sizes up by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeGrouping {
+    /// Every FUNC record on its own.
+    Function,
+    /// The FILE of the function's first LINE record.
+    File,
+    /// The first `depth` components of the function's scope, `il2cpp::os`
+    /// for `il2cpp::os::Thread::RunWrapper()` at depth 2.
+    Namespace(usize),
+}
+
+/// A FUNC record, or the FUNC records of one group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeEntry {
+    pub name: String,
+    /// The function's address, None for groups.
+    pub address: Option<u64>,
+    pub size: u64,
+    pub count: usize,
+}
+
+/// FUNC records with a size in `min_size..min_size * 2`, or of size 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBucket {
+    pub min_size: u64,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// Where the code of a module goes, from [`SymbolFile::function_sizes`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionSizes {
+    pub function_count: usize,
+    pub total_bytes: u64,
+    /// Largest first, equally sized ones by name.
+    pub entries: Vec<SizeEntry>,
+    /// Power of two buckets, smallest first, empty ones left out.
+    pub histogram: Vec<SizeBucket>,
+}
+
+// Files and scopes that can't be told are grouped under these.
+const UNKNOWN_FILE: &str = "??";
+const GLOBAL_SCOPE: &str = "(global)";
+
+fn scope(name: &str, depth: usize) -> String {
+    let name = strip_parameters(name);
+    let name = strip_template_arguments(&name);
+    let components: Vec<&str> = name.split("::").collect();
+    match components.len() {
+        1 => String::from(GLOBAL_SCOPE),
+        len => components[..depth.max(1).min(len - 1)].join("::"),
+    }
+}
+
+fn bucket_floor(size: u64) -> u64 {
+    match size {
+        0 => 0,
+        size => 1 << size.ilog2(),
+    }
+}
+
+impl SymbolFile {
+    /// FUNC sizes, by function or added up by `grouping`, with a histogram of
+    /// function sizes, for binary size investigations.
+    pub fn function_sizes(&self, grouping: SizeGrouping) -> FunctionSizes {
+        let mut sizes = FunctionSizes::default();
+        let mut groups: HashMap<String, SizeEntry> = HashMap::new();
+        let mut histogram: HashMap<u64, SizeBucket> = HashMap::new();
+        let loaded_lines = if grouping == SizeGrouping::File { self.loaded_lines() } else { None };
+        let lines = loaded_lines.as_ref().unwrap_or(&self.lines);
+        for (address, size, function) in self.functions.iter() {
+            sizes.function_count += 1;
+            sizes.total_bytes += size;
+            let min_size = bucket_floor(size);
+            let bucket = histogram.entry(min_size).or_insert(SizeBucket { min_size, count: 0, bytes: 0 });
+            bucket.count += 1;
+            bucket.bytes += size;
+
+            let group = match grouping {
+                SizeGrouping::Function => {
+                    sizes.entries.push(SizeEntry {
+                        name: function.name.clone(),
+                        address: Some(address),
+                        size,
+                        count: 1,
+                    });
+                    continue;
+                }
+                SizeGrouping::File => match lines.retrieve_overlapping(address, address.saturating_add(size.max(1))).next() {
+                    Some((_, _, line)) => self.source_file_name(line.source_file_id),
+                    None => String::from(UNKNOWN_FILE),
+                },
+                SizeGrouping::Namespace(depth) => scope(&function.name, depth),
+            };
+            let entry = groups.entry(group).or_insert_with_key(|group| SizeEntry {
+                name: group.clone(),
+                address: None,
+                size: 0,
+                count: 0,
+            });
+            entry.size += size;
+            entry.count += 1;
+        }
+        sizes.entries.extend(groups.into_values());
+        sizes.entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        sizes.histogram = histogram.into_values().collect();
+        sizes.histogram.sort_by_key(|bucket| bucket.min_size);
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolFileBuilder;
+
+    #[test]
+    fn test_function_sizes() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "src/os/Thread.cpp")
+            .add_function(0x100, 0x40, 0, "il2cpp::os::Thread::RunWrapper(void*)")
+            .add_line(0x100, 0x40, 10, 1)
+            .add_function(0x140, 0x10, 0, "il2cpp::gc::Collect()")
+            .add_line(0x140, 0x10, 20, 1)
+            .add_function(0x200, 0x90, 0, "std::vector<int, std::allocator<int>>::push_back(int const&)")
+            .add_function(0x300, 0x11, 0, "main");
+        let symbol_file = builder.build();
+
+        let sizes = symbol_file.function_sizes(SizeGrouping::Function);
+        assert_eq!((sizes.function_count, sizes.total_bytes), (4, 0xf1));
+        let names: Vec<(&str, Option<u64>)> = sizes.entries.iter().map(|entry| (entry.name.as_str(), entry.address)).collect();
+        assert_eq!(names[0], ("std::vector<int, std::allocator<int>>::push_back(int const&)", Some(0x200)));
+        assert_eq!(names[3], ("il2cpp::gc::Collect()", Some(0x140)));
+        let buckets: Vec<(u64, usize)> = sizes.histogram.iter().map(|bucket| (bucket.min_size, bucket.count)).collect();
+        assert_eq!(buckets, vec![(0x10, 2), (0x40, 1), (0x80, 1)]);
+
+        let groups = |grouping| symbol_file.function_sizes(grouping).entries.into_iter().map(|entry| (entry.name, entry.size, entry.count)).collect::<Vec<_>>();
+        assert_eq!(
+            groups(SizeGrouping::File),
+            vec![(String::from("??"), 0xa1, 2), (String::from("src/os/Thread.cpp"), 0x50, 2)]
+        );
+        assert_eq!(
+            groups(SizeGrouping::Namespace(1)),
+            vec![(String::from("std"), 0x90, 1), (String::from("il2cpp"), 0x50, 2), (String::from("(global)"), 0x11, 1)]
+        );
+        assert_eq!(groups(SizeGrouping::Namespace(2))[1..3], [(String::from("il2cpp::os"), 0x40, 1), (String::from("(global)"), 0x11, 1)]);
+    }
+}
@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// `--source-map FROM=TO` rules rewriting FILE paths from the build machine to
+// a local checkout, e.g. `/buildslave/unity/src=/home/me/unity`. The longest
+// matching FROM wins, and only whole path components match.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    prefixes: Vec<(String, String)>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, from: impl Into<String>, to: impl Into<String>) -> &mut Self {
+        let from = from.into();
+        let from = from.trim_end_matches(['/', '\\']).to_string();
+        self.prefixes.push((from, to.into()));
+        self
+    }
+
+    // FROM=TO, as given on the command line.
+    pub fn add_rule(&mut self, rule: &str) -> Result<&mut Self, String> {
+        match rule.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(self.add(from, to)),
+            _ => Err(format!("invalid source map({}), expected FROM=TO", rule)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    pub fn remap<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let matched = self
+            .prefixes
+            .iter()
+            .filter(|(from, _)| {
+                path.strip_prefix(from.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '\\']))
+            })
+            .max_by_key(|(from, _)| from.len());
+        let (from, to) = match matched {
+            Some(rule) => rule,
+            None => return Cow::Borrowed(path),
+        };
+        let rest = &path[from.len()..];
+        // A Windows build mapped onto a Unix checkout.
+        let rest = if to.contains('\\') { rest.to_string() } else { rest.replace('\\', "/") };
+        Cow::Owned(format!("{}{}", to.trim_end_matches(['/', '\\']), rest))
+    }
+}
+
+// Finds a FILE path, already remapped, on this machine: as is, then under
+// `root` with leading components dropped one at a time, since the build
+// machine's layout rarely matches ours.
+pub fn find_source_file(root: Option<&Path>, path: &str) -> Option<PathBuf> {
+    if path.is_empty() {
+        return None;
+    }
+    if Path::new(path).is_file() {
+        return Some(PathBuf::from(path));
+    }
+    let root = root?;
+    let components: Vec<&str> = path
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty() && !component.ends_with(':'))
+        .collect();
+    (0..components.len())
+        .map(|skip| root.join(components[skip..].join("/")))
+        .find(|candidate| candidate.is_file())
+}
+
+// The lines of `source` within `radius` of `line` (1-based), numbered, with
+// `line` itself marked:
+//
+//     119 |     Init();
+//   > 120 |     *(int*)0 = 1;
+//     121 | }
+pub fn source_snippet(source: &str, line: i64, radius: usize) -> Option<String> {
+    if line < 1 {
+        return None;
+    }
+    let line = line as usize;
+    let first = line.saturating_sub(radius).max(1);
+    let lines: Vec<&str> = source.lines().skip(first - 1).take(line + radius + 1 - first).collect();
+    if lines.len() <= line - first {
+        return None;
+    }
+    let width = (first + lines.len() - 1).to_string().len();
+    let mut snippet = String::new();
+    for (i, text) in lines.iter().enumerate() {
+        let number = first + i;
+        let marker = if number == line { ">" } else { " " };
+        snippet.push_str(&format!("  {} {:>width$} | {}\n", marker, number, text, width = width));
+    }
+    Some(snippet)
+}
+
+// `source_snippet` for a FILE path found with `find_source_file`.
+pub fn read_source_snippet(root: Option<&Path>, path: &str, line: i64, radius: usize) -> Option<String> {
+    let file = find_source_file(root, path)?;
+    let source = fs::read(&file).ok()?;
+    source_snippet(&String::from_utf8_lossy(&source), line, radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let mut source_map = SourceMap::new();
+        source_map
+            .add_rule("/buildslave/unity/src=/home/me/unity")
+            .unwrap()
+            .add_rule("/buildslave/unity/src/External/=/opt/external/")
+            .unwrap()
+            .add_rule("C:\\build=/mnt/build")
+            .unwrap();
+
+        assert_eq!(source_map.remap("/buildslave/unity/src/Runtime/Player.cpp"), "/home/me/unity/Runtime/Player.cpp");
+        assert_eq!(source_map.remap("/buildslave/unity/src/External/zlib/inflate.c"), "/opt/external/zlib/inflate.c");
+        assert_eq!(source_map.remap("/buildslave/unity/srcfoo/a.cpp"), "/buildslave/unity/srcfoo/a.cpp");
+        assert_eq!(source_map.remap("C:\\build\\Runtime\\Player.cpp"), "/mnt/build/Runtime/Player.cpp");
+        assert_eq!(source_map.remap("Player.cpp"), "Player.cpp");
+
+        assert!(SourceMap::new().add_rule("/no/target").is_err());
+        assert!(SourceMap::new().add_rule("=/home/me").is_err());
+    }
+
+    #[test]
+    fn test_source_snippet() {
+        let source = "a\nb\nc\nd\ne\n";
+        assert_eq!(source_snippet(source, 2, 1).unwrap(), "    1 | a\n  > 2 | b\n    3 | c\n");
+        assert_eq!(source_snippet(source, 1, 1).unwrap(), "  > 1 | a\n    2 | b\n");
+        assert_eq!(source_snippet(source, 5, 0).unwrap(), "  > 5 | e\n");
+        assert!(source_snippet(source, 6, 2).is_none());
+        assert!(source_snippet(source, -1, 2).is_none());
+    }
+
+    #[test]
+    fn test_find_source_file() {
+        let root = std::env::temp_dir().join(format!("addr2line_breakpad_source_{}", std::process::id()));
+        fs::create_dir_all(root.join("Runtime")).unwrap();
+        fs::write(root.join("Runtime").join("Player.cpp"), "int main() {}\n").unwrap();
+
+        let found = find_source_file(Some(&root), "C:\\build\\unity\\Runtime\\Player.cpp");
+        assert_eq!(found, Some(root.join("Runtime/Player.cpp")));
+        assert!(find_source_file(Some(&root), "/buildslave/Runtime/Other.cpp").is_none());
+        assert!(find_source_file(None, "/buildslave/Runtime/Player.cpp").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
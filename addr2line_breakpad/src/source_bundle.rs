@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::logging::debug;
+
+use crate::archive::ZIP_MAGIC;
+use crate::source::source_snippet;
+use crate::{decompressing_reader, GZIP_MAGIC, ZSTD_MAGIC};
+
+const BLOCK: u64 = 512;
+
+/// A zip or tar (`.tar.gz` and `.tar.zst` too) of a build's sources that FILE
+/// paths are resolved into, so symbolicating old builds needs no checkout of
+/// each. Zips and plain tars are read at the entry, compressed tars from the
+/// start for every file, which gets slow for large bundles.
+#[derive(Debug)]
+pub struct SourceBundle {
+    path: PathBuf,
+    format: Format,
+    // By file name, as `find` matches the last path component first.
+    entries: HashMap<String, Vec<Entry>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    CompressedTar,
+}
+
+#[derive(Debug)]
+struct Entry {
+    // As stored in the bundle.
+    name: String,
+    // Offset and size of the data in a plain tar.
+    data: (u64, u64),
+}
+
+// Path components of a FILE path or entry name, without drive letters and
+// `.`, the way `find_source_file` sees them.
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != "." && !component.ends_with(':'))
+        .collect()
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Numeric tar header fields are octal, padded with NULs or spaces.
+fn octal(field: &[u8]) -> io::Result<u64> {
+    let text = std::str::from_utf8(field).map_err(|_| invalid_data("invalid tar header"))?;
+    let text = text.trim_matches(['\0', ' ']);
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid_data("invalid tar header"))
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+// The `path` record of a pax extended header, `<length> path=<value>\n`.
+fn pax_path(records: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(records)
+        .lines()
+        .filter_map(|record| record.split_once(' ')?.1.strip_prefix("path="))
+        .map(String::from)
+        .next_back()
+}
+
+// Hands every regular file of a tar to `visit` with its name, data offset
+// and size, until `visit` returns true. GNU long names and pax paths are
+// followed.
+fn walk_tar(reader: &mut dyn Read, mut visit: impl FnMut(&str, u64, u64, &mut dyn Read) -> io::Result<bool>) -> io::Result<()> {
+    let mut header = [0u8; BLOCK as usize];
+    let mut offset = 0;
+    let mut long_name = None;
+    loop {
+        match reader.read_exact(&mut header) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result?,
+        }
+        // Two zero blocks end the archive.
+        if header.iter().all(|b| *b == 0) {
+            return Ok(());
+        }
+        offset += BLOCK;
+        let size = octal(&header[124..136])?;
+        let padded = size.div_ceil(BLOCK) * BLOCK;
+        let mut data = (&mut *reader).take(padded);
+        match header[156] {
+            b'L' | b'x' => {
+                let mut value = vec![];
+                data.by_ref().take(size).read_to_end(&mut value)?;
+                long_name = if header[156] == b'L' { Some(c_string(&value)) } else { pax_path(&value) };
+            }
+            b'0' | b'7' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = c_string(&header[..100]);
+                    let prefix = c_string(&header[345..500]);
+                    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                        format!("{}/{}", prefix, name)
+                    } else {
+                        name
+                    }
+                });
+                if visit(&name, offset, size, &mut data.by_ref().take(size))? {
+                    return Ok(());
+                }
+            }
+            _ => long_name = None,
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        offset += padded;
+    }
+}
+
+fn open_tar(path: &Path) -> io::Result<Box<dyn io::BufRead>> {
+    decompressing_reader(BufReader::new(File::open(path)?))
+}
+
+#[cfg(feature = "compression")]
+fn open_zip(path: &Path) -> io::Result<zip::ZipArchive<File>> {
+    zip::ZipArchive::new(File::open(path)?).map_err(invalid_data_from)
+}
+
+#[cfg(feature = "compression")]
+fn invalid_data_from(err: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(feature = "compression")]
+fn zip_names(path: &Path) -> io::Result<Vec<String>> {
+    let archive = open_zip(path)?;
+    let names = archive.file_names().filter_map(Result::ok).filter(|name| !name.ends_with('/'));
+    Ok(names.map(|name| name.into_owned()).collect())
+}
+
+#[cfg(not(feature = "compression"))]
+fn zip_names(_: &Path) -> io::Result<Vec<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "zip source bundle, built without the compression feature"))
+}
+
+#[cfg(feature = "compression")]
+fn read_zip_entry(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut archive = open_zip(path)?;
+    let mut file = archive.by_name(name).map_err(invalid_data_from)?;
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "compression"))]
+fn read_zip_entry(_: &Path, _: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "zip source bundle, built without the compression feature"))
+}
+
+impl SourceBundle {
+    /// Lists the files of the bundle at `path`, telling zips from (possibly
+    /// compressed) tars by their magic bytes.
+    pub fn open(path: &Path) -> io::Result<SourceBundle> {
+        let mut magic = [0u8; 4];
+        let read = File::open(path)?.read(&mut magic)?;
+        let magic = &magic[..read];
+        let format = if magic.starts_with(ZIP_MAGIC) {
+            Format::Zip
+        } else if magic.starts_with(GZIP_MAGIC) || magic.starts_with(ZSTD_MAGIC) {
+            Format::CompressedTar
+        } else {
+            Format::Tar
+        };
+
+        let mut bundle = SourceBundle {
+            path: path.to_path_buf(),
+            format,
+            entries: HashMap::new(),
+        };
+        let mut add = |name: &str, data| {
+            if let Some(file_name) = components(name).last() {
+                let entry = Entry { name: name.to_string(), data };
+                bundle.entries.entry(file_name.to_string()).or_default().push(entry);
+            }
+        };
+        match format {
+            Format::Zip => zip_names(path)?.iter().for_each(|name| add(name, (0, 0))),
+            Format::Tar | Format::CompressedTar => walk_tar(&mut open_tar(path)?, |name, offset, size, _| {
+                add(name, (offset, size));
+                Ok(false)
+            })?,
+        }
+        debug!("source bundle {}: {} file name(s)", path.display(), bundle.entries.len());
+        Ok(bundle)
+    }
+
+    /// The entry holding a FILE path, already remapped: the one sharing the
+    /// most trailing path components with it, as `find_source_file` drops
+    /// leading components. The first such entry when several do.
+    pub fn find(&self, path: &str) -> Option<&str> {
+        let wanted = components(path);
+        let shared = |entry: &&Entry| {
+            let components = components(&entry.name);
+            components.iter().rev().zip(wanted.iter().rev()).take_while(|(a, b)| a == b).count()
+        };
+        let entries = self.entries.get(*wanted.last()?)?;
+        entries.iter().rev().max_by_key(shared).map(|entry| entry.name.as_str())
+    }
+
+    /// The contents of the file a FILE path resolves to with [`find`](Self::find).
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        let name = self.find(path)?;
+        let entry = self.entries.values().flatten().find(|entry| entry.name == name)?;
+        let result = match self.format {
+            Format::Zip => read_zip_entry(&self.path, name),
+            Format::Tar => File::open(&self.path).and_then(|mut file| {
+                let (offset, size) = entry.data;
+                file.seek(SeekFrom::Start(offset))?;
+                let mut data = vec![];
+                file.take(size).read_to_end(&mut data)?;
+                Ok(data)
+            }),
+            Format::CompressedTar => {
+                let mut data = None;
+                open_tar(&self.path)
+                    .and_then(|mut reader| {
+                        walk_tar(&mut reader, |entry_name, _, _, reader| {
+                            if entry_name != name {
+                                return Ok(false);
+                            }
+                            let mut contents = vec![];
+                            reader.read_to_end(&mut contents)?;
+                            data = Some(contents);
+                            Ok(true)
+                        })
+                    })
+                    .and_then(|_| data.ok_or_else(|| invalid_data(format!("{} vanished", name))))
+            }
+        };
+        match result {
+            Ok(data) => Some(data),
+            Err(err) => {
+                debug!("{}!{}: {}", self.path.display(), name, err);
+                None
+            }
+        }
+    }
+
+    /// Like [`read_source_snippet`](crate::read_source_snippet), reading the
+    /// file from the bundle.
+    pub fn snippet(&self, path: &str, line: i64, radius: usize) -> Option<String> {
+        let source = self.read(path)?;
+        source_snippet(&String::from_utf8_lossy(&source), line, radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A ustar archive of `files`, with the first name over 100 bytes as a
+    // GNU long name entry.
+    fn tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut tar = vec![];
+        let header = |name: &str, size: usize, kind: u8, tar: &mut Vec<u8>| {
+            let mut block = [0u8; BLOCK as usize];
+            block[..name.len().min(100)].copy_from_slice(&name.as_bytes()[..name.len().min(100)]);
+            block[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+            block[156] = kind;
+            block[257..263].copy_from_slice(b"ustar\0");
+            tar.extend_from_slice(&block);
+        };
+        let data = |bytes: &[u8], tar: &mut Vec<u8>| {
+            tar.extend_from_slice(bytes);
+            tar.resize(tar.len().div_ceil(BLOCK as usize) * BLOCK as usize, 0);
+        };
+        for (name, contents) in files {
+            if name.len() > 100 {
+                header("././@LongLink", name.len() + 1, b'L', &mut tar);
+                data(format!("{}\0", name).as_bytes(), &mut tar);
+            }
+            header(name, contents.len(), b'0', &mut tar);
+            data(contents.as_bytes(), &mut tar);
+        }
+        tar.resize(tar.len() + 2 * BLOCK as usize, 0);
+        tar
+    }
+
+    #[test]
+    fn test_source_bundle_tar() {
+        let long = format!("src/{}/Player.cpp", "deep/".repeat(30).trim_end_matches('/'));
+        let files = [
+            ("./src/Runtime/Player.cpp", "a\nb\nc\n"),
+            ("src/Editor/Player.cpp", "editor\n"),
+            (long.as_str(), "long\n"),
+            ("src/Runtime/Thread.cpp", "thread\n"),
+        ];
+        let path = std::env::temp_dir().join(format!("addr2line_breakpad_sources_{}.tar", std::process::id()));
+        fs::write(&path, tar(&files)).unwrap();
+
+        let bundle = SourceBundle::open(&path).unwrap();
+        assert_eq!(bundle.find("/buildslave/unity/Runtime/Player.cpp"), Some("./src/Runtime/Player.cpp"));
+        assert_eq!(bundle.find("C:\\build\\Editor\\Player.cpp"), Some("src/Editor/Player.cpp"));
+        assert_eq!(bundle.find("/b/deep/Player.cpp"), Some(long.as_str()));
+        assert_eq!(bundle.find("Player.cpp"), Some("./src/Runtime/Player.cpp"));
+        assert!(bundle.find("Other.cpp").is_none());
+        assert_eq!(bundle.read("/b/Runtime/Thread.cpp").unwrap(), b"thread\n");
+        assert_eq!(bundle.snippet("/b/Runtime/Player.cpp", 2, 0).unwrap(), "  > 2 | b\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_source_bundle_compressed() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("addr2line_breakpad_bundles_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let gz = dir.join("sources.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&gz).unwrap(), flate2::Compression::fast());
+        encoder.write_all(&tar(&[("a/Runtime/Player.cpp", "player\n"), ("a/Runtime/Thread.cpp", "thread\n")])).unwrap();
+        encoder.finish().unwrap();
+        assert_eq!(SourceBundle::open(&gz).unwrap().read("Runtime/Thread.cpp").unwrap(), b"thread\n");
+
+        let zip_path = dir.join("sources.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        writer.start_file("Runtime/Player.cpp", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"player\n").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(SourceBundle::open(&zip_path).unwrap().read("/build/Runtime/Player.cpp").unwrap(), b"player\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::Symbol;
+
+/// An output template for resolved addresses, e.g. `{addr} {func} {file}:{line}`.
+/// `{addr}` is the looked up address, `{offset}` how far it is into the
+/// function; `{{` and `}}` are literal braces. A missing file prints as `??`,
+/// a missing line or offset as `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolFormat {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Addr,
+    Func,
+    File,
+    Line,
+    Offset,
+}
+
+impl SymbolFormat {
+    pub const PLACEHOLDERS: [&'static str; 5] = ["addr", "func", "file", "line", "offset"];
+
+    /// Writes `symbol`, looked up at `address`, as the template says. Names
+    /// are written as they are, simplify or remap them beforehand.
+    pub fn format(&self, address: u64, symbol: &Symbol) -> String {
+        let mut out = String::new();
+        for piece in &self.pieces {
+            let _ = match piece {
+                Piece::Literal(text) => out.write_str(text),
+                Piece::Addr => write!(out, "{:#x}", address),
+                Piece::Func => out.write_str(&symbol.function_name),
+                Piece::File if symbol.source_file_name.is_empty() => out.write_str("??"),
+                Piece::File => out.write_str(&symbol.source_file_name),
+                Piece::Line if symbol.source_file_number == -1 => out.write_str("?"),
+                Piece::Line => write!(out, "{}", symbol.source_file_number),
+                Piece::Offset => match symbol.function_offset(address) {
+                    Some(offset) => write!(out, "{:#x}", offset),
+                    None => out.write_str("?"),
+                },
+            };
+        }
+        out
+    }
+}
+
+impl FromStr for SymbolFormat {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push(c);
+                rest = &rest[2..];
+                continue;
+            }
+            if c == '}' {
+                return Err(format!("invalid format({}): unmatched }}", template));
+            }
+            if c != '{' {
+                literal.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let (name, tail) = rest[1..].split_once('}').ok_or_else(|| format!("invalid format({}): unclosed {{", template))?;
+            let piece = match name {
+                "addr" => Piece::Addr,
+                "func" => Piece::Func,
+                "file" => Piece::File,
+                "line" => Piece::Line,
+                "offset" => Piece::Offset,
+                _ => {
+                    return Err(format!(
+                        "invalid format({}): unknown placeholder {{{}}}, expected one of {}",
+                        template,
+                        name,
+                        SymbolFormat::PLACEHOLDERS.join(", ")
+                    ))
+                }
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(piece);
+            rest = tail;
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(SymbolFormat { pieces })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lookup_address, SymbolFileBuilder};
+
+    #[test]
+    fn test_symbol_format() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .add_file(1, "Thread.cpp")
+            .add_function(0x100, 0x200, 0, "RunWrapper")
+            .add_line(0x100, 0x200, 88, 1)
+            .add_public(0x400, 0, "start");
+        let symbol_file = builder.build();
+        let line = lookup_address(&symbol_file, 0x2c4).unwrap();
+        let public = lookup_address(&symbol_file, 0x408).unwrap();
+
+        assert_eq!(line.to_string(), "RunWrapper Thread.cpp:88");
+        assert_eq!(public.to_string(), "start ??:?");
+
+        let format: SymbolFormat = "{addr} {func}+{offset} {{{file}:{line}}}".parse().unwrap();
+        assert_eq!(format.format(0x2c4, &line), "0x2c4 RunWrapper+0x1c4 {Thread.cpp:88}");
+        assert_eq!(format.format(0x408, &public), "0x408 start+0x8 {??:?}");
+        let format: SymbolFormat = "{func}".parse().unwrap();
+        assert_eq!(format.format(0x2c4, &line), "RunWrapper");
+
+        assert!("{function}".parse::<SymbolFormat>().unwrap_err().contains("unknown placeholder {function}"));
+        assert!("{func".parse::<SymbolFormat>().is_err());
+        assert!("func}".parse::<SymbolFormat>().is_err());
+    }
+}
@@ -0,0 +1,707 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+#[cfg(feature = "remote")]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "remote")]
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(feature = "debuginfod")]
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::logging::{debug, warn};
+
+#[cfg(feature = "debuginfod")]
+use crate::{debuginfod_urls, fetch_debuginfo, DwarfModule, SymbolFileBuilder};
+#[cfg(feature = "remote")]
+use crate::fetch_symbol_file;
+use crate::{build_id_to_debug_id, lookup_address, observer, lookup_address_with_options, parse_breakpad_symbol_file, LookupOptions, Symbol, SymbolFile};
+
+/// The module an address belongs to, as a crash report identifies it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleId<'a> {
+    pub name: &'a str,
+    /// Breakpad debug id, from an ips image uuid or a minidump.
+    pub debug_id: Option<&'a str>,
+    /// ELF build id, from an Android tombstone.
+    pub build_id: Option<&'a str>,
+}
+
+impl<'a> ModuleId<'a> {
+    pub fn new(name: &'a str) -> Self {
+        ModuleId {
+            name,
+            debug_id: None,
+            build_id: None,
+        }
+    }
+
+    pub fn with_debug_id(mut self, debug_id: &'a str) -> Self {
+        self.debug_id = Some(debug_id).filter(|debug_id| !debug_id.is_empty());
+        self
+    }
+
+    pub fn with_build_id(mut self, build_id: &'a str) -> Self {
+        self.build_id = Some(build_id).filter(|build_id| !build_id.is_empty());
+        self
+    }
+
+    // Like `Module::matches` and `Module::matches_build_id`: a build id
+    // decides if the symbols know theirs, else the name or debug id does.
+    fn matches(&self, name: &str, debug_id: &str, code_id: Option<&str>) -> bool {
+        let same_id = |id: &str, other: &str| !other.is_empty() && id.eq_ignore_ascii_case(other);
+        if let Some(build_id) = self.build_id {
+            if let Some(code_id) = code_id {
+                return same_id(build_id, code_id);
+            }
+            if !debug_id.is_empty() {
+                return same_id(&build_id_to_debug_id(build_id), debug_id);
+            }
+        }
+        self.name == name || self.debug_id.is_some_and(|id| same_id(id, debug_id) || code_id.is_some_and(|code_id| same_id(id, code_id)))
+    }
+}
+
+/// A source of symbols for the modules of a crash report. Front-ends like
+/// `Microdump::symbolicate` only need this, so any backend, or one of the
+/// caller's own, can answer them.
+pub trait Symbolicator {
+    /// The symbol at `rel_addr`, relative to the module's load address, or
+    /// None if the module is unknown or the address not covered.
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>>;
+}
+
+// A lone symbol file answers for its MODULE, or for anything without one.
+impl Symbolicator for SymbolFile {
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        if let Some(record) = self.module().filter(|record| !module.matches(&record.name, &record.debug_id, self.code_id())) {
+            debug!("{} is not module {}", module.name, record.name);
+            return None;
+        }
+        lookup_address(self, rel_addr)
+    }
+}
+
+// The debug info knows no module name, so it answers for any module.
+#[cfg(feature = "debuginfod")]
+impl Symbolicator for DwarfModule {
+    fn lookup(&self, _: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        DwarfModule::lookup(self, rel_addr)
+    }
+}
+
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub debug_id: String,
+    pub symbols: SymbolFile,
+    // Consulted for addresses the breakpad symbols don't cover.
+    #[cfg(feature = "debuginfod")]
+    pub dwarf: Option<DwarfModule>,
+    #[cfg(feature = "debuginfod")]
+    debuginfod: Option<DebuginfodFallback>,
+    lookups: AtomicUsize,
+    hits: AtomicUsize,
+}
+
+// Debug info for the module's build id, fetched from debuginfod on the first
+// address the breakpad symbols miss.
+#[cfg(feature = "debuginfod")]
+#[derive(Debug)]
+struct DebuginfodFallback {
+    build_id: String,
+    cache_dir: PathBuf,
+    demangle: bool,
+    dwarf: OnceLock<Option<DwarfModule>>,
+}
+
+#[cfg(feature = "debuginfod")]
+impl DebuginfodFallback {
+    fn dwarf(&self) -> Option<&DwarfModule> {
+        self.dwarf
+            .get_or_init(|| match fetch_debuginfo(&self.build_id, &self.cache_dir).and_then(|path| DwarfModule::open(&path)) {
+                Ok(mut dwarf) => {
+                    dwarf.set_demangle(self.demangle);
+                    Some(dwarf)
+                }
+                Err(err) => {
+                    warn!("{}", err);
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// How many lookups a module answered, for `--stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LookupStats {
+    pub lookups: usize,
+    pub hits: usize,
+}
+
+impl LookupStats {
+    pub fn misses(&self) -> usize {
+        self.lookups - self.hits
+    }
+}
+
+impl Module {
+    // Name and debug id come from the MODULE record, if there is one.
+    pub fn new(symbols: SymbolFile) -> Self {
+        let (name, debug_id) = match symbols.module() {
+            Some(record) => (record.name.clone(), record.debug_id.clone()),
+            None => (String::new(), String::new()),
+        };
+        Module {
+            name,
+            debug_id,
+            symbols,
+            #[cfg(feature = "debuginfod")]
+            dwarf: None,
+            #[cfg(feature = "debuginfod")]
+            debuginfod: None,
+            lookups: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    // Module backed only by the DWARF debug info a debuginfod server has for
+    // `build_id`.
+    #[cfg(feature = "debuginfod")]
+    pub fn from_debuginfod(name: &str, build_id: &str, cache_dir: &Path) -> std::io::Result<Self> {
+        let dwarf = DwarfModule::open(&fetch_debuginfo(build_id, cache_dir)?)?;
+        Ok(Module {
+            name: name.to_string(),
+            debug_id: build_id_to_debug_id(build_id),
+            symbols: SymbolFileBuilder::new().build(),
+            dwarf: Some(dwarf),
+            debuginfod: None,
+            lookups: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    // Module backed by a local ELF file with DWARF or just a symbol table,
+    // such as `native-debug-symbols.zip!arm64-v8a/libil2cpp.so.sym` from
+    // Google Play. Archive entries are extracted into `cache_dir` first,
+    // `module_hint` picks one as for breakpad symbols.
+    #[cfg(feature = "debuginfod")]
+    pub fn from_elf(name: &str, path: &Path, module_hint: Option<&str>, cache_dir: &Path) -> std::io::Result<Self> {
+        let (archive, entry) = crate::archive::split_archive_path(path);
+        let path = if entry.is_some() || !crate::is_elf_file(&archive, None) {
+            crate::archive::extract_zip_entry(&archive, entry.as_deref(), module_hint, cache_dir)?
+        } else {
+            archive
+        };
+        Ok(Module {
+            name: name.to_string(),
+            debug_id: String::new(),
+            symbols: SymbolFileBuilder::new().build(),
+            dwarf: Some(DwarfModule::open(&path)?),
+            debuginfod: None,
+            lookups: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        Module::new(parse_breakpad_symbol_file(path))
+    }
+
+    // Resolves addresses the breakpad symbols miss from the debug info the
+    // DEBUGINFOD_URLS servers have for the code id, the ELF build id of Linux
+    // and Android modules. It is only fetched on the first miss. Does nothing
+    // for modules with DWARF already, without a build id, or when no servers
+    // are configured.
+    #[cfg(feature = "debuginfod")]
+    pub fn enable_debuginfod(&mut self, cache_dir: &Path, demangle: bool) {
+        let is_elf = self.symbols.module().is_none_or(|module| module.os == "Linux");
+        let build_id = match self.symbols.code_id() {
+            Some(code_id) if is_elf && self.dwarf.is_none() && !debuginfod_urls().is_empty() => code_id,
+            _ => return,
+        };
+        self.debuginfod = Some(DebuginfodFallback {
+            build_id: build_id.to_string(),
+            cache_dir: cache_dir.to_path_buf(),
+            demangle,
+            dwarf: OnceLock::new(),
+        });
+    }
+
+    // Where addresses the breakpad symbols miss are looked up.
+    #[cfg(feature = "debuginfod")]
+    fn fallback_dwarf(&self) -> Option<&DwarfModule> {
+        self.dwarf.as_ref().or_else(|| self.debuginfod.as_ref()?.dwarf())
+    }
+
+    // Matches the module name, its debug id or its code id (case-insensitively,
+    // crash reports print uuids and build ids in lower case). Android reports
+    // identify libraries by ELF build id, which breakpad stores as the code id.
+    pub fn matches(&self, module_name_or_id: &str) -> bool {
+        self.name == module_name_or_id
+            || (!self.debug_id.is_empty() && self.debug_id.eq_ignore_ascii_case(module_name_or_id))
+            || self.symbols.code_id().is_some_and(|code_id| code_id.eq_ignore_ascii_case(module_name_or_id))
+    }
+
+    // Whether the module was built from the ELF file with `build_id`: Some if
+    // it knows its code id or debug id, None if there is nothing to compare.
+    pub fn matches_build_id(&self, build_id: &str) -> Option<bool> {
+        if let Some(code_id) = self.symbols.code_id() {
+            return Some(code_id.eq_ignore_ascii_case(build_id));
+        }
+        if self.debug_id.is_empty() {
+            return None;
+        }
+        Some(self.debug_id.eq_ignore_ascii_case(&build_id_to_debug_id(build_id)))
+    }
+
+    pub fn lookup(&self, rel_addr: u64) -> Option<Symbol<'_>> {
+        let started = observer::start();
+        let symbol = lookup_address(&self.symbols, rel_addr);
+        #[cfg(feature = "debuginfod")]
+        let symbol = symbol.or_else(|| self.fallback_dwarf()?.lookup(rel_addr));
+        self.count_lookup(rel_addr, symbol.is_some(), started);
+        symbol
+    }
+
+    // `lookup` for many addresses, fastest in ascending order; see
+    // `lookup_addresses`.
+    pub fn lookup_addresses(&self, sorted: &[u64]) -> Vec<Option<Symbol<'_>>> {
+        let mut cursor = self.symbols.lookup_cursor();
+        sorted
+            .iter()
+            .map(|&rel_addr| {
+                let started = observer::start();
+                let symbol = cursor.lookup(rel_addr);
+                #[cfg(feature = "debuginfod")]
+                let symbol = symbol.or_else(|| self.fallback_dwarf()?.lookup(rel_addr));
+                self.count_lookup(rel_addr, symbol.is_some(), started);
+                symbol
+            })
+            .collect()
+    }
+
+    // Every symbol `options` asks for, the preferred one first. Empty if the
+    // address could not be resolved.
+    pub fn lookup_with_options(&self, rel_addr: u64, options: &LookupOptions) -> Vec<Symbol<'_>> {
+        let started = observer::start();
+        let symbols = lookup_address_with_options(&self.symbols, rel_addr, options);
+        #[cfg(feature = "debuginfod")]
+        let symbols = if symbols.is_empty() {
+            self.fallback_dwarf().and_then(|dwarf| dwarf.lookup(rel_addr)).into_iter().collect()
+        } else {
+            symbols
+        };
+        self.count_lookup(rel_addr, !symbols.is_empty(), started);
+        symbols
+    }
+
+    fn count_lookup(&self, rel_addr: u64, hit: bool, started: Option<Instant>) {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        observer::observe(started, |observer, duration| observer.lookup(&self.name, rel_addr, hit, duration));
+    }
+
+    pub fn lookup_stats(&self) -> LookupStats {
+        LookupStats {
+            lookups: self.lookups.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Symbolicator for Module {
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        if !module.matches(&self.name, &self.debug_id, self.symbols.code_id()) {
+            return None;
+        }
+        Module::lookup(self, rel_addr)
+    }
+}
+
+// Holds the symbols of many modules, so one crash report can be symbolicated
+// against all of its images.
+#[derive(Debug, Default)]
+pub struct ModuleSet {
+    modules: Vec<Module>,
+    aliases: HashMap<String, String>,
+}
+
+impl ModuleSet {
+    pub fn new() -> Self {
+        ModuleSet {
+            modules: vec![],
+            aliases: HashMap::new(),
+        }
+    }
+
+    // Lets `image_name` from a crash report match the module named
+    // `module_name` in its symbol file. Aliases go both ways, so configs
+    // may list the pair in either order.
+    pub fn add_alias(&mut self, image_name: &str, module_name: &str) {
+        self.aliases.insert(image_name.to_string(), module_name.to_string());
+    }
+
+    fn alias(&self, name: &str) -> Option<&String> {
+        self.aliases
+            .get(name)
+            .or_else(|| self.aliases.iter().find(|(_, module_name)| *module_name == name).map(|(image_name, _)| image_name))
+    }
+
+    pub fn add_module(&mut self, module: Module) {
+        self.modules.push(module);
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn module(&self, module_name_or_id: &str) -> Option<&Module> {
+        let alias = self.alias(module_name_or_id);
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.matches(module_name_or_id) || alias.is_some_and(|alias| module.matches(alias)));
+        match (module, alias) {
+            (Some(module), Some(alias)) => debug!("{} matched module {} via alias {}", module_name_or_id, module.name, alias),
+            (Some(module), None) => debug!("{} matched module {}", module_name_or_id, module.name),
+            (None, _) => debug!("no symbols for {}", module_name_or_id),
+        }
+        module
+    }
+
+    // Picks the module for an image by build id first, so renamed libraries
+    // and several builds of one library resolve to the right symbols. A
+    // module matching only by name is rejected if it is of another build.
+    pub fn module_with_build_id(&self, module_name: &str, build_id: Option<&str>) -> Option<&Module> {
+        let build_id = match build_id {
+            Some(build_id) => build_id,
+            None => return self.module(module_name),
+        };
+        if let Some(module) = self.modules.iter().find(|module| module.matches_build_id(build_id) == Some(true)) {
+            debug!("{} (BuildId {}) matched module {}", module_name, build_id, module.name);
+            return Some(module);
+        }
+        let module = self.module(module_name)?;
+        if module.matches_build_id(build_id) == Some(false) {
+            warn!(
+                "symbols for {} are of another build (BuildId {}, have {})",
+                module_name,
+                build_id,
+                module.symbols.code_id().unwrap_or(&module.debug_id)
+            );
+            return None;
+        }
+        Some(module)
+    }
+
+    pub fn lookup(&self, module_name_or_id: &str, rel_addr: u64) -> Option<Symbol<'_>> {
+        self.module(module_name_or_id)?.lookup(rel_addr)
+    }
+}
+
+impl Symbolicator for ModuleSet {
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        let found = match module.build_id {
+            Some(build_id) => self.module_with_build_id(module.name, Some(build_id)),
+            None => module.debug_id.and_then(|debug_id| self.module(debug_id)).or_else(|| self.module(module.name)),
+        };
+        found?.lookup(rel_addr)
+    }
+}
+
+// (name, debug id, build id, address)
+type CacheKey = (String, Option<String>, Option<String>, u64);
+
+// Least recently used first by the tick of their last use.
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<CacheKey, (Option<Symbol<'static>>, u64)>,
+    order: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl Lru {
+    fn get(&mut self, key: &CacheKey) -> Option<Option<Symbol<'static>>> {
+        let (symbol, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        self.tick += 1;
+        *used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(symbol.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, symbol: Option<Symbol<'static>>, capacity: usize) {
+        if self.entries.len() >= capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        if let Some((_, used)) = self.entries.insert(key, (symbol, self.tick)) {
+            self.order.remove(&used);
+        }
+    }
+}
+
+/// How many lookups a [`CachingSymbolicator`] answered from its cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+/// Remembers the results, misses too, of the last `capacity` lookups of
+/// another symbolicator, for servers seeing the same hot addresses in every
+/// report.
+#[derive(Debug)]
+pub struct CachingSymbolicator<S> {
+    inner: S,
+    capacity: usize,
+    cache: Mutex<Lru>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<S: Symbolicator> CachingSymbolicator<S> {
+    pub fn new(inner: S, capacity: usize) -> Self {
+        CachingSymbolicator {
+            inner,
+            capacity: capacity.max(1),
+            cache: Mutex::new(Lru::default()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.cache.lock().unwrap().entries.len(),
+        }
+    }
+
+    pub fn clear(&self) {
+        *self.cache.lock().unwrap() = Lru::default();
+    }
+}
+
+impl<S: Symbolicator> Symbolicator for CachingSymbolicator<S> {
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        let key = (module.name.to_string(), module.debug_id.map(str::to_string), module.build_id.map(str::to_string), rel_addr);
+        let cached = self.cache.lock().unwrap().get(&key);
+        if let Some(symbol) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return symbol;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Not under the lock, the inner lookup may be slow.
+        let symbol = self.inner.lookup(module, rel_addr).map(Symbol::into_owned);
+        self.cache.lock().unwrap().insert(key, symbol.clone(), self.capacity);
+        symbol
+    }
+}
+
+// Modules by name and debug id; None if the store has no symbols for one.
+#[cfg(feature = "remote")]
+type FetchedModules = HashMap<(String, String), Option<Arc<Module>>>;
+
+/// Downloads the symbols of each module from a symbol store (see
+/// [`fetch_symbol_file`]) on its first lookup. Modules without a debug or
+/// build id can't be looked up in a store and are not resolved.
+#[cfg(feature = "remote")]
+#[derive(Debug)]
+pub struct RemoteSymbolicator {
+    location: String,
+    cache_dir: PathBuf,
+    modules: Mutex<FetchedModules>,
+}
+
+#[cfg(feature = "remote")]
+impl RemoteSymbolicator {
+    pub fn new(location: &str, cache_dir: &Path) -> Self {
+        RemoteSymbolicator {
+            location: location.to_string(),
+            cache_dir: cache_dir.to_path_buf(),
+            modules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn module(&self, name: &str, debug_id: String) -> Option<Arc<Module>> {
+        let mut modules = self.modules.lock().unwrap();
+        let module = modules.entry((name.to_string(), debug_id)).or_insert_with_key(|(name, debug_id)| {
+            match fetch_symbol_file(&self.location, Some((name, debug_id)), &self.cache_dir) {
+                Ok(path) => Some(Arc::new(Module::from_path(&path))),
+                Err(err) => {
+                    warn!("no symbols for {} {} in {}: {}", name, debug_id, self.location, err);
+                    None
+                }
+            }
+        });
+        module.clone()
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Symbolicator for RemoteSymbolicator {
+    fn lookup(&self, module: &ModuleId, rel_addr: u64) -> Option<Symbol<'_>> {
+        let debug_id = module.debug_id.map(str::to_string).or_else(|| module.build_id.map(build_id_to_debug_id))?;
+        // The module may be evicted with the lock released, keep no borrows.
+        self.module(module.name, debug_id)?.lookup(rel_addr).map(Symbol::into_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use crate::{SymbolFileBuilder, SymbolKind};
+
+    fn module(name: &str, debug_id: &str, function: &str) -> Module {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("mac", "arm64", debug_id, name)
+            .add_function(0x100, 0x10, 0, function);
+        Module::new(builder.build())
+    }
+
+    #[test]
+    fn test_lookup_by_name_or_id() {
+        let mut symbolicator = ModuleSet::new();
+        symbolicator.add_module(module("UnityFramework", "EE27598B6A283E389075D1014679C8B00", "unity"));
+        symbolicator.add_module(module("jxpocket", "567DC4978A7B3006A6E4B333A73D8AC30", "app"));
+
+        assert_eq!(symbolicator.lookup("UnityFramework", 0x104).unwrap().function_name, "unity");
+        assert_eq!(symbolicator.lookup("jxpocket", 0x104).unwrap().function_name, "app");
+        assert_eq!(
+            symbolicator.lookup("ee27598b6a283e389075d1014679c8b00", 0x104).unwrap().function_name,
+            "unity"
+        );
+        assert!(symbolicator.lookup("libsystem_kernel.dylib", 0x104).is_none());
+
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0", "libil2cpp.so")
+            .set_code_id("B4B2BD4FB3AC2B43F6AD8E4A87BA9D4BB8C8D8A1", None)
+            .add_function(0x100, 0x10, 0, "il2cpp");
+        symbolicator.add_module(Module::new(builder.build()));
+        assert_eq!(
+            symbolicator.lookup("b4b2bd4fb3ac2b43f6ad8e4a87ba9d4bb8c8d8a1", 0x104).unwrap().function_name,
+            "il2cpp"
+        );
+
+        // Tombstones name the build; a same-named library of another build is
+        // not a match.
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "0102030405060708090A0B0C0D0E0F100", "libil2cpp.so")
+            .add_function(0x100, 0x10, 0, "il2cpp_old");
+        symbolicator.add_module(Module::new(builder.build()));
+        let module = symbolicator.module_with_build_id("libil2cpp.so", Some("b4b2bd4fb3ac2b43f6ad8e4a87ba9d4bb8c8d8a1")).unwrap();
+        assert_eq!(module.lookup(0x104).unwrap().function_name, "il2cpp");
+        let module = symbolicator.module_with_build_id("renamed.so", Some("0403020106050807090a0b0c0d0e0f10")).unwrap();
+        assert_eq!(module.lookup(0x104).unwrap().function_name, "il2cpp_old");
+        assert!(symbolicator.module_with_build_id("UnityFramework", Some("ffff")).is_none());
+        assert!(symbolicator.module_with_build_id("UnityFramework", None).is_some());
+
+        symbolicator.add_alias("libunity.so", "UnityFramework");
+        assert_eq!(symbolicator.lookup("libunity.so", 0x104).unwrap().function_name, "unity");
+        symbolicator.add_alias("jxpocket", "jxpocket.app");
+        assert_eq!(symbolicator.module("jxpocket.app").unwrap().name, "jxpocket");
+
+        assert!(symbolicator.lookup("UnityFramework", 0x200).is_none());
+        let stats = symbolicator.module("UnityFramework").unwrap().lookup_stats();
+        assert_eq!((stats.lookups, stats.hits, stats.misses()), (4, 3, 1));
+
+        let module = symbolicator.module("jxpocket").unwrap();
+        let names: Vec<_> = module.lookup_addresses(&[0x100, 0x10f, 0x200]).into_iter().map(|symbol| symbol.map(|symbol| symbol.function_name)).collect();
+        assert_eq!(names, vec![Some(Cow::Borrowed("app")), Some(Cow::Borrowed("app")), None]);
+        assert_eq!(module.lookup_stats().hits, 3);
+    }
+
+    #[test]
+    fn test_symbolicator_trait() {
+        fn lookup(symbolicator: &dyn Symbolicator, module: ModuleId) -> Option<String> {
+            symbolicator.lookup(&module, 0x104).map(|symbol| symbol.function_name.into_owned())
+        }
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0", "libil2cpp.so")
+            .add_function(0x100, 0x10, 0, "il2cpp");
+        let symbol_file = builder.build();
+        let build_id = "b4b2bd4fb3ac2b43f6ad8e4a87ba9d4bb8c8d8a1";
+
+        assert_eq!(lookup(&symbol_file, ModuleId::new("libil2cpp.so")).as_deref(), Some("il2cpp"));
+        assert_eq!(lookup(&symbol_file, ModuleId::new("renamed.so").with_build_id(build_id)).as_deref(), Some("il2cpp"));
+        assert_eq!(lookup(&symbol_file, ModuleId::new("libil2cpp.so").with_build_id("ffff")), None);
+        assert_eq!(lookup(&symbol_file, ModuleId::new("libunity.so")), None);
+        assert_eq!(lookup(&SymbolFileBuilder::new().build(), ModuleId::new("libunity.so")), None);
+
+        let mut symbolicator = ModuleSet::new();
+        symbolicator.add_module(Module::new(symbol_file));
+        symbolicator.add_module(module("UnityFramework", "EE27598B6A283E389075D1014679C8B00", "unity"));
+        assert_eq!(lookup(&symbolicator, ModuleId::new("UnityFramework")).as_deref(), Some("unity"));
+        assert_eq!(lookup(&symbolicator, ModuleId::new("?").with_debug_id("ee27598b6a283e389075d1014679c8b00")).as_deref(), Some("unity"));
+        assert_eq!(lookup(&symbolicator, ModuleId::new("renamed.so").with_build_id(build_id)).as_deref(), Some("il2cpp"));
+        assert_eq!(lookup(&symbolicator.modules()[1], ModuleId::new("libil2cpp.so")), None);
+
+        // Callers plug in their own sources.
+        struct Constant;
+        impl Symbolicator for Constant {
+            fn lookup(&self, module: &ModuleId, _: u64) -> Option<Symbol<'_>> {
+                Some(Symbol {
+                    function_name: Cow::Owned(module.name.to_string()),
+                    source_file_name: Cow::Borrowed(""),
+                    source_file_number: -1,
+                    kind: SymbolKind::Public,
+                    stack_param_size: 0,
+                    function_address: None,
+                    function_size: None,
+                })
+            }
+        }
+        assert_eq!(lookup(&Constant, ModuleId::new("libfoo.so")).as_deref(), Some("libfoo.so"));
+    }
+
+    #[test]
+    fn test_caching_symbolicator() {
+        let mut symbolicator = ModuleSet::new();
+        symbolicator.add_module(module("UnityFramework", "EE27598B6A283E389075D1014679C8B00", "unity"));
+        let cache = CachingSymbolicator::new(symbolicator, 2);
+        let unity = ModuleId::new("UnityFramework");
+        let lookup = |rel_addr| cache.lookup(&unity, rel_addr).map(|symbol| symbol.function_name);
+
+        assert_eq!(lookup(0x104).as_deref(), Some("unity"));
+        assert_eq!(lookup(0x104).as_deref(), Some("unity"));
+        assert_eq!(lookup(0x200), None);
+        assert_eq!(lookup(0x200), None);
+        assert_eq!(cache.cache_stats(), CacheStats { hits: 2, misses: 2, entries: 2 });
+
+        // 0x104 was used last, so 0x200 is evicted.
+        lookup(0x104);
+        lookup(0x108);
+        lookup(0x104);
+        assert_eq!(cache.cache_stats(), CacheStats { hits: 4, misses: 3, entries: 2 });
+        lookup(0x200);
+        assert_eq!(cache.cache_stats().misses, 4);
+        assert_eq!(cache.inner().modules()[0].lookup_stats().lookups, 4);
+
+        cache.clear();
+        assert_eq!(cache.cache_stats().entries, 0);
+    }
+}
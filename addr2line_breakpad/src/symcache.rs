@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Write};
+
+use crate::{source_basename, source_dir, SymbolFile, SymbolFileBuilder};
+
+// Sentry's SymCache, version 7, as `symbolic-symcache` reads and writes it.
+// Little endian, every section padded to 8 bytes:
+//
+//   header      magic "SYMC", version, debug id (16 byte uuid, u32 appendix,
+//               11 bytes padding, u8 type), arch, the counts of strings,
+//               files, functions, source locations and ranges, the size of
+//               the string bytes, 16 reserved bytes
+//   strings     (offset, length) into the string bytes
+//   files       (comp dir, directory, name) string indices
+//   functions   (name, comp dir) string indices, entry pc, language
+//   locations   (file, line, function, inlined into) indices
+//   ranges      start address of each range, ascending; range i is covered
+//               by location i, which may be inlined into others
+//   string bytes
+//
+// Missing indices are u32::MAX. A range whose location has no function ends
+// the one before it.
+const SYMCACHE_MAGIC: &[u8; 4] = b"SYMC";
+const SYMCACHE_VERSION: u32 = 7;
+const HEADER_SIZE: usize = 84;
+const NONE: u32 = u32::MAX;
+
+// Numbering of `symbolic_common::Arch`.
+const ARCHS: [(&str, u32); 7] = [("x86", 101), ("x86_64", 201), ("arm", 301), ("armv7", 305), ("arm64", 401), ("arm64e", 403), ("ppc", 601)];
+
+/// Whether `data` starts like a SymCache.
+pub fn is_symcache(data: &[u8]) -> bool {
+    data.starts_with(SYMCACHE_MAGIC)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct SourceLocation {
+    file: u32,
+    line: u32,
+    function: u32,
+    inlined_into: u32,
+}
+
+const END_OF_FUNCTION: SourceLocation = SourceLocation {
+    file: NONE,
+    line: 0,
+    function: NONE,
+    inlined_into: NONE,
+};
+
+#[derive(Default)]
+struct Strings {
+    entries: Vec<(u32, u32)>,
+    bytes: Vec<u8>,
+    indices: HashMap<String, u32>,
+}
+
+impl Strings {
+    fn insert(&mut self, string: &str) -> u32 {
+        if let Some(index) = self.indices.get(string) {
+            return *index;
+        }
+        let index = self.entries.len() as u32;
+        self.entries.push((self.bytes.len() as u32, string.len() as u32));
+        self.bytes.extend_from_slice(string.as_bytes());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{} does not fit a SymCache", what))
+}
+
+fn to_u32(value: u64, what: &str) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| too_large(what))
+}
+
+impl SymbolFile {
+    /// Writes the FUNC, LINE and PUBLIC records as a SymCache, for tools of
+    /// the `symbolic` ecosystem. Addresses must fit 32 bits.
+    pub fn write_symcache(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut strings = Strings::default();
+        let mut files: Vec<[u32; 3]> = vec![];
+        let mut file_indices: HashMap<i64, u32> = HashMap::new();
+        let mut functions: Vec<[u32; 4]> = vec![];
+        let mut ranges: BTreeMap<u64, SourceLocation> = BTreeMap::new();
+        let loaded_lines = self.loaded_lines();
+        let lines = loaded_lines.as_ref().unwrap_or(&self.lines);
+
+        // PUBLIC records reach up to the next symbol, FUNCs override them.
+        for (address, public) in &self.public_symbols {
+            let function = functions.len() as u32;
+            functions.push([strings.insert(&public.name), NONE, to_u32(*address, "PUBLIC address")?, 0]);
+            ranges.insert(*address, SourceLocation { function, ..END_OF_FUNCTION });
+        }
+        for (address, size, func) in self.functions.iter() {
+            let function = functions.len() as u32;
+            functions.push([strings.insert(&func.name), NONE, to_u32(address, "FUNC address")?, 0]);
+            let end = address.checked_add(size).ok_or_else(|| too_large("FUNC size"))?;
+            to_u32(end, "FUNC end")?;
+            // FUNCs win over the PUBLIC records inside them, as in lookups.
+            let inside: Vec<u64> = ranges.range(address..end).map(|(address, _)| *address).collect();
+            for address in inside {
+                ranges.remove(&address);
+            }
+            let without_line = SourceLocation { function, ..END_OF_FUNCTION };
+            ranges.insert(address, without_line);
+            for (line_address, line_size, line) in lines.retrieve_overlapping(address, end) {
+                let start = line_address.max(address);
+                let file = match file_indices.get(&line.source_file_id) {
+                    Some(file) => *file,
+                    None => {
+                        let path = self.source_file_name(line.source_file_id);
+                        let directory = source_dir(&path);
+                        let directory = if directory.is_empty() { NONE } else { strings.insert(directory) };
+                        files.push([NONE, directory, strings.insert(source_basename(&path))]);
+                        file_indices.insert(line.source_file_id, files.len() as u32 - 1);
+                        files.len() as u32 - 1
+                    }
+                };
+                let line_number = u32::try_from(line.line_number).unwrap_or(0);
+                ranges.insert(start, SourceLocation { file, line: line_number, function, inlined_into: NONE });
+                let line_end = line_address.saturating_add(line_size).min(end);
+                if line_end < end {
+                    ranges.entry(line_end).or_insert(without_line);
+                }
+            }
+            // Unless a PUBLIC starts right there; the next FUNC replaces it.
+            ranges.entry(end).or_insert(END_OF_FUNCTION);
+        }
+
+        let module = self.module.as_ref();
+        let mut out = vec![];
+        out.extend_from_slice(SYMCACHE_MAGIC);
+        out.extend_from_slice(&SYMCACHE_VERSION.to_le_bytes());
+        out.extend_from_slice(&encode_debug_id(module.map_or("", |module| module.debug_id.as_str())));
+        let arch = module.and_then(|module| ARCHS.iter().find(|(name, _)| module.arch.eq_ignore_ascii_case(name)));
+        out.extend_from_slice(&arch.map_or(0, |(_, arch)| *arch).to_le_bytes());
+        for count in [strings.entries.len(), files.len(), functions.len(), ranges.len(), ranges.len(), strings.bytes.len()] {
+            out.extend_from_slice(&to_u32(count as u64, "symbol file")?.to_le_bytes());
+        }
+        out.extend_from_slice(&[0; 16]);
+        let words = |out: &mut Vec<u8>, values: &[u32]| {
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        align(&mut out);
+        for (offset, len) in &strings.entries {
+            words(&mut out, &[*offset, *len]);
+        }
+        align(&mut out);
+        for file in &files {
+            words(&mut out, file);
+        }
+        align(&mut out);
+        for function in &functions {
+            words(&mut out, function);
+        }
+        align(&mut out);
+        for location in ranges.values() {
+            words(&mut out, &[location.file, location.line, location.function, location.inlined_into]);
+        }
+        align(&mut out);
+        for address in ranges.keys() {
+            words(&mut out, &[*address as u32]);
+        }
+        align(&mut out);
+        out.extend_from_slice(&strings.bytes);
+        writer.write_all(&out)
+    }
+}
+
+fn align(out: &mut Vec<u8>) {
+    out.resize(out.len().next_multiple_of(8), 0);
+}
+
+// Breakpad's 33 hex digit debug id: the uuid, then the age as appendix.
+fn encode_debug_id(debug_id: &str) -> [u8; 32] {
+    let mut raw = [0u8; 32];
+    let hex = |digits: &str| u32::from_str_radix(digits, 16).ok();
+    if debug_id.len() > 32 && debug_id.is_char_boundary(32) {
+        for (i, byte) in raw[..16].iter_mut().enumerate() {
+            *byte = hex(&debug_id[i * 2..i * 2 + 2]).unwrap_or(0) as u8;
+        }
+        raw[16..20].copy_from_slice(&hex(&debug_id[32..]).unwrap_or(0).to_le_bytes());
+    }
+    raw
+}
+
+fn decode_debug_id(raw: &[u8]) -> String {
+    let uuid: String = raw[..16].iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!("{}{:X}", uuid, u32::from_le_bytes(raw[16..20].try_into().unwrap()))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| String::from("truncated SymCache"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    // `count` records of `N` u32 each, from the next 8 byte boundary.
+    fn records<const N: usize>(&mut self, count: u32) -> Result<Vec<[u32; N]>, String> {
+        self.offset = self.offset.next_multiple_of(8);
+        (0..count)
+            .map(|_| {
+                let mut record = [0; N];
+                for value in &mut record {
+                    *value = self.u32()?;
+                }
+                Ok(record)
+            })
+            .collect()
+    }
+}
+
+/// Reads a SymCache written by `symbolic-symcache` (version 7) or
+/// [`SymbolFile::write_symcache`] as a symbol file, so it serves lookups
+/// like any other. Inlined frames are folded into the function they are
+/// inlined into, with the line of the innermost one. SymCaches carry no
+/// module name, `name` is used.
+pub fn parse_symcache(data: &[u8], name: &str) -> Result<SymbolFile, String> {
+    let mut reader = Reader { data, offset: 0 };
+    if reader.take(4)? != SYMCACHE_MAGIC {
+        return Err(String::from("not a SymCache"));
+    }
+    let version = reader.u32()?;
+    if version != SYMCACHE_VERSION {
+        return Err(format!("unsupported SymCache version {}, expected {}", version, SYMCACHE_VERSION));
+    }
+    let debug_id = decode_debug_id(reader.take(32)?);
+    let arch = reader.u32()?;
+    let mut counts = [0; 6];
+    for count in &mut counts {
+        *count = reader.u32()?;
+    }
+    let [num_strings, num_files, num_functions, num_locations, num_ranges, string_bytes] = counts;
+    reader.take(16)?;
+    debug_assert_eq!(reader.offset, HEADER_SIZE);
+    let string_entries = reader.records::<2>(num_strings)?;
+    let files = reader.records::<3>(num_files)?;
+    let functions = reader.records::<4>(num_functions)?;
+    let locations = reader.records::<4>(num_locations)?;
+    let ranges = reader.records::<1>(num_ranges)?;
+    reader.offset = reader.offset.next_multiple_of(8);
+    let bytes = reader.take(string_bytes as usize)?;
+    if locations.len() < ranges.len() {
+        return Err(String::from("SymCache has fewer source locations than ranges"));
+    }
+
+    let string = |index: u32| -> Option<String> {
+        let [offset, len] = *string_entries.get(index as usize)?;
+        let bytes = bytes.get(offset as usize..(offset as usize).checked_add(len as usize)?)?;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    };
+    let mut builder = SymbolFileBuilder::new();
+    let arch = ARCHS.iter().find(|(_, id)| *id == arch).map_or("unknown", |(name, _)| name);
+    builder.set_module("unknown", arch, debug_id, name);
+    for (id, [comp_dir, directory, file_name]) in files.iter().enumerate() {
+        let mut path = String::new();
+        for part in [string(*comp_dir), string(*directory), string(*file_name)].iter().flatten() {
+            // Absolute parts replace what comes before them.
+            if part.starts_with('/') || part.get(1..3) == Some(":\\") {
+                path.clear();
+            } else if !path.is_empty() && !path.ends_with(['/', '\\']) {
+                path.push('/');
+            }
+            path.push_str(part);
+        }
+        builder.add_file(id as i64, path);
+    }
+
+    // Consecutive ranges of one outermost function make up its FUNC record,
+    // each range with a file one of its LINE records.
+    let outermost = |mut location: [u32; 4]| {
+        for _ in 0..locations.len() {
+            match locations.get(location[3] as usize) {
+                Some(parent) if location[3] != NONE => location = *parent,
+                _ => break,
+            }
+        }
+        location[2]
+    };
+    let mut current: Option<(u32, u64)> = None;
+    for (i, [start]) in ranges.iter().enumerate() {
+        let start = u64::from(*start);
+        let location = locations[i];
+        let function = outermost(location);
+        if let Some((previous, function_start)) = current {
+            if previous != function {
+                add_function(&mut builder, function_start, start, functions.get(previous as usize).and_then(|function| string(function[0])));
+                current = None;
+            }
+        }
+        if function == NONE || function as usize >= functions.len() {
+            continue;
+        }
+        if current.is_none() {
+            current = Some((function, start));
+        }
+        let end = ranges.get(i + 1).map_or(start + 1, |[end]| u64::from(*end));
+        if location[0] != NONE {
+            builder.add_line(start, end.saturating_sub(start), i64::from(location[1]), i64::from(location[0]));
+        }
+    }
+    // Nothing ends the last function, as with PUBLIC records.
+    if let Some((function, function_start)) = current {
+        let name = string(functions[function as usize][0]).unwrap_or_else(|| String::from("<unknown>"));
+        builder.add_public(function_start, 0, name);
+    }
+    Ok(builder.build())
+}
+
+fn add_function(builder: &mut SymbolFileBuilder, start: u64, end: u64, name: Option<String>) {
+    builder.add_function(start, end.saturating_sub(start), 0, name.unwrap_or_else(|| String::from("<unknown>")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup_address;
+
+    #[test]
+    fn test_symcache_round_trip() {
+        let mut builder = SymbolFileBuilder::new();
+        builder
+            .set_module("Linux", "arm64", "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0", "libil2cpp.so")
+            .add_file(3, "/build/il2cpp/os/Thread.cpp")
+            .add_file(7, "main.cpp")
+            .add_function(0x100, 0x40, 0, "il2cpp::os::Thread::RunWrapper(void*)")
+            .add_line(0x100, 0x10, 88, 3)
+            .add_line(0x120, 0x20, 90, 3)
+            .add_function(0x140, 0x10, 0, "main")
+            .add_line(0x140, 0x10, 5, 7)
+            .add_public(0x200, 0, "start");
+        let mut data = vec![];
+        builder.build().write_symcache(&mut data).unwrap();
+        assert!(is_symcache(&data));
+        assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), SYMCACHE_VERSION);
+
+        let symcache = parse_symcache(&data, "libil2cpp.so").unwrap();
+        let module = symcache.module().unwrap();
+        assert_eq!((module.arch.as_str(), module.debug_id.as_str()), ("arm64", "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0"));
+        let symbol = lookup_address(&symcache, 0x124).unwrap();
+        assert_eq!(symbol.to_string(), "il2cpp::os::Thread::RunWrapper(void*) /build/il2cpp/os/Thread.cpp:90");
+        assert_eq!((symbol.function_address, symbol.function_size), (Some(0x100), Some(0x40)));
+        // The gap between the LINE records still belongs to the FUNC.
+        assert_eq!(lookup_address(&symcache, 0x114).unwrap().to_string(), "il2cpp::os::Thread::RunWrapper(void*) ??:?");
+        assert_eq!(lookup_address(&symcache, 0x148).unwrap().to_string(), "main main.cpp:5");
+        assert_eq!(lookup_address(&symcache, 0x208).unwrap().function_name, "start");
+        assert!(lookup_address(&symcache, 0x160).is_none());
+
+        assert_eq!(parse_symcache(b"MODULE Linux", "a").unwrap_err(), "not a SymCache");
+        assert_eq!(parse_symcache(&data[..100], "a").unwrap_err(), "truncated SymCache");
+    }
+}
@@ -0,0 +1,133 @@
+use std::io;
+
+use serde_json::{json, Value};
+use crate::logging::debug;
+
+use crate::http::{get_json, post_json, put};
+
+/// What [`upload_symbols`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadStatus {
+    Uploaded,
+    /// The collector already had symbols for the module, nothing was sent.
+    AlreadyPresent,
+    /// Sent, but the collector had identical data.
+    Duplicate,
+}
+
+// Module names become path segments of the API.
+fn escape(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn field<'a>(response: &'a Value, key: &str, url: &str) -> io::Result<&'a str> {
+    response
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{}: no {} in response", url, key)))
+}
+
+/// Publishes the breakpad symbols of `debug_file` (the MODULE name) with
+/// `debug_id` to a symbol collector speaking the sym-upload-v2 protocol, as
+/// `sym_upload -p sym-upload-v2` does: unless `force`, ask whether the
+/// collector has them, then get an upload URL, PUT `data` there and complete
+/// the upload. `url` is the collector's base URL, `api_key` its key.
+pub fn upload_symbols(url: &str, api_key: &str, debug_file: &str, debug_id: &str, data: &[u8], force: bool) -> io::Result<UploadStatus> {
+    let url = url.trim_end_matches('/');
+    let key = escape(api_key);
+    if !force {
+        let status_url = format!("{}/v1/symbols/{}/{}:checkStatus?key={}", url, escape(debug_file), escape(debug_id), key);
+        let status = get_json(&status_url, &[])?;
+        if status.get("status").and_then(Value::as_str) == Some("FOUND") {
+            return Ok(UploadStatus::AlreadyPresent);
+        }
+    }
+
+    let create_url = format!("{}/v1/uploads:create?key={}", url, key);
+    let created = post_json(&create_url, &[], &json!({}))?;
+    let upload_url = field(&created, "uploadUrl", &create_url)?;
+    let upload_key = field(&created, "uploadKey", &create_url)?;
+    debug!("uploading {}/{} as {}", debug_file, debug_id, upload_key);
+    // A signed storage URL, the key stays with the collector.
+    put(upload_url, &[], data)?;
+
+    let complete_url = format!("{}/v1/uploads/{}:complete?key={}", url, escape(upload_key), key);
+    let body = json!({
+        "symbol_id": {"debug_file": debug_file, "debug_id": debug_id},
+        "symbol_upload_type": "BREAKPAD",
+    });
+    let completed = post_json(&complete_url, &[], &body)?;
+    match field(&completed, "result", &complete_url)? {
+        "OK" => Ok(UploadStatus::Uploaded),
+        "DUPLICATE_DATA" => Ok(UploadStatus::Duplicate),
+        result => Err(io::Error::other(format!("upload of {}/{} failed: {}", debug_file, debug_id, result))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    // A collector answering each connection with the next of `responses`
+    // (`{base}` is replaced with its own URL) and recording the requests.
+    fn collector(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (recorded, url) = (requests.clone(), base.clone());
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let (mut request, mut line, mut length) = (String::new(), String::new(), 0);
+                reader.read_line(&mut request).unwrap();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                recorded.lock().unwrap().push(format!("{} {}", request.trim_end(), String::from_utf8_lossy(&body)));
+                let body = response.replace("{base}", &url);
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        (base, requests)
+    }
+
+    #[test]
+    fn test_upload_symbols() {
+        let (url, requests) = collector(vec![
+            r#"{"status": "MISSING"}"#,
+            r#"{"uploadUrl": "{base}/storage/abc", "uploadKey": "abc"}"#,
+            "",
+            r#"{"result": "OK"}"#,
+        ]);
+        let status = upload_symbols(&url, "k3y", "libunity.so", "4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0", b"MODULE", false).unwrap();
+        assert_eq!(status, UploadStatus::Uploaded);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0], "GET /v1/symbols/libunity.so/4FBDB2B4ACB3432BF6AD8E4A87BA9D4B0:checkStatus?key=k3y HTTP/1.1 ");
+        assert_eq!(requests[1], "POST /v1/uploads:create?key=k3y HTTP/1.1 {}");
+        assert_eq!(requests[2], "PUT /storage/abc HTTP/1.1 MODULE");
+        assert!(requests[3].starts_with("POST /v1/uploads/abc:complete?key=k3y HTTP/1.1 "));
+        let body: Value = serde_json::from_str(requests[3].split_once(" HTTP/1.1 ").unwrap().1).unwrap();
+        assert_eq!(body["symbol_id"]["debug_file"], "libunity.so");
+        assert_eq!(body["symbol_upload_type"], "BREAKPAD");
+
+        let (url, _) = collector(vec![r#"{"status": "FOUND"}"#]);
+        assert_eq!(upload_symbols(&url, "k3y", "libunity.so", "0102", b"MODULE", false).unwrap(), UploadStatus::AlreadyPresent);
+        assert_eq!(escape("My App.pdb"), "My%20App.pdb");
+    }
+}
@@ -0,0 +1,55 @@
+extern crate clap;
+use clap::{App, AppSettings, Arg, Shell, SubCommand};
+use std::io;
+
+use addr2line_breakpad::cli::{global_args, init_logging, lookup_args, run_lookup, run_symbol_subcommand, symbol_subcommands};
+use ips_breakpad::cli::{aggregate_subcommand, report_args, run_aggregate, run_report, run_scan, scan_subcommand};
+
+// Both tools behind one binary, with the symbol loading options and config
+// file of `addr2line_breakpad` and `ips_breakpad`:
+//
+//   breakpad-tools lookup UnityFramework.sym 0xb11a94
+//   breakpad-tools ips UnityFramework.sym jxpocket.ips
+//   breakpad-tools tombstone libunity.sym tombstone_00
+//   breakpad-tools scan libil2cpp.sym game.log
+fn app() -> App<'static, 'static> {
+    App::new("Breakpad symbol file tools")
+        .version("1.0")
+        .author("yhnu <buutuud@gmail.com>")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .args(&global_args())
+        .subcommand(lookup_args(SubCommand::with_name("lookup").about("resolve addresses of a module")))
+        .subcommand(report_args(SubCommand::with_name("ips").about("symbolicate an iOS .ips or macOS .crash report")))
+        .subcommand(report_args(SubCommand::with_name("tombstone").about("symbolicate an Android tombstone, logcat backtrace or microdump")))
+        .subcommand(aggregate_subcommand())
+        .subcommand(scan_subcommand())
+        .subcommands(symbol_subcommands())
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("print a completion script for the shell, e.g. `breakpad-tools completions bash > /etc/bash_completion.d/breakpad-tools`")
+                .arg(Arg::with_name("shell").possible_values(&Shell::variants()).required(true)),
+        )
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Some(matches) = matches.subcommand_matches("completions") {
+        let shell = matches.value_of("shell").unwrap().parse().unwrap();
+        app().gen_completions_to("breakpad-tools", shell, &mut io::stdout());
+        return;
+    }
+    // Global flags can also follow the subcommand, and only reach its matches.
+    init_logging(matches.subcommand().1.unwrap_or(&matches));
+
+    if run_symbol_subcommand(&matches) {
+        return;
+    }
+    match matches.subcommand() {
+        ("lookup", Some(matches)) => run_lookup(matches),
+        ("ips", Some(matches)) | ("tombstone", Some(matches)) => run_report(matches),
+        ("aggregate", Some(matches)) => run_aggregate(matches),
+        ("scan", Some(matches)) => run_scan(matches),
+        _ => unreachable!(),
+    }
+}
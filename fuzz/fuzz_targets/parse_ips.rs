@@ -0,0 +1,18 @@
+#![no_main]
+
+use ips_breakpad::{parse_binary_images, parse_frame_line};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    for image in parse_binary_images(&input) {
+        image.size();
+        image.debug_id();
+    }
+    for line in input.lines() {
+        if let Some(frame) = parse_frame_line(line) {
+            // The offset is spliced back into the line when symbolicating.
+            let _ = (&line[..frame.offset_span.start], &line[frame.offset_span.end..]);
+        }
+    }
+});
@@ -0,0 +1,30 @@
+#![no_main]
+
+use addr2line_breakpad::{all_symbols, lookup_address, lookup_addresses, lookup_range, ParserOptions, SymbolFile};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let options = ParserOptions {
+        keep_unparsed_records: true,
+        ..ParserOptions::default()
+    };
+    let symbol_file = match SymbolFile::from_reader_with_options(data, &options) {
+        Ok(symbol_file) => symbol_file,
+        Err(_) => return,
+    };
+    // Probe around every record the parser accepted.
+    let mut addresses: Vec<u64> = vec![0, u64::MAX];
+    for record in all_symbols(&symbol_file) {
+        addresses.push(record.address);
+        addresses.push(record.address.wrapping_add(record.size));
+        addresses.push(record.address.wrapping_sub(1));
+    }
+    for &address in &addresses {
+        lookup_address(&symbol_file, address);
+    }
+    lookup_addresses(&symbol_file, &addresses);
+    addresses.sort_unstable();
+    lookup_addresses(&symbol_file, &addresses);
+    lookup_range(&symbol_file, 0, u64::MAX);
+    symbol_file.write_breakpad(&mut std::io::sink()).unwrap();
+});
@@ -0,0 +1,1161 @@
+// Argument parsing and commands of `ips_breakpad`, shared with the combined
+// `breakpad-tools`.
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use addr2line_breakpad::cli::{load_config, low_memory_arg, parse_context_lines, parse_source_bundle, parse_source_map, parse_symbol_format, parse_with_progress, print_stats, source_context, stats_arg, ParseSettings};
+use addr2line_breakpad::{caller_address, canonical_frame, find_symbol_file, find_symbol_file_for_arch, parse_microdump, symbol_file_exists, Config, FrameTrust, Microdump, Module, NameSimplification, SourceBundle, SourceMap, Symbol, SymbolFormat, SymbolKind, ModuleSet, MICRODUMP_END};
+use crate::{find_image_by_address, for_each_ordered, format_frame_line, parse_address_list, parse_binary_images, parse_exception_summary, parse_frame_line, parse_registers, parse_report_arch, parse_thread_state_header, parse_tombstone_frame_line, set_frame_regex, BinaryImage, IpsReport, ExceptionSummary, Frame, TombstoneFrame};
+#[cfg(feature = "remote")]
+use addr2line_breakpad::{default_cache_dir, fetch_symbol_file, is_remote_source};
+#[cfg(feature = "debuginfod")]
+use addr2line_breakpad::{debuginfod_urls, is_elf_file, parse_debuginfod_source};
+#[cfg(feature = "debuginfod")]
+use std::collections::HashSet;
+
+mod aggregate;
+mod html;
+mod markdown;
+#[cfg(feature = "proto")]
+mod proto;
+mod scan;
+
+struct Options {
+    basenames: bool,
+    simplify_names: NameSimplification,
+    json: bool,
+    // `#00 module!function+0x1c4 (file.cpp:88)` frames, see --canonical-frames.
+    canonical_frames: bool,
+    strict: bool,
+    resymbolicate: bool,
+    // Look frames above the first up at their call, see --no-pc-adjustment.
+    adjust_return_addresses: bool,
+    source_map: SourceMap,
+    source_root: Option<PathBuf>,
+    source_bundle: Option<SourceBundle>,
+    context_lines: usize,
+    source_url: Option<String>,
+    // How resolved frames are printed, see --format.
+    format: SymbolFormat,
+}
+
+// `symbol`, looked up at the module relative `address`, as --format says.
+fn get_symed_line(symbol: &Symbol, address: u64, options: &Options) -> String {
+    let source_file_name = if options.basenames {
+        Cow::Borrowed(symbol.source_basename())
+    } else {
+        options.source_map.remap(&symbol.source_file_name)
+    };
+    let symbol = Symbol {
+        function_name: options.simplify_names.simplify(&symbol.function_name),
+        source_file_name,
+        ..symbol.clone()
+    };
+    options.format.format(address, &symbol)
+}
+
+// With --source-root or --source-bundle, the lines around the resolved one.
+fn print_source_context(out: &mut impl Write, symbol: &Symbol, options: &Options) {
+    let (root, bundle) = (options.source_root.as_deref(), options.source_bundle.as_ref());
+    if let Some(snippet) = source_context(&symbol.source_file_name, symbol.source_file_number, root, bundle, options.context_lines, &options.source_map) {
+        let _ = write!(out, "{}", snippet);
+    }
+}
+
+fn print_exception_summary(out: &mut impl Write, summary: &ExceptionSummary) {
+    if summary.is_empty() {
+        return;
+    }
+    let unknown = String::from("?");
+    let _ = writeln!(out, "==================== Crash Summary ====================");
+    let _ = writeln!(out, "Exception Type:     {}", summary.exception_type.as_ref().unwrap_or(&unknown));
+    let _ = writeln!(out, "Exception Codes:    {}", summary.exception_codes.as_ref().unwrap_or(&unknown));
+    if let Some(reason) = &summary.termination_reason {
+        let _ = writeln!(out, "Termination Reason: {}", reason);
+    }
+    if let Some(address) = &summary.fault_address {
+        let _ = writeln!(out, "Fault Address:      {}", address);
+    }
+    let _ = writeln!(out, "Crashed Thread:     {}", summary.crashed_thread.as_ref().unwrap_or(&unknown));
+    let _ = writeln!(out, "=======================================================");
+    let _ = writeln!(out);
+}
+
+// Breakpad debug id of the image named `module`, or one of its aliases.
+fn find_image_debug_id(input: &str, module: &str, config: &Config) -> Option<String> {
+    let images = parse_binary_images(input);
+    let image = images.iter().find(|image| image.matches(module));
+    image
+        .or_else(|| config.module_aliases_of(module).into_iter().find_map(|alias| images.iter().find(|image| image.matches(alias))))
+        .map(|image| image.debug_id())
+}
+
+// `-` reads the report from stdin.
+fn read_ips(ips: &Path) -> String {
+    let mut input = String::new();
+    if ips == Path::new("-") {
+        io::stdin().read_to_string(&mut input).unwrap();
+        return input;
+    }
+    let file = File::open(ips).unwrap();
+    let mut reader = BufReader::new(file);
+    reader.read_to_string(&mut input).unwrap();
+    input
+}
+
+// Local path of the symbol file. s3://, gs:// and http(s):// stores are resolved by the
+// module's debug id from the report's Binary Images. An input that isn't a
+// file is taken as a module name and searched for in the configured symbol
+// directories, then symbol servers.
+fn resolve_input(input: &str, module: Option<&str>, report: &str, config: &Config) -> PathBuf {
+    let debug_id = find_image_debug_id(report, module.unwrap_or(input), config);
+    #[cfg(feature = "remote")]
+    {
+        if is_remote_source(input) {
+            let module = module.zip(debug_id.as_deref());
+            return match fetch_symbol_file(input, module, &default_cache_dir()) {
+                Ok(path) => path,
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    let path = PathBuf::from(input);
+    if symbol_file_exists(&path) {
+        return path;
+    }
+    let name = module.unwrap_or(input);
+    // Directories may hold the library for several ABIs, the report's wins.
+    let found = match parse_report_arch(report) {
+        Some(arch) => find_symbol_file_for_arch(&config.symbol_search_path(), name, debug_id.as_deref(), arch).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(-1);
+        }),
+        None => find_symbol_file(&config.symbol_search_path(), name, debug_id.as_deref()),
+    };
+    if let Some(path) = found {
+        return path;
+    }
+    #[cfg(feature = "remote")]
+    {
+        if let Some(debug_id) = &debug_id {
+            for server in &config.symbol_servers {
+                if let Ok(path) = fetch_symbol_file(server, Some((name, debug_id)), &default_cache_dir()) {
+                    return path;
+                }
+            }
+        }
+    }
+    println!("input file({}) is not exists", path.display());
+    process::exit(-1);
+}
+
+fn load_module(input: &str, module_name: Option<&str>, report: &str, config: &Config, settings: &ParseSettings) -> Module {
+    #[cfg(feature = "debuginfod")]
+    {
+        if let Some(build_id) = parse_debuginfod_source(input) {
+            return match Module::from_debuginfod(module_name.unwrap_or(""), build_id, &default_cache_dir()) {
+                Ok(mut module) => {
+                    if let Some(dwarf) = module.dwarf.as_mut() {
+                        dwarf.set_demangle(config.demangle.unwrap_or(true));
+                    }
+                    module
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    let input = resolve_input(input, module_name, report, config);
+    // Android native debug symbols, as uploaded to Google Play, are ELF files.
+    #[cfg(feature = "debuginfod")]
+    {
+        if is_elf_file(&input, module_name) {
+            return match Module::from_elf(module_name.unwrap_or(""), &input, module_name, &default_cache_dir()) {
+                Ok(mut module) => {
+                    if let Some(dwarf) = module.dwarf.as_mut() {
+                        dwarf.set_demangle(config.demangle.unwrap_or(true));
+                    }
+                    module
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(-1);
+                }
+            };
+        }
+    }
+    #[allow(unused_mut)]
+    let mut module = Module::new(parse_with_progress(&input, module_name, settings));
+    // Addresses the symbols miss are looked up by the build id in INFO CODE_ID.
+    #[cfg(feature = "debuginfod")]
+    module.enable_debuginfod(&default_cache_dir(), config.demangle.unwrap_or(true));
+    module
+}
+
+// Adds a module backed by debuginfod for each BuildId of the report's
+// tombstone frames that no loaded symbols have, so system libraries without
+// breakpad symbols resolve too. Needs DEBUGINFOD_URLS.
+#[cfg(feature = "debuginfod")]
+fn add_debuginfod_modules(symbolicator: &mut ModuleSet, report: &str, config: &Config) {
+    if debuginfod_urls().is_empty() {
+        return;
+    }
+    let mut tried = HashSet::new();
+    for frame in report.lines().filter_map(parse_tombstone_frame_line) {
+        let build_id = match frame.build_id {
+            Some(build_id) if tried.insert(build_id.to_ascii_lowercase()) => build_id,
+            _ => continue,
+        };
+        if symbolicator.module_with_build_id(frame.image, Some(build_id)).is_some() {
+            continue;
+        }
+        match Module::from_debuginfod(frame.image, build_id, &default_cache_dir()) {
+            Ok(mut module) => {
+                info!("{}: debug info for BuildId {} from debuginfod", frame.image, build_id);
+                if let Some(dwarf) = module.dwarf.as_mut() {
+                    dwarf.set_demangle(config.demangle.unwrap_or(true));
+                }
+                symbolicator.add_module(module);
+            }
+            Err(err) => warn!("{}: {}", frame.image, err),
+        }
+    }
+}
+
+enum Resolution<'a> {
+    // Not a frame of a module we have symbols for.
+    Skipped,
+    Symbol(Symbol<'a>),
+    // The report already symbolicated the frame and we kept its symbol.
+    Report,
+    NotFound,
+    OutOfRange,
+}
+
+impl Resolution<'_> {
+    fn is_failure(&self) -> bool {
+        matches!(self, Resolution::NotFound | Resolution::OutOfRange)
+    }
+}
+
+fn resolve_frame<'a>(frame: &Frame, images: &[BinaryImage], symbolicator: &'a ModuleSet, options: &Options) -> Resolution<'a> {
+    let module = match symbolicator.module(&frame.image) {
+        Some(module) => module,
+        None => return Resolution::Skipped,
+    };
+    let image = images.iter().find(|image| image.matches(&frame.image));
+    if frame.symbol.is_some() {
+        // Keep the report's symbol unless ours resolves the frame.
+        return image
+            .filter(|image| options.resymbolicate && frame.address >= image.start)
+            .and_then(|image| module.lookup(lookup_address(module, frame.index, frame.address - image.start, options)))
+            .map_or(Resolution::Report, Resolution::Symbol);
+    }
+    let e = frame.offset;
+    if let Some(size) = image.map(|image| image.size()).filter(|size| e >= *size) {
+        warn!(
+            "frame {}: offset {:#x} is beyond the end of image {} ({:#x} bytes), it probably belongs to another module or needs rebasing",
+            frame.index, e, frame.image, size
+        );
+        return Resolution::OutOfRange;
+    }
+    module.lookup(lookup_address(module, frame.index, e, options)).map_or(Resolution::NotFound, Resolution::Symbol)
+}
+
+// Frames other than the first hold return addresses, which point past their
+// call and may already be in the next function or line.
+fn lookup_address(module: &Module, frame_index: usize, rel_addr: u64, options: &Options) -> u64 {
+    if !options.adjust_return_addresses || frame_index == 0 {
+        return rel_addr;
+    }
+    caller_address(module.symbols.module().map_or("", |record| record.arch.as_str()), rel_addr)
+}
+
+// Frames of modules we have symbols for that could not be resolved, listed
+// at the end of a report so they aren't lost in it.
+#[derive(Default)]
+struct Unresolved {
+    offsets: BTreeMap<String, Vec<u64>>,
+}
+
+#[derive(Serialize)]
+struct JsonUnresolvedImage {
+    image: String,
+    count: usize,
+    offsets: Vec<String>,
+}
+
+impl Unresolved {
+    fn add(&mut self, image: &str, offset: u64) {
+        self.offsets.entry(image.to_string()).or_default().push(offset);
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.values().map(Vec::len).sum()
+    }
+
+    // Images with the most unresolved frames first.
+    fn to_json(&self) -> Vec<JsonUnresolvedImage> {
+        let mut images: Vec<JsonUnresolvedImage> = self
+            .offsets
+            .iter()
+            .map(|(image, offsets)| JsonUnresolvedImage {
+                image: image.clone(),
+                count: offsets.len(),
+                offsets: offsets.iter().map(|offset| format!("{:#x}", offset)).collect(),
+            })
+            .collect();
+        images.sort_by_key(|image| Reverse(image.count));
+        images
+    }
+}
+
+fn print_unresolved(out: &mut impl Write, unresolved: &[JsonUnresolvedImage]) {
+    if unresolved.is_empty() {
+        return;
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Unresolved frames:");
+    for image in unresolved {
+        let _ = writeln!(out, "  {}: {} ({})", image.image, image.count, image.offsets.join(", "));
+    }
+}
+
+// Prints `line`, symbolicated if it is a frame of a module we have symbols
+// for, and notes such a frame in `unresolved` if it could not be resolved.
+fn symbolicate_line(out: &mut impl Write, line: &str, images: &[BinaryImage], symbolicator: &ModuleSet, options: &Options, unresolved: &mut Unresolved) {
+    let frame = match parse_frame_line(line) {
+        Some(frame) => frame,
+        None => {
+            if let Some(frame) = parse_tombstone_frame_line(line) {
+                return symbolicate_tombstone_line(out, line, &frame, symbolicator, options, unresolved);
+            }
+            let _ = writeln!(out, "{}", line);
+            return;
+        }
+    };
+    let resolution = resolve_frame(&Frame::from(&frame), images, symbolicator, options);
+    if resolution.is_failure() {
+        unresolved.add(frame.image, frame.offset);
+    }
+    // Our symbol's offset is from the image, the report's from its symbol.
+    let rel_addr = match frame.symbol {
+        Some(_) => images
+            .iter()
+            .find(|image| image.matches(frame.image))
+            .map_or(frame.offset, |image| frame.address.wrapping_sub(image.start)),
+        None => frame.offset,
+    };
+    if options.canonical_frames {
+        let index = frame.index.parse().unwrap_or(0);
+        let canonical = match (&resolution, frame.symbol) {
+            (Resolution::Symbol(symbol), _) => canonical_frame(frame.image, rel_addr, Some(symbol), options.simplify_names),
+            (_, Some(name)) => format!("{}!{}+{:#x}", frame.image, options.simplify_names.simplify(name), frame.offset),
+            (_, None) => canonical_frame(frame.image, frame.offset, None, options.simplify_names),
+        };
+        let _ = writeln!(out, "#{:02} {}", index, canonical);
+        if let Resolution::Symbol(symbol) = &resolution {
+            print_source_context(out, symbol, options);
+        }
+        return;
+    }
+    let symed_offset = match &resolution {
+        Resolution::Symbol(symbol) => get_symed_line(symbol, rel_addr, options),
+        Resolution::NotFound => format!("Not found symbol for address({:#x}", frame.offset),
+        _ => {
+            let _ = writeln!(out, "{}", line);
+            return;
+        }
+    };
+    let _ = writeln!(out, "{}{}{}", &line[..frame.offset_span.start], symed_offset, &line[frame.offset_span.end..]);
+    if let Resolution::Symbol(symbol) = &resolution {
+        print_source_context(out, symbol, options);
+    }
+}
+
+// Tombstone frames are matched to symbols by BuildId where they carry one.
+fn symbolicate_tombstone_line(out: &mut impl Write, line: &str, frame: &TombstoneFrame, symbolicator: &ModuleSet, options: &Options, unresolved: &mut Unresolved) {
+    let module = symbolicator.module_with_build_id(frame.image, frame.build_id);
+    let symbol = module.and_then(|module| module.lookup(frame.pc));
+    if module.is_some() && symbol.is_none() {
+        unresolved.add(frame.image, frame.pc);
+    }
+    if options.canonical_frames {
+        let index: usize = frame.index.parse().unwrap_or(0);
+        // debuggerd's own `function+decimal offset` for modules without symbols.
+        let tombstone_function = frame.function_span.clone().and_then(|span| {
+            let (name, offset) = line[span].rsplit_once('+')?;
+            Some((name, offset.parse::<u64>().ok()?))
+        });
+        let canonical = match (&symbol, tombstone_function) {
+            (None, Some((name, offset))) => format!("{}!{}+{:#x}", frame.image, options.simplify_names.simplify(name), offset),
+            _ => canonical_frame(frame.image, frame.pc, symbol.as_ref(), options.simplify_names),
+        };
+        let _ = writeln!(out, "#{:02} {}", index, canonical);
+        if let Some(symbol) = &symbol {
+            print_source_context(out, symbol, options);
+        }
+        return;
+    }
+    let symbol = match symbol {
+        Some(symbol) => symbol,
+        None => {
+            let _ = writeln!(out, "{}", line);
+            return;
+        }
+    };
+    let symed_line = get_symed_line(&symbol, frame.pc, options);
+    match &frame.function_span {
+        Some(span) => {
+            let _ = writeln!(out, "{}{}{}", &line[..span.start], symed_line, &line[span.end..]);
+        }
+        None => {
+            let _ = writeln!(out, "{} ({}){}", &line[..frame.path_end], symed_line, &line[frame.path_end..]);
+        }
+    }
+    print_source_context(out, &symbol, options);
+}
+
+// Prints the stack recovered from a microdump, noting the crashing frame in
+// `unresolved` if it could not be resolved.
+fn print_microdump(out: &mut impl Write, dump: &Microdump, symbolicator: &ModuleSet, options: &Options, unresolved: &mut Unresolved) {
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "Microdump backtrace ({}, {}):",
+        dump.product.as_deref().unwrap_or("?"),
+        dump.arch.as_deref().unwrap_or("?")
+    );
+    for (i, frame) in dump.symbolicate(symbolicator).iter().enumerate() {
+        let (module, rel_addr) = match &frame.module {
+            Some((name, rel_addr)) => (name.as_str(), *rel_addr),
+            None => ("???", frame.address),
+        };
+        if frame.symbol.is_none() && frame.trust == FrameTrust::Context {
+            unresolved.add(module, rel_addr);
+        }
+        if options.canonical_frames {
+            let _ = writeln!(out, "#{:02} {}", i, canonical_frame(module, rel_addr, frame.symbol.as_ref(), options.simplify_names));
+        } else {
+            let symbol = frame.symbol.as_ref().map(|symbol| format!(" ({})", get_symed_line(symbol, rel_addr, options))).unwrap_or_default();
+            let _ = writeln!(out, "#{:02} pc {:016x}  {}{} [{}]", i, rel_addr, module, symbol, frame.trust);
+        }
+        if let Some(symbol) = &frame.symbol {
+            print_source_context(out, symbol, options);
+        }
+    }
+}
+
+// The crashed thread's registers pointing into an image, and pc and lr in
+// any case: lr often still names the caller when the stack is corrupt.
+fn print_registers(out: &mut impl Write, registers: &[(&str, u64)], images: &[BinaryImage], symbolicator: &ModuleSet, options: &Options) {
+    let mut lines = vec![];
+    for &(name, value) in registers {
+        let line = match find_image_by_address(images, value) {
+            Some(image) => {
+                // lr holds a return address, looked up at its call like the
+                // frames below the first.
+                let frame = Frame {
+                    index: usize::from(name == "lr"),
+                    image: image.name.clone(),
+                    address: value,
+                    offset: value - image.start,
+                    symbol: None,
+                };
+                let symbol = match resolve_frame(&frame, images, symbolicator, options) {
+                    Resolution::Symbol(symbol) => format!(" ({})", get_symed_line(&symbol, frame.offset, options)),
+                    _ => String::new(),
+                };
+                format!("{:>6}: 0x{:016x} {} + {}{}", name, value, image.name, frame.offset, symbol)
+            }
+            None if name == "pc" || name == "lr" => format!("{:>6}: 0x{:016x} ???", name, value),
+            None => continue,
+        };
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        return;
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Symbolicated registers:");
+    for line in lines {
+        let _ = writeln!(out, "{}", line);
+    }
+}
+
+// Returns the number of targeted frames that could not be resolved.
+fn parser_ips(out: &mut impl Write, input: &str, symbolicator: &ModuleSet, options: &Options) -> usize {
+    let mut unresolved = Unresolved::default();
+
+    print_exception_summary(out, &parse_exception_summary(input));
+
+    let images = parse_binary_images(input);
+    let mut in_last_exception = false;
+    let mut registers: Option<Vec<(&str, u64)>> = None;
+    for line in input.lines() {
+        if let Some(thread_registers) = registers.as_mut() {
+            if !line.trim().is_empty() {
+                thread_registers.extend(parse_registers(line));
+                let _ = writeln!(out, "{}", line);
+                continue;
+            }
+            print_registers(out, thread_registers, &images, symbolicator, options);
+            registers = None;
+        } else if parse_thread_state_header(line).is_some() {
+            registers = Some(vec![]);
+            let _ = writeln!(out, "{}", line);
+            continue;
+        }
+        if line.contains(MICRODUMP_END) {
+            let _ = writeln!(out, "{}", line);
+            if let Some(dump) = parse_microdump(input) {
+                print_microdump(out, &dump, symbolicator, options, &mut unresolved);
+            }
+            continue;
+        }
+        if line.starts_with("Last Exception Backtrace:") {
+            in_last_exception = true;
+        } else if in_last_exception {
+            // The flat address list is expanded into one frame per address.
+            if let Some(addresses) = parse_address_list(line) {
+                for (i, address) in addresses.into_iter().enumerate() {
+                    symbolicate_line(out, &format_frame_line(i, &images, address), &images, symbolicator, options, &mut unresolved);
+                }
+                continue;
+            }
+            in_last_exception = line.trim().is_empty();
+        }
+        symbolicate_line(out, line, &images, symbolicator, options, &mut unresolved);
+    }
+    if let Some(thread_registers) = registers {
+        print_registers(out, &thread_registers, &images, symbolicator, options);
+    }
+    print_unresolved(out, &unresolved.to_json());
+    unresolved.len()
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    // Set when several reports are symbolicated at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    // The JSON line newer ips files start with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ips_header: Option<serde_json::Value>,
+    metadata: BTreeMap<String, String>,
+    exception: ExceptionSummary,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    last_exception_backtrace: Vec<JsonFrame>,
+    threads: Vec<JsonThread>,
+    // Frames of our modules that could not be resolved, by image.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unresolved: Vec<JsonUnresolvedImage>,
+}
+
+#[derive(Serialize)]
+struct JsonThread {
+    index: usize,
+    name: Option<String>,
+    crashed: bool,
+    frames: Vec<JsonFrame>,
+}
+
+#[derive(Serialize)]
+struct JsonFrame {
+    index: usize,
+    image: String,
+    address: String,
+    // From the image's load address, if known.
+    offset: Option<u64>,
+    function: Option<String>,
+    // `function` shortened by --simplify-names, if that changed it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_function: Option<String>,
+    file: Option<String>,
+    line: Option<i64>,
+    // line, func or public for our symbols, report for symbols kept from the
+    // report, not_found, out_of_range, or none for other modules.
+    #[serde(rename = "match")]
+    match_kind: &'static str,
+}
+
+impl JsonFrame {
+    // The name to show outside of JSON output.
+    fn display_function(&self) -> Option<&str> {
+        self.short_function.as_deref().or(self.function.as_deref())
+    }
+}
+
+fn json_frame(frame: &Frame, images: &[BinaryImage], symbolicator: &ModuleSet, options: &Options) -> (JsonFrame, bool) {
+    let offset = match frame.symbol {
+        Some(_) => images
+            .iter()
+            .find(|image| image.matches(&frame.image))
+            .and_then(|image| frame.address.checked_sub(image.start)),
+        None => Some(frame.offset),
+    };
+    let mut json = JsonFrame {
+        index: frame.index,
+        image: frame.image.clone(),
+        address: format!("{:#x}", frame.address),
+        offset,
+        function: frame.symbol.clone(),
+        short_function: None,
+        file: None,
+        line: None,
+        match_kind: if frame.symbol.is_some() { "report" } else { "none" },
+    };
+    let resolution = resolve_frame(frame, images, symbolicator, options);
+    match &resolution {
+        Resolution::Symbol(symbol) => {
+            json.function = Some(symbol.function_name.to_string());
+            if !symbol.source_file_name.is_empty() {
+                json.file = Some(if options.basenames { symbol.source_basename().into() } else { options.source_map.remap(&symbol.source_file_name) }.into_owned());
+            }
+            if symbol.source_file_number != -1 {
+                json.line = Some(symbol.source_file_number);
+            }
+            json.match_kind = match symbol.kind {
+                _ if json.line.is_some() => "line",
+                SymbolKind::Func => "func",
+                SymbolKind::Public => "public",
+            };
+        }
+        Resolution::NotFound => json.match_kind = "not_found",
+        Resolution::OutOfRange => json.match_kind = "out_of_range",
+        Resolution::Skipped | Resolution::Report => {}
+    }
+    if let Some(function) = &json.function {
+        if let Cow::Owned(short_function) = options.simplify_names.simplify(function) {
+            json.short_function = Some(short_function).filter(|short_function| short_function != function);
+        }
+    }
+    (json, !resolution.is_failure())
+}
+
+// Symbolicates the frame, noting it in `unresolved` if it could not be.
+fn symbolicate_frame(frame: &Frame, images: &[BinaryImage], symbolicator: &ModuleSet, options: &Options, unresolved: &mut Unresolved) -> JsonFrame {
+    let (json, resolved) = json_frame(frame, images, symbolicator, options);
+    if !resolved {
+        unresolved.add(&frame.image, frame.offset);
+    }
+    json
+}
+
+// The --json counterpart of `parser_ips`.
+fn ips_to_json(input: &str, symbolicator: &ModuleSet, options: &Options) -> (JsonReport, usize) {
+    let ips_report = IpsReport::parse(input);
+    let images = &ips_report.images;
+    let mut unresolved = Unresolved::default();
+    let mut report = JsonReport {
+        path: None,
+        ips_header: input.lines().next().and_then(|line| serde_json::from_str(line).ok()),
+        metadata: ips_report.metadata.iter().cloned().collect(),
+        last_exception_backtrace: ips_report
+            .last_exception_backtrace
+            .iter()
+            .map(|frame| symbolicate_frame(frame, images, symbolicator, options, &mut unresolved))
+            .collect(),
+        threads: ips_report
+            .threads
+            .iter()
+            .map(|thread| JsonThread {
+                index: thread.index,
+                name: thread.name.clone(),
+                crashed: thread.crashed,
+                frames: thread.frames.iter().map(|frame| symbolicate_frame(frame, images, symbolicator, options, &mut unresolved)).collect(),
+            })
+            .collect(),
+        exception: ips_report.exception,
+        unresolved: vec![],
+    };
+    report.unresolved = unresolved.to_json();
+    (report, unresolved.len())
+}
+
+#[derive(Serialize)]
+struct JsonlFrame<'a> {
+    // Set when several reports are symbolicated at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    // None in the Last Exception Backtrace.
+    thread: Option<usize>,
+    #[serde(flatten)]
+    frame: JsonFrame,
+}
+
+// --jsonl: one JSON object per frame, printed as soon as it is resolved.
+fn print_jsonl(out: &mut impl Write, input: &str, path: Option<&str>, symbolicator: &ModuleSet, options: &Options) -> usize {
+    let report = IpsReport::parse(input);
+    let mut unresolved = Unresolved::default();
+    for (thread, frame) in report.frames() {
+        let frame = symbolicate_frame(frame, &report.images, symbolicator, options, &mut unresolved);
+        let _ = writeln!(out, "{}", serde_json::to_string(&JsonlFrame { path, thread, frame }).unwrap());
+    }
+    unresolved.len()
+}
+
+// Returns the number of frames that could not be resolved.
+#[cfg(feature = "proto")]
+fn write_proto(path: &str, report: &str, symbolicator: &ModuleSet, options: &Options) -> usize {
+    let (json, failures) = ips_to_json(report, symbolicator, options);
+    if let Err(err) = fs::write(path, proto::encode_report(&json)) {
+        println!("protobuf report({}) can not be written: {}", path, err);
+        process::exit(-1);
+    }
+    failures
+}
+
+#[cfg(not(feature = "proto"))]
+fn write_proto(_: &str, _: &str, _: &ModuleSet, _: &Options) -> usize {
+    println!("--proto-out requires ips_breakpad to be built with the proto feature");
+    process::exit(-1);
+}
+
+// --simplify-names, else the config file's setting for the selected output.
+fn parse_simplify_names(matches: &ArgMatches, config: &Config) -> NameSimplification {
+    if let Some(mode) = matches.value_of("simplify-names") {
+        return mode.parse().unwrap();
+    }
+    let simplify_names = &config.output.simplify_names;
+    if matches.is_present("html") {
+        simplify_names.html
+    } else if matches.is_present("markdown") {
+        simplify_names.markdown
+    } else if matches.is_present("json") || matches.is_present("jsonl") || matches.is_present("proto-out") || config.output.format.as_deref() == Some("json") {
+        simplify_names.json
+    } else {
+        simplify_names.text
+    }
+}
+
+// Symbolicating a single report, the default command of `ips_breakpad`.
+pub fn report_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(
+        Arg::with_name("input")
+            .help("input symbol file, symbols.zip[!entry] (also native-debug-symbols.zip from Google Play), an s3:// / gs:// / http(s):// symbol store or debuginfod:BUILD_ID (both require --module), appcenter:OWNER/APP[@VERSION], or a module name to search the configured symbol directories for")
+            .required(true),
+    )
+    .arg(
+        Arg::with_name("module")
+            .short("m")
+            .long("module")
+            .takes_value(true)
+            .help("image name of the symbolicated module as it appears in the report [default: MODULE name of the symbol file]"),
+    )
+    .arg(
+        Arg::with_name("basenames")
+            .short("s")
+            .long("basenames")
+            .help("strip directory names from source file paths"),
+    )
+    .arg(
+        Arg::with_name("simplify-names")
+            .long("simplify-names")
+            .takes_value(true)
+            .value_name("MODE")
+            .possible_values(&NameSimplification::VARIANTS)
+            .help("shorten function names by dropping template arguments, parameter lists or both, JSON output keeps the full name too [default: from the config file, else none]"),
+    )
+    .arg(
+        Arg::with_name("source-map")
+            .long("source-map")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("FROM=TO")
+            .help("rewrite source file paths starting with FROM to start with TO, e.g. a local checkout"),
+    )
+    .arg(
+        Arg::with_name("source-root")
+            .long("source-root")
+            .takes_value(true)
+            .value_name("DIR")
+            .help("print the source lines around each resolved frame, reading files from DIR"),
+    )
+    .arg(
+        Arg::with_name("source-bundle")
+            .long("source-bundle")
+            .takes_value(true)
+            .value_name("ARCHIVE")
+            .help("print the source lines around each resolved frame, and show them in --html reports, reading files from a zip or tar(.gz) of the build's sources"),
+    )
+    .arg(
+        Arg::with_name("context")
+            .long("context")
+            .takes_value(true)
+            .value_name("N")
+            .default_value("2")
+            .help("lines of source to print before and after the resolved line with --source-root or --source-bundle"),
+    )
+    .arg(
+        Arg::with_name("source-url")
+            .long("source-url")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .help("link frames in the --html report to a code browser, e.g. https://git.example.com/blob/main/{path}#L{line}"),
+    )
+    .arg(
+        Arg::with_name("strict")
+            .long("strict")
+            .help("exit with a non-zero status if any frame of the module could not be resolved"),
+    )
+    .arg(
+        Arg::with_name("json")
+            .long("json")
+            .help("print the report as a JSON document of threads and frames instead of rewriting it"),
+    )
+    .arg(
+        Arg::with_name("jsonl")
+            .long("jsonl")
+            .conflicts_with("json")
+            .help("print one JSON object per frame as soon as it is symbolicated, for streaming large reports"),
+    )
+    .arg(
+        Arg::with_name("html")
+            .long("html")
+            .takes_value(true)
+            .value_name("PATH")
+            .conflicts_with_all(&["json", "jsonl"])
+            .help("write the report as a standalone HTML page to PATH instead of printing it"),
+    )
+    .arg(
+        Arg::with_name("proto-out")
+            .long("proto-out")
+            .takes_value(true)
+            .value_name("PATH")
+            .conflicts_with_all(&["json", "jsonl", "html"])
+            .help("write the report as a binary protobuf (see proto/report.proto) to PATH instead of printing it"),
+    )
+    .arg(
+        Arg::with_name("markdown")
+            .long("markdown")
+            .conflicts_with_all(&["json", "jsonl", "html", "proto-out"])
+            .help("print a short Markdown summary of the exception and the crashed thread, for pasting into issues"),
+    )
+    .arg(
+        Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .conflicts_with_all(&["json", "jsonl", "html", "proto-out", "markdown"])
+            .help("print resolved frames as TEMPLATE, with the placeholders {addr} (relative to the image), {func}, {file}, {line} and {offset} (into the function) [default: {func} {file}:{line}]"),
+    )
+    .arg(
+        Arg::with_name("canonical-frames")
+            .long("canonical-frames")
+            .conflicts_with_all(&["json", "jsonl", "html", "proto-out", "markdown", "format"])
+            .help("print .ips, tombstone and microdump frames alike as #00 module!function+0x1c4 (file.cpp:88), for log pipelines normalizing crashes"),
+    )
+    .arg(frame_regex_arg())
+    .arg(jobs_arg())
+    .arg(stats_arg())
+    .arg(low_memory_arg())
+    .arg(
+        Arg::with_name("no-pc-adjustment")
+            .long("no-pc-adjustment")
+            .help("look up the return addresses of .ips/.crash frames other than the first as printed, not at their call (tombstone and logcat frames come adjusted by debuggerd)"),
+    )
+    .arg(
+        Arg::with_name("resymbolicate")
+            .long("resymbolicate")
+            .help("replace symbols already present in the report with ours for the module's frames"),
+    )
+    .arg(
+        Arg::with_name("ips")
+            .help("crash reports to lookup (.ips, .crash, tombstone or logcat), or - to read one from stdin")
+            .multiple(true)
+            .required(true),
+    )
+}
+
+pub fn aggregate_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("aggregate")
+        .about("symbolicate every report in a directory and group them by crash signature")
+        .arg(Arg::with_name("input").help("input symbol file, as for a single report").required(true))
+        .arg(Arg::with_name("dir").help("directory of crash reports").required(true))
+        .arg(
+            Arg::with_name("module")
+                .short("m")
+                .long("module")
+                .takes_value(true)
+                .help("image name of the symbolicated module as it appears in the reports [default: MODULE name of the symbol file]"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .long("frames")
+                .takes_value(true)
+                .value_name("N")
+                .default_value("5")
+                .help("number of top frames in our module that make up a crash signature"),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .takes_value(true)
+                .value_name("N")
+                .help("only print the N most frequent crash signatures"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("print the clusters as a JSON array, with first and last seen timestamps and a representative stack"),
+        )
+        .arg(frame_regex_arg())
+        .arg(jobs_arg())
+        .arg(stats_arg())
+        .arg(low_memory_arg())
+}
+
+pub fn run_aggregate(matches: &ArgMatches) {
+    let config = load_config(matches);
+    apply_frame_regex(matches, &config);
+    aggregate::run_aggregate(matches, &config, &ParseSettings::from_matches(matches));
+}
+
+pub fn scan_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("scan")
+        .about("symbolicate the addresses in any text, like logs with the engine's own backtraces: 0x... addresses and lib.so+0x... references of the module get their frame appended")
+        .arg(Arg::with_name("input").help("input symbol file, as for a single report").required(true))
+        .arg(Arg::with_name("log").help("text to scan, - for stdin").required(true))
+        .arg(
+            Arg::with_name("module")
+                .short("m")
+                .long("module")
+                .takes_value(true)
+                .help("module name as it appears in lib.so+0x... references [default: MODULE name of the symbol file]"),
+        )
+        .arg(
+            Arg::with_name("base")
+                .long("base")
+                .takes_value(true)
+                .value_name("ADDR")
+                .help("load address of the module, subtracted from bare 0x... addresses [default: they are module relative]"),
+        )
+        .arg(stats_arg())
+        .arg(low_memory_arg())
+}
+
+pub fn run_scan(matches: &ArgMatches) {
+    scan::run_scan(matches, &load_config(matches), &ParseSettings::from_matches(matches));
+}
+
+// Separates the reports when several are given, as head and tail do.
+fn print_report_header(out: &mut impl Write, path: &Path, first: bool) {
+    if !first {
+        let _ = writeln!(out);
+    }
+    let _ = writeln!(out, "==> {} <==", path.display());
+}
+
+// Report output goes straight to stdout, unless reports are symbolicated in
+// parallel: then each goes to a buffer, printed in order.
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
+
+fn frame_regex_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("frame-regex")
+        .long("frame-regex")
+        .takes_value(true)
+        .value_name("REGEX")
+        .help("match .ips/.crash frame lines with REGEX instead, for other report dialects; it needs the named groups so (image), mem_address (hex), base and offset (decimal), i (frame index) is optional [default: from the config file, else the Apple format]")
+}
+
+// --frame-regex, else the config file's.
+fn apply_frame_regex(matches: &ArgMatches, config: &Config) {
+    if let Some(pattern) = matches.value_of("frame-regex").or(config.frame_regex.as_deref()) {
+        if let Err(err) = set_frame_regex(pattern) {
+            println!("invalid frame regex({}): {}", pattern, err);
+            process::exit(-1);
+        }
+    }
+}
+
+fn jobs_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("jobs")
+        .short("j")
+        .long("jobs")
+        .takes_value(true)
+        .value_name("N")
+        .help("number of reports to symbolicate in parallel [default: number of CPUs]")
+}
+
+fn parse_jobs(matches: &ArgMatches) -> usize {
+    match matches.value_of("jobs") {
+        Some(jobs) => match jobs.parse() {
+            Ok(jobs) if jobs > 0 => jobs,
+            _ => {
+                println!("invalid job count({})", jobs);
+                process::exit(-1);
+            }
+        },
+        None => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    }
+}
+
+// Writes the report at `index` of those given on the command line to `out` in
+// the selected output format. Returns the number of frames that could not be
+// resolved, and with --json and several reports, the document to print in
+// an array with the others.
+fn symbolicate_report(out: &mut impl Write, index: usize, ips: &Path, report: &str, matches: &ArgMatches, symbolicator: &ModuleSet, options: &Options) -> (usize, Option<JsonReport>) {
+    let path = ips.display().to_string();
+    let multiple = matches.values_of("ips").unwrap().len() > 1;
+    if let Some(path) = matches.value_of("html") {
+        let (json, failures) = ips_to_json(report, symbolicator, options);
+        if let Err(err) = fs::write(path, html::render_html(&json, options)) {
+            println!("html report({}) can not be written: {}", path, err);
+            process::exit(-1);
+        }
+        (failures, None)
+    } else if let Some(path) = matches.value_of("proto-out") {
+        (write_proto(path, report, symbolicator, options), None)
+    } else if matches.is_present("markdown") {
+        if multiple {
+            print_report_header(out, ips, index == 0);
+        }
+        let (json, failures) = ips_to_json(report, symbolicator, options);
+        let _ = write!(out, "{}", markdown::render_markdown(&json));
+        (failures, None)
+    } else if matches.is_present("jsonl") {
+        (print_jsonl(out, report, Some(path.as_str()).filter(|_| multiple), symbolicator, options), None)
+    } else if options.json {
+        let (mut json, failures) = ips_to_json(report, symbolicator, options);
+        if multiple {
+            json.path = Some(path);
+            return (failures, Some(json));
+        }
+        let _ = writeln!(out, "{}", serde_json::to_string_pretty(&json).unwrap());
+        (failures, None)
+    } else {
+        if multiple {
+            print_report_header(out, ips, index == 0);
+        }
+        (parser_ips(out, report, symbolicator, options), None)
+    }
+}
+
+pub fn run_report(matches: &ArgMatches) {
+    let paths: Vec<&Path> = matches.values_of("ips").unwrap().map(Path::new).collect();
+    for ips in &paths {
+        if *ips != Path::new("-") && !ips.exists() {
+            println!("ips file({}) is not exists", ips.display());
+            process::exit(-1);
+        }
+    }
+    let multiple = paths.len() > 1;
+    if multiple && (matches.is_present("html") || matches.is_present("proto-out")) {
+        println!("--html and --proto-out take a single report");
+        process::exit(-1);
+    }
+    let jobs = parse_jobs(matches);
+    // The module is resolved from the first report, the others are read as
+    // they are symbolicated.
+    info!("symbolicating {}", paths[0].display());
+    let first_report = read_ips(paths[0]);
+    let read_report = |index: usize, ips: &Path| {
+        if index == 0 {
+            return Cow::Borrowed(first_report.as_str());
+        }
+        info!("symbolicating {}", ips.display());
+        Cow::Owned(read_ips(ips))
+    };
+
+    let config = load_config(matches);
+    apply_frame_regex(matches, &config);
+
+    let started = Instant::now();
+    let mut module = load_module(matches.value_of("input").unwrap(), matches.value_of("module"), &first_report, &config, &ParseSettings::from_matches(matches));
+    let parse_time = started.elapsed();
+    if let Some(name) = matches.value_of("module") {
+        module.name = name.to_string();
+    }
+    let mut symbolicator = ModuleSet::new();
+    symbolicator.add_module(module);
+    for (image_name, module_name) in &config.module_aliases {
+        symbolicator.add_alias(image_name, module_name);
+    }
+    // The other reports are read again when symbolicated, stdin can't be.
+    #[cfg(feature = "debuginfod")]
+    if !debuginfod_urls().is_empty() {
+        for (index, ips) in paths.iter().enumerate() {
+            match index {
+                0 => add_debuginfod_modules(&mut symbolicator, &first_report, &config),
+                _ if *ips != Path::new("-") => add_debuginfod_modules(&mut symbolicator, &read_ips(ips), &config),
+                _ => {}
+            }
+        }
+    }
+    let options = Options {
+        basenames: matches.is_present("basenames") || config.output.basenames,
+        simplify_names: parse_simplify_names(matches, &config),
+        json: matches.is_present("json") || config.output.format.as_deref() == Some("json"),
+        canonical_frames: matches.is_present("canonical-frames"),
+        strict: matches.is_present("strict"),
+        resymbolicate: matches.is_present("resymbolicate"),
+        adjust_return_addresses: !matches.is_present("no-pc-adjustment"),
+        source_map: parse_source_map(matches),
+        source_root: matches.value_of("source-root").map(PathBuf::from),
+        source_bundle: parse_source_bundle(matches),
+        context_lines: parse_context_lines(matches),
+        source_url: matches.value_of("source-url").map(String::from),
+        format: parse_symbol_format(matches, "{func} {file}:{line}"),
+    };
+    let mut failures = 0;
+    let mut json_reports = vec![];
+    if multiple && jobs > 1 {
+        // The symbols are only read, every worker shares them.
+        for_each_ordered(
+            &paths,
+            jobs,
+            |index, ips| {
+                let mut out = String::new();
+                let result = symbolicate_report(&mut out, index, ips, &read_report(index, ips), matches, &symbolicator, &options);
+                (out, result)
+            },
+            |(out, (report_failures, json))| {
+                print!("{}", out);
+                failures += report_failures;
+                json_reports.extend(json);
+            },
+        );
+    } else {
+        for (index, ips) in paths.iter().enumerate() {
+            let (report_failures, json) = symbolicate_report(&mut Stdout, index, ips, &read_report(index, ips), matches, &symbolicator, &options);
+            failures += report_failures;
+            json_reports.extend(json);
+        }
+    }
+    if !json_reports.is_empty() {
+        println!("{}", serde_json::to_string_pretty(&json_reports).unwrap());
+    }
+    if matches.is_present("stats") {
+        print_stats(parse_time, symbolicator.modules());
+    }
+    if options.strict && failures > 0 {
+        eprintln!("{} frame(s) could not be resolved", failures);
+        process::exit(1);
+    }
+    process::exit(0);
+}
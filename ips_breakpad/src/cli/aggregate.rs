@@ -0,0 +1,243 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use addr2line_breakpad::cli::{print_stats, ParseSettings};
+use addr2line_breakpad::{Config, SourceMap, ModuleSet};
+use clap::ArgMatches;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{for_each_ordered, parse_report_timestamp};
+#[cfg(feature = "debuginfod")]
+use super::add_debuginfod_modules;
+use super::{ips_to_json, load_module, parse_jobs, JsonFrame, JsonReport, Options};
+
+// Deep enough to tell call sites apart, a representative stack beyond this
+// is noise in a listing of many clusters.
+const MAX_REPRESENTATIVE_FRAMES: usize = 20;
+
+#[derive(Serialize)]
+struct Cluster {
+    signature: String,
+    count: usize,
+    // As written in the reports, compared as instants.
+    first_seen: Option<String>,
+    last_seen: Option<String>,
+    #[serde(skip)]
+    seen_range: Option<(i64, i64)>,
+    reports: Vec<PathBuf>,
+    frames: Vec<JsonFrame>,
+}
+
+impl Cluster {
+    fn add_timestamp(&mut self, timestamp: &str) {
+        let seconds = match parse_report_timestamp(timestamp) {
+            Some(seconds) => seconds,
+            None => return,
+        };
+        let (first, last) = self.seen_range.unwrap_or((seconds, seconds));
+        if seconds <= first {
+            self.first_seen = Some(timestamp.to_string());
+        }
+        if seconds >= last {
+            self.last_seen = Some(timestamp.to_string());
+        }
+        self.seen_range = Some((first.min(seconds), last.max(seconds)));
+    }
+}
+
+// Every regular, non-hidden file of the directory, in name order.
+fn report_paths(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            println!("report directory({}) can not be read: {}", dir.display(), err);
+            process::exit(-1);
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !path.file_name().unwrap().to_string_lossy().starts_with('.'))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// The stack that crashed: the Last Exception Backtrace of an uncaught
+// exception, otherwise the crashed thread.
+// `Date/Time:` of the text report, or the `timestamp` of its JSON header.
+fn report_timestamp(report: &JsonReport) -> Option<String> {
+    report.metadata.get("Date/Time").cloned().or_else(|| {
+        let timestamp = report.ips_header.as_ref()?.get("timestamp")?.as_str()?;
+        Some(timestamp.to_string())
+    })
+}
+
+fn crashed_frames(report: JsonReport) -> Vec<JsonFrame> {
+    if !report.last_exception_backtrace.is_empty() {
+        return report.last_exception_backtrace;
+    }
+    report.threads.into_iter().find(|thread| thread.crashed).map(|thread| thread.frames).unwrap_or_default()
+}
+
+fn frame_label(function: Option<&str>, frame: &JsonFrame) -> String {
+    match function {
+        Some(function) => function.to_string(),
+        None => format!("{}+{:#x}", frame.image, frame.offset.unwrap_or(0)),
+    }
+}
+
+// The top `depth` frames in modules we have symbols for, or in any module
+// when the crash never reached ours. Full names, so template instantiations
+// stay apart whatever --simplify-names shows.
+fn crash_signature(frames: &[JsonFrame], symbolicator: &ModuleSet, depth: usize) -> String {
+    let app_frames: Vec<&JsonFrame> = frames.iter().filter(|frame| symbolicator.module(&frame.image).is_some()).collect();
+    let frames: Vec<&JsonFrame> = if app_frames.is_empty() { frames.iter().collect() } else { app_frames };
+    let labels: Vec<String> = frames.iter().take(depth).map(|frame| frame_label(frame.function.as_deref(), frame)).collect();
+    if labels.is_empty() {
+        String::from("<no frames>")
+    } else {
+        labels.join(" | ")
+    }
+}
+
+fn print_cluster(cluster: &Cluster) {
+    println!("{} report(s): {}", cluster.count, cluster.signature);
+    if let (Some(first), Some(last)) = (&cluster.first_seen, &cluster.last_seen) {
+        println!("  first seen {}, last seen {}", first, last);
+    }
+    for path in &cluster.reports {
+        println!("    {}", path.display());
+    }
+    for frame in cluster.frames.iter().take(MAX_REPRESENTATIVE_FRAMES) {
+        let location = match (&frame.file, frame.line) {
+            (Some(file), Some(line)) => format!(" {}:{}", file, line),
+            (Some(file), None) => format!(" {}", file),
+            _ => String::new(),
+        };
+        println!("  #{:02} {:<30} {}{}", frame.index, frame.image, frame_label(frame.display_function(), frame), location);
+    }
+    println!();
+}
+
+// `ips_breakpad aggregate <symbol file> <dir>`: symbolicates every report in
+// the directory and groups them by crash signature, largest cluster first,
+// the `--top N` of them with `--top`.
+pub fn run_aggregate(matches: &ArgMatches, config: &Config, settings: &ParseSettings) {
+    let dir = Path::new(matches.value_of("dir").unwrap());
+    let depth: usize = match matches.value_of("frames").unwrap().parse() {
+        Ok(depth) => depth,
+        Err(_) => {
+            println!("invalid frame count({})", matches.value_of("frames").unwrap());
+            process::exit(-1);
+        }
+    };
+    let top = matches.value_of("top").map(|top| match top.parse::<usize>() {
+        Ok(top) => top,
+        Err(_) => {
+            println!("invalid cluster count({})", top);
+            process::exit(-1);
+        }
+    });
+    let reports: Vec<(PathBuf, String)> = report_paths(dir)
+        .into_iter()
+        .filter_map(|path| match fs::read(&path) {
+            Ok(bytes) => Some((path, String::from_utf8_lossy(&bytes).into_owned())),
+            Err(err) => {
+                warn!("skipping {}: {}", path.display(), err);
+                None
+            }
+        })
+        .collect();
+    if reports.is_empty() {
+        println!("no reports in {}", dir.display());
+        process::exit(-1);
+    }
+
+    let first_report = reports[0].1.as_str();
+    let started = Instant::now();
+    let mut module = load_module(matches.value_of("input").unwrap(), matches.value_of("module"), first_report, config, settings);
+    let parse_time = started.elapsed();
+    if let Some(name) = matches.value_of("module") {
+        module.name = name.to_string();
+    }
+    let mut symbolicator = ModuleSet::new();
+    symbolicator.add_module(module);
+    for (image_name, module_name) in &config.module_aliases {
+        symbolicator.add_alias(image_name, module_name);
+    }
+    #[cfg(feature = "debuginfod")]
+    for (_, report) in &reports {
+        add_debuginfod_modules(&mut symbolicator, report, config);
+    }
+    let options = Options {
+        basenames: config.output.basenames,
+        simplify_names: if matches.is_present("json") { config.output.simplify_names.json } else { config.output.simplify_names.text },
+        json: false,
+        canonical_frames: false,
+        strict: false,
+        resymbolicate: false,
+        adjust_return_addresses: true,
+        source_map: SourceMap::new(),
+        source_root: None,
+        source_bundle: None,
+        context_lines: 0,
+        source_url: None,
+        format: "{func} {file}:{line}".parse().unwrap(),
+    };
+
+    let mut clusters: Vec<Cluster> = vec![];
+    let mut by_signature: HashMap<String, usize> = HashMap::new();
+    // Symbolicated in parallel, clustered in directory order.
+    let symbolicate = |_, (path, report): &(PathBuf, String)| {
+        info!("symbolicating {}", path.display());
+        let (json, _) = ips_to_json(report, &symbolicator, &options);
+        let timestamp = report_timestamp(&json);
+        let frames = crashed_frames(json);
+        let signature = crash_signature(&frames, &symbolicator, depth);
+        (path.clone(), timestamp, frames, signature)
+    };
+    for_each_ordered(&reports, parse_jobs(matches), symbolicate, |(path, timestamp, frames, signature)| {
+        let i = match by_signature.get(&signature) {
+            Some(&i) => i,
+            None => {
+                by_signature.insert(signature.clone(), clusters.len());
+                clusters.push(Cluster {
+                    signature,
+                    count: 0,
+                    first_seen: None,
+                    last_seen: None,
+                    seen_range: None,
+                    reports: vec![],
+                    frames,
+                });
+                clusters.len() - 1
+            }
+        };
+        let cluster = &mut clusters[i];
+        cluster.count += 1;
+        cluster.reports.push(path);
+        if let Some(timestamp) = &timestamp {
+            cluster.add_timestamp(timestamp);
+        }
+    });
+
+    // Stable, so equally sized clusters stay in the order first seen.
+    clusters.sort_by_key(|cluster| Reverse(cluster.count));
+    clusters.truncate(top.unwrap_or(clusters.len()));
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&clusters).unwrap());
+    } else {
+        for cluster in &clusters {
+            print_cluster(cluster);
+        }
+    }
+    if matches.is_present("stats") {
+        print_stats(parse_time, symbolicator.modules());
+    }
+}
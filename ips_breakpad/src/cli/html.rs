@@ -0,0 +1,137 @@
+use std::fmt::Write;
+
+use addr2line_breakpad::find_source_file;
+use crate::{escape_html, expand_source_url};
+
+use super::{JsonFrame, JsonReport, Options};
+
+const STYLE: &str = "
+body { font-family: -apple-system, Segoe UI, Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; }
+table { border-collapse: collapse; font-family: Menlo, Consolas, monospace; font-size: 0.85em; }
+td, th { padding: 2px 10px; text-align: left; vertical-align: top; }
+tr:nth-child(even) { background: #f6f6f6; }
+details { margin: 0.5em 0; border: 1px solid #ddd; border-radius: 4px; padding: 0.3em 0.6em; }
+summary { cursor: pointer; font-weight: bold; }
+.crashed { border-color: #d33; background: #fff4f4; }
+.crashed > summary { color: #c00; }
+.unresolved { color: #999; }
+.not_found, .out_of_range { color: #c00; }
+pre.source { margin: 0.3em 0; color: #555; }
+";
+
+// Which of our lookups produced the frame's symbol, see `JsonFrame::match_kind`.
+fn frame_class(frame: &JsonFrame) -> &'static str {
+    match frame.match_kind {
+        "line" | "func" | "public" => "resolved",
+        "not_found" => "not_found",
+        "out_of_range" => "out_of_range",
+        _ => "unresolved",
+    }
+}
+
+// A --source-url template wins over a file found under --source-root.
+fn source_link(file: &str, line: Option<i64>, options: &Options) -> Option<String> {
+    if let Some(template) = &options.source_url {
+        return Some(expand_source_url(template, file, line));
+    }
+    let path = find_source_file(options.source_root.as_deref(), file)?;
+    let path = path.canonicalize().unwrap_or(path);
+    Some(format!("file://{}", path.display().to_string().replace('\\', "/")))
+}
+
+fn write_frames(html: &mut String, frames: &[JsonFrame], options: &Options) {
+    html.push_str("<table>\n<tr><th>#</th><th>Image</th><th>Address</th><th>Function</th><th>Location</th></tr>\n");
+    for frame in frames {
+        let location = match &frame.file {
+            Some(file) => {
+                let text = match frame.line {
+                    Some(line) => format!("{}:{}", file, line),
+                    None => file.clone(),
+                };
+                let mut location = match source_link(file, frame.line, options) {
+                    Some(link) => format!("<a href=\"{}\">{}</a>", escape_html(&link), escape_html(&text)),
+                    None => escape_html(&text),
+                };
+                // The report may be read far from any checkout, the bundle's
+                // lines go into the page.
+                let bundle = options.source_bundle.as_ref();
+                if let Some(snippet) = frame.line.and_then(|line| bundle?.snippet(file, line, options.context_lines)) {
+                    let _ = write!(location, "<pre class=\"source\">{}</pre>", escape_html(&snippet));
+                }
+                location
+            }
+            None => String::new(),
+        };
+        let _ = writeln!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            frame_class(frame),
+            frame.index,
+            escape_html(&frame.image),
+            frame.address,
+            escape_html(frame.display_function().unwrap_or("???")),
+            location
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+// A standalone page: no scripts or external assets, threads are <details>
+// elements so they collapse without JavaScript, the crashed one open.
+pub fn render_html(report: &JsonReport, options: &Options) -> String {
+    let title = report
+        .metadata
+        .get("Process")
+        .map_or_else(|| String::from("Crash report"), |process| format!("Crash report: {}", process));
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>",
+        escape_html(&title),
+        STYLE,
+        escape_html(&title)
+    );
+
+    let exception = &report.exception;
+    let summary = [
+        ("Exception Type", &exception.exception_type),
+        ("Exception Codes", &exception.exception_codes),
+        ("Termination Reason", &exception.termination_reason),
+        ("Fault Address", &exception.fault_address),
+        ("Crashed Thread", &exception.crashed_thread),
+    ];
+    html.push_str("<table class=\"summary\">\n");
+    for (key, value) in summary.iter().filter_map(|(key, value)| Some((key, value.as_ref()?))) {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", key, escape_html(value));
+    }
+    for (key, value) in &report.metadata {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape_html(key), escape_html(value));
+    }
+    html.push_str("</table>\n");
+
+    if !report.last_exception_backtrace.is_empty() {
+        html.push_str("<details class=\"crashed\" open>\n<summary>Last Exception Backtrace</summary>\n");
+        write_frames(&mut html, &report.last_exception_backtrace, options);
+        html.push_str("</details>\n");
+    }
+    for thread in &report.threads {
+        let mut summary = format!("Thread {}", thread.index);
+        if let Some(name) = &thread.name {
+            let _ = write!(summary, ": {}", name);
+        }
+        if thread.crashed {
+            summary.push_str(" (crashed)");
+        }
+        let _ = writeln!(
+            html,
+            "<details{}>\n<summary>{}</summary>",
+            if thread.crashed { " class=\"crashed\" open" } else { "" },
+            escape_html(&summary)
+        );
+        write_frames(&mut html, &thread.frames, options);
+        html.push_str("</details>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use crate::escape_markdown_cell;
+
+use super::{JsonFrame, JsonReport};
+
+fn write_frames(markdown: &mut String, frames: &[JsonFrame]) {
+    markdown.push_str("| # | Image | Function | Location |\n|---|---|---|---|\n");
+    for frame in frames {
+        let function = match frame.display_function() {
+            // Backslashes are literal in code spans, only the pipe needs one.
+            Some(function) => format!("`{}`", function.replace('`', "'").replace('|', "\\|")),
+            None => format!("`{}`", frame.address),
+        };
+        let location = match (&frame.file, frame.line) {
+            (Some(file), Some(line)) => format!("{}:{}", escape_markdown_cell(file), line),
+            (Some(file), None) => escape_markdown_cell(file),
+            _ => String::new(),
+        };
+        let _ = writeln!(markdown, "| {} | {} | {} | {} |", frame.index, escape_markdown_cell(&frame.image), function, location);
+    }
+}
+
+// Exception info and the crashed thread only: short enough to paste into an
+// issue, the full report stays attached.
+pub fn render_markdown(report: &JsonReport) -> String {
+    let mut markdown = String::new();
+    match report.metadata.get("Process") {
+        Some(process) => {
+            let _ = writeln!(markdown, "### Crash in {}\n", process);
+        }
+        None => markdown.push_str("### Crash\n\n"),
+    }
+
+    let exception = &report.exception;
+    let summary = [
+        ("Exception Type", &exception.exception_type),
+        ("Exception Codes", &exception.exception_codes),
+        ("Termination Reason", &exception.termination_reason),
+        ("Fault Address", &exception.fault_address),
+    ];
+    for (key, value) in summary.iter().filter_map(|(key, value)| Some((key, value.as_ref()?))) {
+        let _ = writeln!(markdown, "- **{}:** `{}`", key, value);
+    }
+    for key in ["Version", "OS Version", "Hardware Model"] {
+        if let Some(value) = report.metadata.get(key) {
+            let _ = writeln!(markdown, "- **{}:** {}", key, value);
+        }
+    }
+
+    if !report.last_exception_backtrace.is_empty() {
+        markdown.push_str("\n#### Last Exception Backtrace\n\n");
+        write_frames(&mut markdown, &report.last_exception_backtrace);
+    }
+    if let Some(thread) = report.threads.iter().find(|thread| thread.crashed) {
+        match &thread.name {
+            Some(name) => {
+                let _ = writeln!(markdown, "\n#### Thread {} Crashed: {}\n", thread.index, name);
+            }
+            None => {
+                let _ = writeln!(markdown, "\n#### Thread {} Crashed\n", thread.index);
+            }
+        }
+        write_frames(&mut markdown, &thread.frames);
+    }
+    if !report.unresolved.is_empty() {
+        markdown.push_str("\n#### Unresolved frames\n\n");
+        for image in &report.unresolved {
+            let _ = writeln!(markdown, "- {}: {} (`{}`)", escape_markdown_cell(&image.image), image.count, image.offsets.join(", "));
+        }
+    }
+    markdown
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use prost::Message;
+
+use super::{JsonFrame, JsonReport};
+
+// Hand-written counterparts of proto/report.proto, kept in sync by tag.
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Report {
+    #[prost(btree_map = "string, string", tag = "1")]
+    pub metadata: BTreeMap<String, String>,
+    #[prost(message, optional, tag = "2")]
+    pub exception: Option<Exception>,
+    #[prost(message, repeated, tag = "3")]
+    pub last_exception_backtrace: Vec<Frame>,
+    #[prost(message, repeated, tag = "4")]
+    pub threads: Vec<Thread>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Exception {
+    #[prost(string, optional, tag = "1")]
+    pub r#type: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub codes: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub termination_reason: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub crashed_thread: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub fault_address: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Thread {
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(string, optional, tag = "2")]
+    pub name: Option<String>,
+    #[prost(bool, tag = "3")]
+    pub crashed: bool,
+    #[prost(message, repeated, tag = "4")]
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Frame {
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(string, tag = "2")]
+    pub module: String,
+    #[prost(uint64, tag = "3")]
+    pub address: u64,
+    #[prost(uint64, optional, tag = "4")]
+    pub offset: Option<u64>,
+    #[prost(message, optional, tag = "5")]
+    pub symbol: Option<Symbol>,
+    #[prost(enumeration = "MatchKind", tag = "6")]
+    pub r#match: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Symbol {
+    #[prost(string, tag = "1")]
+    pub function: String,
+    #[prost(string, optional, tag = "2")]
+    pub file: Option<String>,
+    #[prost(int64, optional, tag = "3")]
+    pub line: Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum MatchKind {
+    None = 0,
+    Line = 1,
+    Func = 2,
+    Public = 3,
+    Report = 4,
+    NotFound = 5,
+    OutOfRange = 6,
+}
+
+impl MatchKind {
+    fn from_json(match_kind: &str) -> Self {
+        match match_kind {
+            "line" => MatchKind::Line,
+            "func" => MatchKind::Func,
+            "public" => MatchKind::Public,
+            "report" => MatchKind::Report,
+            "not_found" => MatchKind::NotFound,
+            "out_of_range" => MatchKind::OutOfRange,
+            _ => MatchKind::None,
+        }
+    }
+}
+
+fn frame(frame: &JsonFrame) -> Frame {
+    let address = frame.address.trim_start_matches("0x");
+    Frame {
+        index: frame.index as u32,
+        module: frame.image.clone(),
+        address: u64::from_str_radix(address, 16).unwrap_or(0),
+        offset: frame.offset,
+        symbol: frame.function.as_ref().map(|function| Symbol {
+            function: function.clone(),
+            file: frame.file.clone(),
+            line: frame.line,
+        }),
+        r#match: MatchKind::from_json(frame.match_kind) as i32,
+    }
+}
+
+// The --proto-out counterpart of the --json document.
+pub fn encode_report(report: &JsonReport) -> Vec<u8> {
+    let exception = &report.exception;
+    Report {
+        metadata: report.metadata.clone(),
+        exception: Some(Exception {
+            r#type: exception.exception_type.clone(),
+            codes: exception.exception_codes.clone(),
+            termination_reason: exception.termination_reason.clone(),
+            crashed_thread: exception.crashed_thread.clone(),
+            fault_address: exception.fault_address.clone(),
+        }),
+        last_exception_backtrace: report.last_exception_backtrace.iter().map(frame).collect(),
+        threads: report
+            .threads
+            .iter()
+            .map(|thread| Thread {
+                index: thread.index as u32,
+                name: thread.name.clone(),
+                crashed: thread.crashed,
+                frames: thread.frames.iter().map(frame).collect(),
+            })
+            .collect(),
+    }
+    .encode_to_vec()
+}
+
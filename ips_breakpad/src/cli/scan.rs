@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+use addr2line_breakpad::cli::{print_stats, ParseSettings};
+use addr2line_breakpad::{canonical_frame, parse_address, Config, ModuleSet, NameSimplification};
+use clap::ArgMatches;
+
+use crate::find_address_tokens;
+#[cfg(feature = "debuginfod")]
+use super::add_debuginfod_modules;
+use super::{load_module, read_ips};
+
+// `line` with every address of a loaded module followed by its frame:
+//   assert at 0x7a1c2b04 [libil2cpp.so!Assert+0x24 (Debug.cpp:88)]
+// `module+0x...` references are looked up in that module, bare addresses in
+// the first module, less `base`.
+fn annotate_line(line: &str, symbolicator: &ModuleSet, base: Option<u64>, simplify_names: NameSimplification) -> String {
+    let mut annotated = String::with_capacity(line.len());
+    let mut copied = 0;
+    for token in find_address_tokens(line) {
+        let (module, rel_addr) = match token.module {
+            Some(name) => match symbolicator.module(name) {
+                Some(module) => (module, token.address),
+                None => continue,
+            },
+            None => match (symbolicator.modules().first(), token.address.checked_sub(base.unwrap_or(0))) {
+                (Some(module), Some(rel_addr)) if !module.symbols.is_out_of_range(rel_addr) => (module, rel_addr),
+                _ => continue,
+            },
+        };
+        let symbol = match module.lookup(rel_addr) {
+            Some(symbol) => symbol,
+            None => continue,
+        };
+        annotated.push_str(&line[copied..token.span.end]);
+        annotated.push_str(&format!(" [{}]", canonical_frame(&module.name, rel_addr, Some(&symbol), simplify_names)));
+        copied = token.span.end;
+    }
+    annotated.push_str(&line[copied..]);
+    annotated
+}
+
+pub fn run_scan(matches: &ArgMatches, config: &Config, settings: &ParseSettings) {
+    let path = Path::new(matches.value_of("log").unwrap());
+    if path != Path::new("-") && !path.exists() {
+        println!("log file({}) is not exists", path.display());
+        process::exit(-1);
+    }
+    let base = matches.value_of("base").map(|base| match parse_address(base) {
+        Some(base) => base,
+        None => {
+            println!("invalid base address({})", base);
+            process::exit(-1);
+        }
+    });
+    let text = read_ips(path);
+
+    let started = Instant::now();
+    let mut module = load_module(matches.value_of("input").unwrap(), matches.value_of("module"), &text, config, settings);
+    let parse_time = started.elapsed();
+    if let Some(name) = matches.value_of("module") {
+        module.name = name.to_string();
+    }
+    let mut symbolicator = ModuleSet::new();
+    symbolicator.add_module(module);
+    for (image_name, module_name) in &config.module_aliases {
+        symbolicator.add_alias(image_name, module_name);
+    }
+    #[cfg(feature = "debuginfod")]
+    add_debuginfod_modules(&mut symbolicator, &text, config);
+
+    let simplify_names = config.output.simplify_names.text;
+    for line in text.lines() {
+        println!("{}", annotate_line(line, &symbolicator, base, simplify_names));
+    }
+    if matches.is_present("stats") {
+        print_stats(parse_time, symbolicator.modules());
+    }
+}
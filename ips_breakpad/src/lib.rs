@@ -0,0 +1,644 @@
+use addr2line_breakpad::ModuleId;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+
+pub mod cli;
+mod report;
+
+pub use report::{parse_exception_summary, IpsReport, ExceptionSummary, Frame, Thread};
+
+// Frame lines look like:
+//   3   UnityFramework                	0x0000000107415a94 0x106904000 + 11606676
+// macOS reports additionally contain image names with spaces, digits and
+// punctuation (`libc++.1.dylib`, `Google Chrome Framework`) and `???` frames
+// whose base is a bare `0`.
+const FRAME_RE: &str = r"^(?P<i>\d+)\s+(?P<so>\S.*?)\s+0x(?P<mem_address>[0-9a-fA-F]+)\s+(?:0x)?(?P<base>[0-9a-fA-F]+)\s\+\s(?P<offset>[0-9]+)\s*$";
+
+// Frames of system libraries usually come symbolicated already:
+//   0   libsystem_kernel.dylib        	0x00000001bda6e30c mach_msg_trap + 8
+//   1   UIKitCore                     	0x00000001924f2ee8 -[UIApplication _run] + 1052 (UIApplication.m:3421)
+const SYMBOLICATED_FRAME_RE: &str = r"^(?P<i>\d+)\s+(?P<so>\S.*?)\s+0x(?P<mem_address>[0-9a-fA-F]+)\s+(?P<symbolicated>(?P<symbol>\S.*?)\s\+\s(?P<offset>[0-9]+)(?:\s+\(.*\))?)\s*$";
+
+// Android tombstone and logcat backtraces look like:
+//   #00 pc 000000000004c8c4  /apex/com.android.runtime/lib64/bionic/libc.so (abort+164) (BuildId: 2b7a7a7c0e1a58b5)
+//   #01 pc 00000000001a2b3c  /data/app/com.foo/lib/arm64/libil2cpp.so (offset 0x1234000) (BuildId: b4b2bd4f)
+// The pc is relative to the library, like breakpad addresses.
+const TOMBSTONE_FRAME_RE: &str = r"^\s*#(?P<i>\d+)\s+pc\s+(?P<pc>[0-9a-fA-F]+)\s+(?P<path>\S+)(?:\s+\(offset 0x[0-9a-fA-F]+\))?(?:\s+\((?P<function>[^()]*(?:\([^()]*\)[^()]*)*)\))?(?:\s+\(BuildId: (?P<build_id>[0-9a-fA-F]+)\))?\s*$";
+
+// Binary Images lines look like:
+//   0x106904000 - 0x10a7fffff UnityFramework arm64  <ee27598b6a283e389075d1014679c8b0> /private/.../UnityFramework
+// macOS prefixes third party images with `+`, shows a bundle id plus version
+// instead of the file name and may print the uuid with dashes.
+const IMAGE_RE: &str = r"^\s*0x(?P<start>[0-9a-fA-F]+)\s*-\s*0x(?P<end>[0-9a-fA-F]+)\s+\+?(?P<name>.+?)\s+.*<(?P<uuid>[0-9a-fA-F-]{32,36})>\s*(?P<path>.*)$";
+
+// Registers of the crashed thread, several per line:
+//   Thread 0 crashed with ARM Thread State (64-bit):
+//      x28: 0x000000010a84b200   fp: 0x000000016db71340   lr: 0x000000018f732318
+//       sp: 0x000000016db71330   pc: 0x00000001bda6e30c cpsr: 0x60000000
+const REGISTER_RE: &str = r"\b(?P<name>[a-z][a-z0-9]*):\s+0x(?P<value>[0-9a-fA-F]{1,16})\b";
+
+// Addresses in free text, as engines log them in their own backtraces:
+//   assert at 0x7a1c2b04 (libil2cpp.so+0x1c4a2b0)
+const ADDRESS_TOKEN_RE: &str = r"(?:(?P<module>[\w.\-]+)\+)?\b0x(?P<address>[0-9a-fA-F]{1,16})\b";
+
+static FRAME_RE_OVERRIDE: OnceLock<Regex> = OnceLock::new();
+
+fn frame_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    FRAME_RE_OVERRIDE.get().unwrap_or_else(|| RE.get_or_init(|| Regex::new(FRAME_RE).unwrap()))
+}
+
+// A frame regex given by the user, checked for the groups
+// `parse_frame_line` reads.
+fn compile_frame_regex(pattern: &str) -> Result<Regex, String> {
+    let re = Regex::new(pattern).map_err(|err| err.to_string())?;
+    let names: Vec<&str> = re.capture_names().flatten().collect();
+    let missing: Vec<&str> = ["so", "mem_address", "base", "offset"].iter().copied().filter(|name| !names.contains(name)).collect();
+    if !missing.is_empty() {
+        return Err(format!("missing named groups: {}", missing.join(", ")));
+    }
+    Ok(re)
+}
+
+/// Replaces the regex matching unsymbolicated frame lines, for report
+/// dialects the built-in one misses (translated reports, crash SDK exports).
+/// It needs the named groups `so` (image name), `mem_address` (hex, without
+/// 0x), `base` and `offset` (decimal, from the image start); `i`, the frame
+/// index, is optional. Can only be set once, before the first report is
+/// parsed.
+pub fn set_frame_regex(pattern: &str) -> Result<(), String> {
+    FRAME_RE_OVERRIDE.set(compile_frame_regex(pattern)?).map_err(|_| String::from("the frame regex is already set"))
+}
+
+fn symbolicated_frame_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(SYMBOLICATED_FRAME_RE).unwrap())
+}
+
+fn tombstone_frame_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(TOMBSTONE_FRAME_RE).unwrap())
+}
+
+fn image_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(IMAGE_RE).unwrap())
+}
+
+fn register_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(REGISTER_RE).unwrap())
+}
+
+fn address_token_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(ADDRESS_TOKEN_RE).unwrap())
+}
+
+/// A backtrace line of a crash report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameLine<'a> {
+    pub index: &'a str,
+    pub image: &'a str,
+    pub address: u64,
+    /// Offset of the frame's address from the image's load address.
+    pub offset: u64,
+    /// Where the decimal offset appears in the line.
+    pub offset_span: Range<usize>,
+    /// Set for frames the report already symbolicated. `offset` is then
+    /// relative to the symbol and `offset_span` covers the symbol, offset and
+    /// source location.
+    pub symbol: Option<&'a str>,
+}
+
+pub fn parse_frame_line(line: &str) -> Option<FrameLine<'_>> {
+    parse_frame_line_with(frame_re(), line)
+}
+
+fn parse_frame_line_with<'a>(frame_re: &Regex, line: &'a str) -> Option<FrameLine<'a>> {
+    let line = line.trim_end();
+    if let Some(cap) = frame_re.captures(line) {
+        let offset = cap.name("offset").unwrap();
+        return Some(FrameLine {
+            index: cap.name("i").map_or("", |i| i.as_str()),
+            image: cap.name("so").unwrap().as_str(),
+            address: u64::from_str_radix(&cap["mem_address"], 16).ok()?,
+            offset: offset.as_str().parse().ok()?,
+            offset_span: offset.range(),
+            symbol: None,
+        });
+    }
+    let cap = symbolicated_frame_re().captures(line)?;
+    Some(FrameLine {
+        index: cap.name("i").unwrap().as_str(),
+        image: cap.name("so").unwrap().as_str(),
+        address: u64::from_str_radix(&cap["mem_address"], 16).ok()?,
+        offset: cap["offset"].parse().ok()?,
+        offset_span: cap.name("symbolicated").unwrap().range(),
+        symbol: Some(cap.name("symbol").unwrap().as_str()),
+    })
+}
+
+/// A backtrace line of an Android tombstone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TombstoneFrame<'a> {
+    pub index: &'a str,
+    pub pc: u64,
+    pub path: &'a str,
+    /// File name of the library, as breakpad names the module.
+    pub image: &'a str,
+    pub build_id: Option<&'a str>,
+    /// The function name the tombstone already has, inside its parentheses.
+    pub function_span: Option<Range<usize>>,
+    pub path_end: usize,
+}
+
+impl<'a> TombstoneFrame<'a> {
+    // Identifies the library by BuildId where the frame has one.
+    pub fn module_id(&self) -> ModuleId<'a> {
+        let module = ModuleId::new(self.image);
+        match self.build_id {
+            Some(build_id) => module.with_build_id(build_id),
+            None => module,
+        }
+    }
+}
+
+pub fn parse_tombstone_frame_line(line: &str) -> Option<TombstoneFrame<'_>> {
+    let cap = tombstone_frame_re().captures(line)?;
+    let path = cap.name("path").unwrap();
+    let mut build_id = cap.name("build_id").map(|build_id| build_id.as_str());
+    let mut function = cap.name("function");
+    // Without a function name the BuildId lands in the function group.
+    if let Some(id) = function.and_then(|function| function.as_str().strip_prefix("BuildId: ")).filter(|_| build_id.is_none()) {
+        build_id = Some(id);
+        function = None;
+    }
+    Some(TombstoneFrame {
+        index: cap.name("i").unwrap().as_str(),
+        pc: u64::from_str_radix(&cap["pc"], 16).ok()?,
+        path: path.as_str(),
+        image: path.as_str().rsplit('/').next().unwrap_or(path.as_str()),
+        build_id,
+        function_span: function.map(|function| function.range()),
+        path_end: path.end(),
+    })
+}
+
+/// An entry of the report's Binary Images section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BinaryImage {
+    pub name: String,
+    pub file_name: String,
+    pub start: u64,
+    /// Inclusive, as printed in the report.
+    pub end: u64,
+    pub uuid: String,
+}
+
+impl BinaryImage {
+    pub fn matches(&self, module: &str) -> bool {
+        self.name == module || self.file_name == module
+    }
+
+    pub fn size(&self) -> u64 {
+        self.end.saturating_sub(self.start).saturating_add(1)
+    }
+
+    // Breakpad debug id: the uppercase uuid plus age 0.
+    pub fn debug_id(&self) -> String {
+        format!("{}0", self.uuid.replace('-', "").to_uppercase())
+    }
+}
+
+pub fn parse_binary_images(input: &str) -> Vec<BinaryImage> {
+    input
+        .lines()
+        .filter_map(|line| image_re().captures(line))
+        .map(|cap| {
+            let path = cap["path"].trim();
+            BinaryImage {
+                name: cap["name"].to_string(),
+                file_name: path.rsplit('/').next().unwrap_or(path).to_string(),
+                start: u64::from_str_radix(&cap["start"], 16).unwrap_or(0),
+                end: u64::from_str_radix(&cap["end"], 16).unwrap_or(0),
+                uuid: cap["uuid"].to_string(),
+            }
+        })
+        .collect()
+}
+
+pub fn find_image_by_address(images: &[BinaryImage], address: u64) -> Option<&BinaryImage> {
+    images.iter().find(|image| image.start <= address && address <= image.end)
+}
+
+// Older iOS reports print the Last Exception Backtrace as one flat list of
+// absolute addresses:
+//   (0x1a2b3c4d0 0x1a2b3c4e4 0x106d283e4 ...)
+pub fn parse_address_list(line: &str) -> Option<Vec<u64>> {
+    let line = line.trim();
+    let list = line.strip_prefix('(').and_then(|list| list.strip_suffix(')')).unwrap_or(line);
+    let addresses: Vec<u64> = list
+        .split_whitespace()
+        .map(|address| address.strip_prefix("0x").and_then(|hex| u64::from_str_radix(hex, 16).ok()))
+        .collect::<Option<_>>()?;
+    if addresses.is_empty() {
+        None
+    } else {
+        Some(addresses)
+    }
+}
+
+/// A `0x`-prefixed address in free text, or a `module+0x...` reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressToken<'a> {
+    /// Where the token, module included, appears in the line.
+    pub span: Range<usize>,
+    pub module: Option<&'a str>,
+    /// Relative to `module` if there is one.
+    pub address: u64,
+}
+
+pub fn find_address_tokens(line: &str) -> Vec<AddressToken<'_>> {
+    address_token_re()
+        .captures_iter(line)
+        .filter_map(|cap| {
+            Some(AddressToken {
+                span: cap.get(0).unwrap().range(),
+                module: cap.name("module").map(|module| module.as_str()),
+                address: u64::from_str_radix(&cap["address"], 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+// Spells out an address of a flat backtrace as a regular frame line, so it
+// can be symbolicated like the frames of a thread.
+pub fn format_frame_line(index: usize, images: &[BinaryImage], address: u64) -> String {
+    match find_image_by_address(images, address) {
+        Some(image) => format!("{:<4}{:<30}\t0x{:016x} 0x{:x} + {}", index, image.name, address, image.start, address - image.start),
+        None => format!("{:<4}{:<30}\t0x{:016x} 0x0 + {}", index, "???", address, address),
+    }
+}
+
+/// A line that opens or names a thread's backtrace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadHeader<'a> {
+    /// `Thread 0 name:  Dispatch queue: com.apple.main-thread`
+    Name(usize, &'a str),
+    /// `Thread 0:` or `Thread 0 Crashed:`
+    Start { index: usize, crashed: bool },
+}
+
+pub fn parse_thread_header(line: &str) -> Option<ThreadHeader<'_>> {
+    let rest = line.trim_end().strip_prefix("Thread ")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let index = rest[..digits].parse().ok()?;
+    match &rest[digits..] {
+        ":" => Some(ThreadHeader::Start { index, crashed: false }),
+        " Crashed:" => Some(ThreadHeader::Start { index, crashed: true }),
+        tail => tail.strip_prefix(" name:").map(|name| ThreadHeader::Name(index, name.trim())),
+    }
+}
+
+/// The thread index of a `Thread 0 crashed with ARM Thread State (64-bit):`
+/// line, which opens the register dump.
+pub fn parse_thread_state_header(line: &str) -> Option<usize> {
+    let rest = line.trim_end().strip_prefix("Thread ")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let state = rest[digits..].strip_prefix(" crashed with ")?;
+    if !state.contains("Thread State") {
+        return None;
+    }
+    rest[..digits].parse().ok()
+}
+
+/// `(name, value)` of the registers on a line of the register dump.
+pub fn parse_registers(line: &str) -> Vec<(&str, u64)> {
+    register_re()
+        .captures_iter(line)
+        .filter_map(|cap| Some((cap.name("name").unwrap().as_str(), u64::from_str_radix(&cap["value"], 16).ok()?)))
+        .collect()
+}
+
+/// The CPU architecture a report was written for: `Code Type: ARM-64 (Native)`
+/// of .ips/.crash text, `"cpuType" : "ARM-64"` of JSON ips or the
+/// `ABI: 'arm64'` line of tombstones. See [`addr2line_breakpad::normalize_arch`].
+pub fn parse_report_arch(input: &str) -> Option<&str> {
+    input.lines().find_map(|line| {
+        let line = line.trim();
+        if let Some(code_type) = line.strip_prefix("Code Type:") {
+            return Some(code_type.trim());
+        }
+        if let Some(abi) = line.strip_prefix("ABI:") {
+            return Some(abi.trim().trim_matches('\''));
+        }
+        let (_, cpu_type) = line.split_once("\"cpuType\"")?;
+        let cpu_type = cpu_type.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+        cpu_type.split('"').next()
+    })
+}
+
+// `Key: value` lines at the top of the report, up to the first backtrace.
+pub fn parse_header_fields(input: &str) -> Vec<(&str, &str)> {
+    input
+        .lines()
+        .take_while(|line| parse_thread_header(line).is_none() && !line.starts_with("Last Exception Backtrace:") && !line.starts_with("Binary Images:"))
+        .filter(|line| !line.starts_with(char::is_whitespace) && !line.starts_with('{'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// `2021-06-09 15:23:50.6600 +0800`, as in `Date/Time:` of ips reports and the
+// `timestamp` of their JSON header, to seconds since the epoch. Tombstones
+// leave out the space before the offset.
+pub fn parse_report_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (date, rest) = value.split_once(' ')?;
+    let offset_start = rest.find(['+', '-'])?;
+    let (time, offset) = (rest[..offset_start].trim(), &rest[offset_start..]);
+
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.split('.').next()?.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || offset.len() != 5 || !offset.is_ascii() {
+        return None;
+    }
+    let offset_minutes = offset[1..3].parse::<i64>().ok()? * 60 + offset[3..].parse::<i64>().ok()?;
+    let offset_seconds = if offset.starts_with('-') { -offset_minutes * 60 } else { offset_minutes * 60 };
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset_seconds)
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Text for a Markdown table cell: pipes would end the cell, newlines the row.
+pub fn escape_markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+// `--source-url` templates link frames to a code browser, e.g.
+// `https://git.example.com/unity/blob/main/{path}#L{line}`.
+pub fn expand_source_url(template: &str, path: &str, line: Option<i64>) -> String {
+    let path = path.replace('\\', "/");
+    let line = line.map_or(String::new(), |line| line.to_string());
+    template.replace("{path}", path.trim_start_matches('/')).replace("{line}", &line)
+}
+
+// Runs `work` on every item on up to `jobs` threads and hands the results to
+// `done` in the order of `items`, each as soon as those before it are done,
+// so output can be written while later items are still being worked on.
+pub fn for_each_ordered<T, R>(items: &[T], jobs: usize, work: impl Fn(usize, &T) -> R + Sync, mut done: impl FnMut(R))
+where
+    T: Sync,
+    R: Send,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        items.iter().enumerate().for_each(|(i, item)| done(work(i, item)));
+        return;
+    }
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                match items.get(i) {
+                    Some(item) => {
+                        if sender.send((i, work(i, item))).is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            });
+        }
+        drop(sender);
+        let mut pending = BTreeMap::new();
+        let mut expected = 0;
+        for (i, result) in receiver {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&expected) {
+                done(result);
+                expected += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame_line() {
+        let line = "3   UnityFramework                \t0x0000000107415a94 0x106904000 + 11606676";
+        let frame = parse_frame_line(line).unwrap();
+        assert_eq!(frame.index, "3");
+        assert_eq!(frame.image, "UnityFramework");
+        assert_eq!(frame.offset, 11606676);
+        assert_eq!(&line[frame.offset_span], "11606676");
+        assert_eq!(frame.symbol, None);
+
+        let frame = parse_frame_line("12  Google Chrome Framework  0x00000001045a2b3c 0 + 4368968508  ").unwrap();
+        assert_eq!(frame.image, "Google Chrome Framework");
+        assert_eq!(parse_frame_line("Thread 0 Crashed:"), None);
+        assert_eq!(parse_frame_line("0 a 0x1 0x0 + 99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_frame_regex_override() {
+        // A crash SDK export: image first, then the address and the offset
+        // as `base+offset`.
+        let re = compile_frame_regex(r"^(?P<so>\S+) at 0x(?P<mem_address>[0-9a-f]+) \(0x(?P<base>[0-9a-f]+)\+(?P<offset>\d+)\)$").unwrap();
+        let line = "UnityFramework at 0x107415a94 (0x106904000+11606676)";
+        let frame = parse_frame_line_with(&re, line).unwrap();
+        assert_eq!((frame.index, frame.image, frame.address, frame.offset), ("", "UnityFramework", 0x107415a94, 11606676));
+        assert_eq!(&line[frame.offset_span], "11606676");
+        assert_eq!(parse_frame_line_with(&re, "Thread 0 Crashed:"), None);
+
+        assert_eq!(compile_frame_regex(r"(?P<so>\S+) (?P<offset>\d+)").unwrap_err(), "missing named groups: mem_address, base");
+        assert!(compile_frame_regex(r"(?P<so>").is_err());
+    }
+
+    #[test]
+    fn test_parse_symbolicated_frame_line() {
+        let line = "1   UIKitCore                     \t0x00000001924f2ee8 -[UIApplication _run] + 1052 (UIApplication.m:3421)";
+        let frame = parse_frame_line(line).unwrap();
+        assert_eq!(frame.image, "UIKitCore");
+        assert_eq!(frame.address, 0x1924f2ee8);
+        assert_eq!(frame.symbol, Some("-[UIApplication _run]"));
+        assert_eq!(frame.offset, 1052);
+        assert_eq!(&line[frame.offset_span], "-[UIApplication _run] + 1052 (UIApplication.m:3421)");
+
+        let frame = parse_frame_line("0   libsystem_kernel.dylib        \t0x00000001bda6e30c mach_msg_trap + 8").unwrap();
+        assert_eq!((frame.symbol, frame.offset), (Some("mach_msg_trap"), 8));
+    }
+
+    #[test]
+    fn test_parse_thread_state() {
+        assert_eq!(parse_thread_state_header("Thread 0 crashed with ARM Thread State (64-bit):"), Some(0));
+        assert_eq!(parse_thread_state_header("Thread 12 crashed with X86 Thread State (64-bit):"), Some(12));
+        assert_eq!(parse_thread_state_header("Thread 0 Crashed:"), None);
+        assert_eq!(
+            parse_registers("   x28: 0x000000010a84b200   fp: 0x000000016db71340   lr: 0x000000018f732318"),
+            vec![("x28", 0x10a84b200), ("fp", 0x16db71340), ("lr", 0x18f732318)]
+        );
+        assert_eq!(parse_registers("   esr: 0x56000080  Address size fault"), vec![("esr", 0x56000080)]);
+        assert!(parse_registers("Binary Images:").is_empty());
+    }
+
+    #[test]
+    fn test_parse_report_arch() {
+        assert_eq!(parse_report_arch("Hardware Model:      iPhone12,1\nCode Type:           ARM-64 (Native)\n"), Some("ARM-64 (Native)"));
+        assert_eq!(parse_report_arch("{\"uptime\":1000,\"cpuType\" : \"ARM-64\",\"procRole\":\"Foreground\"}"), Some("ARM-64"));
+        assert_eq!(parse_report_arch("Revision: '0'\nABI: 'arm64'\nTimestamp: 2021-06-09"), Some("arm64"));
+        assert_eq!(parse_report_arch("Thread 0 Crashed:"), None);
+    }
+
+    #[test]
+    fn test_parse_thread_header() {
+        assert_eq!(parse_thread_header("Thread 0 Crashed:"), Some(ThreadHeader::Start { index: 0, crashed: true }));
+        assert_eq!(parse_thread_header("Thread 12:"), Some(ThreadHeader::Start { index: 12, crashed: false }));
+        assert_eq!(parse_thread_header("Thread 0 name:  Dispatch queue: com.apple.main-thread"), Some(ThreadHeader::Name(0, "Dispatch queue: com.apple.main-thread")));
+        assert_eq!(parse_thread_header("Thread 0 crashed with ARM Thread State (64-bit):"), None);
+
+        let fields = parse_header_fields("{\"app_name\":\"jxpocket\"}\nHardware Model:      iPhone11,8\nDate/Time:           2021-06-09 15:23:50.6600 +0800\n\nThread 0 Crashed:\nFoo: bar\n");
+        assert_eq!(fields, vec![("Hardware Model", "iPhone11,8"), ("Date/Time", "2021-06-09 15:23:50.6600 +0800")]);
+    }
+
+    #[test]
+    fn test_parse_tombstone_frame_line() {
+        let line = "      #00 pc 000000000004c8c4  /apex/com.android.runtime/lib64/bionic/libc.so (abort+164) (BuildId: 2b7a7a7c0e1a58b5)";
+        let frame = parse_tombstone_frame_line(line).unwrap();
+        assert_eq!((frame.index, frame.pc, frame.image), ("00", 0x4c8c4, "libc.so"));
+        assert_eq!(frame.build_id, Some("2b7a7a7c0e1a58b5"));
+        assert_eq!(frame.module_id(), ModuleId::new("libc.so").with_build_id("2b7a7a7c0e1a58b5"));
+        assert_eq!(&line[frame.function_span.unwrap()], "abort+164");
+
+        let line = "#01 pc 00000000001a2b3c  /data/app/com.foo/lib/arm64/libil2cpp.so (offset 0x1234000) (il2cpp::vm::Foo(int)+8)";
+        let frame = parse_tombstone_frame_line(line).unwrap();
+        assert_eq!(frame.build_id, None);
+        assert_eq!(&line[frame.function_span.unwrap()], "il2cpp::vm::Foo(int)+8");
+
+        let line = "#02 pc 0000000000001000  /data/app/com.foo/lib/arm64/libgame.so (BuildId: 0a0b)";
+        let frame = parse_tombstone_frame_line(line).unwrap();
+        assert_eq!((frame.build_id, frame.function_span), (Some("0a0b"), None));
+
+        let line = "#02 pc 0000000000001000  /data/app/com.foo/lib/arm64/libgame.so";
+        let frame = parse_tombstone_frame_line(line).unwrap();
+        assert_eq!((frame.image, frame.function_span, frame.path_end), ("libgame.so", None, line.len()));
+        assert_eq!(parse_tombstone_frame_line("backtrace:"), None);
+    }
+
+    #[test]
+    fn test_parse_binary_images() {
+        let images = parse_binary_images(
+            "Binary Images:\n\
+             0x106904000 - 0x108eabfff UnityFramework arm64  <ee27598b6a283e389075d1014679c8b0> /var/Frameworks/UnityFramework.framework/UnityFramework\n\
+             0x1 - 0x0 +broken arm64 <ee27598b-6a28-3e38-9075-d1014679c8b0> /x/libbroken.dylib\n",
+        );
+        assert_eq!(images.len(), 2);
+        assert!(images[0].matches("UnityFramework"));
+        assert_eq!(images[0].size(), 0x25a8000);
+        assert_eq!(images[0].debug_id(), "EE27598B6A283E389075D1014679C8B00");
+        assert!(images[1].matches("libbroken.dylib"));
+        assert_eq!(images[1].size(), 1);
+        assert_eq!(images[1].debug_id(), images[0].debug_id());
+    }
+
+    #[test]
+    fn test_last_exception_backtrace() {
+        assert_eq!(parse_address_list("(0x1a2b 0x106d283e4)"), Some(vec![0x1a2b, 0x106d283e4]));
+        assert_eq!(parse_address_list("0x10 0x20"), Some(vec![0x10, 0x20]));
+        assert_eq!(parse_address_list("()"), None);
+        assert_eq!(parse_address_list("Thread 0 Crashed:"), None);
+
+        let images = parse_binary_images("0x106904000 - 0x108eabfff UnityFramework arm64  <ee27598b6a283e389075d1014679c8b0> /x/UnityFramework\n");
+        let line = format_frame_line(4, &images, 0x106d283e4);
+        let frame = parse_frame_line(&line).unwrap();
+        assert_eq!((frame.index, frame.image, frame.offset), ("4", "UnityFramework", 4342756));
+        assert_eq!(parse_frame_line(&format_frame_line(5, &images, 0x1)).unwrap().image, "???");
+    }
+
+    #[test]
+    fn test_find_address_tokens() {
+        let line = "[Engine] assert at 0x7a1c2b04 (libil2cpp.so+0x1c4a2b0), flags=0xff x0x12";
+        let tokens = find_address_tokens(line);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[0].module, tokens[0].address, &line[tokens[0].span.clone()]), (None, 0x7a1c2b04, "0x7a1c2b04"));
+        assert_eq!((tokens[1].module, tokens[1].address, &line[tokens[1].span.clone()]), (Some("libil2cpp.so"), 0x1c4a2b0, "libil2cpp.so+0x1c4a2b0"));
+        assert_eq!(tokens[2].address, 0xff);
+        assert!(find_address_tokens("0x12345678901234567 0xg").is_empty());
+    }
+
+    #[test]
+    fn test_html_helpers() {
+        assert_eq!(escape_html("std::vector<int>::at(\"a\" & 'b')"), "std::vector&lt;int&gt;::at(&quot;a&quot; &amp; &#39;b&#39;)");
+        assert_eq!(
+            expand_source_url("https://git.example.com/unity/blob/main/{path}#L{line}", "/Runtime/Misc/Player.cpp", Some(120)),
+            "https://git.example.com/unity/blob/main/Runtime/Misc/Player.cpp#L120"
+        );
+        assert_eq!(expand_source_url("vscode://file/{path}:{line}", "C:\\src\\a.cpp", None), "vscode://file/C:/src/a.cpp:");
+        assert_eq!(escape_markdown_cell("operator|(a, b)\nfoo"), "operator\\|(a, b) foo");
+    }
+
+    #[test]
+    fn test_for_each_ordered() {
+        let items: Vec<u64> = (0..100).collect();
+        for jobs in [0, 1, 4, 200] {
+            let mut results = vec![];
+            // Later items finish first.
+            for_each_ordered(&items, jobs, |i, item| {
+                thread::sleep(std::time::Duration::from_micros(100 - *item));
+                (i, item * 2)
+            }, |result| results.push(result));
+            assert_eq!(results, items.iter().map(|item| (*item as usize, item * 2)).collect::<Vec<_>>());
+        }
+        for_each_ordered(&[] as &[u64], 4, |_, item| *item, |_| unreachable!());
+    }
+
+    #[test]
+    fn test_parse_report_timestamp() {
+        assert_eq!(parse_report_timestamp("1970-01-01 00:00:00 +0000"), Some(0));
+        assert_eq!(parse_report_timestamp("2021-06-09 15:23:50.6600 +0800"), Some(1623223430));
+        assert_eq!(parse_report_timestamp("2021-06-09 07:23:50.00 +0000"), Some(1623223430));
+        assert_eq!(parse_report_timestamp("2021-06-08 23:23:50.123456789-0800"), Some(1623223430));
+        assert_eq!(parse_report_timestamp("2021-06-09"), None);
+        assert_eq!(parse_report_timestamp("2021-13-09 15:23:50 +0800"), None);
+        assert_eq!(parse_report_timestamp("2021-06-09 15:23:50 +0é0"), None);
+    }
+}
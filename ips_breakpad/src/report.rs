@@ -0,0 +1,276 @@
+use addr2line_breakpad::{ModuleId, Symbol, Symbolicator};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{format_frame_line, parse_address_list, parse_binary_images, parse_frame_line, parse_header_fields, parse_thread_header, BinaryImage, FrameLine, ThreadHeader};
+
+/// The exception fields of a report's header.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ExceptionSummary {
+    pub exception_type: Option<String>,
+    pub exception_codes: Option<String>,
+    pub termination_reason: Option<String>,
+    pub crashed_thread: Option<String>,
+    pub fault_address: Option<String>,
+}
+
+impl ExceptionSummary {
+    pub fn is_empty(&self) -> bool {
+        self.exception_type.is_none()
+            && self.exception_codes.is_none()
+            && self.termination_reason.is_none()
+            && self.crashed_thread.is_none()
+            && self.fault_address.is_none()
+    }
+}
+
+fn header_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.strip_prefix(key)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .map(|value| value.trim())
+}
+
+fn fault_address(value: &str) -> Option<String> {
+    value
+        .split(" at ")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .filter(|address| address.starts_with("0x"))
+        .map(|address| address.to_string())
+}
+
+pub fn parse_exception_summary(input: &str) -> ExceptionSummary {
+    let mut summary = ExceptionSummary::default();
+    for line in input.lines() {
+        if let Some(value) = header_value(line, "Exception Type") {
+            summary.exception_type = Some(value.to_string());
+        } else if let Some(value) = header_value(line, "Exception Codes") {
+            summary.exception_codes = Some(value.to_string());
+            summary.fault_address = summary.fault_address.or_else(|| fault_address(value));
+        } else if let Some(value) = header_value(line, "Exception Subtype") {
+            // "KERN_INVALID_ADDRESS at 0x0000000000000010"
+            summary.fault_address = fault_address(value).or(summary.fault_address);
+        } else if let Some(value) = header_value(line, "Termination Reason") {
+            summary.termination_reason = Some(value.to_string());
+        } else if let Some(value) = header_value(line, "Triggered by Thread")
+            .or_else(|| header_value(line, "Crashed Thread"))
+        {
+            // macOS: "Crashed Thread:        0  Dispatch queue: com.apple.main-thread"
+            let index = value.split_whitespace().next().unwrap_or(value);
+            summary.crashed_thread = Some(index.to_string());
+        } else if let Some(ThreadHeader::Start { index, crashed: true }) = parse_thread_header(line) {
+            // the header fields are absent in some truncated reports
+            if summary.crashed_thread.is_none() {
+                summary.crashed_thread = Some(index.to_string());
+            }
+        }
+    }
+    summary
+}
+
+/// A backtrace frame of an [`IpsReport`], not tied to the report's text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Frame {
+    pub index: usize,
+    pub image: String,
+    pub address: u64,
+    /// As in [`FrameLine::offset`].
+    pub offset: u64,
+    pub symbol: Option<String>,
+}
+
+impl From<&FrameLine<'_>> for Frame {
+    fn from(frame: &FrameLine) -> Frame {
+        Frame {
+            index: frame.index.parse().unwrap_or(0),
+            image: frame.image.to_string(),
+            address: frame.address,
+            offset: frame.offset,
+            symbol: frame.symbol.map(|symbol| symbol.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Thread {
+    pub index: usize,
+    pub name: Option<String>,
+    pub crashed: bool,
+    pub frames: Vec<Frame>,
+}
+
+/// The parsed structure of an ips crash report, for tools that symbolicate
+/// or summarize it rather than rewrite its lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IpsReport {
+    /// The `Key: Value` lines of the header, in order.
+    pub metadata: Vec<(String, String)>,
+    pub exception: ExceptionSummary,
+    /// Frames outside of any thread, as the address list newer reports
+    /// print, resolved against `images`.
+    pub last_exception_backtrace: Vec<Frame>,
+    pub threads: Vec<Thread>,
+    pub images: Vec<BinaryImage>,
+}
+
+impl IpsReport {
+    pub fn parse(input: &str) -> IpsReport {
+        let images = parse_binary_images(input);
+        let mut report = IpsReport {
+            metadata: parse_header_fields(input).into_iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            exception: parse_exception_summary(input),
+            last_exception_backtrace: vec![],
+            threads: vec![],
+            images: vec![],
+        };
+        let mut names = BTreeMap::new();
+        let mut in_thread = false;
+        let mut in_last_exception = false;
+        for line in input.lines() {
+            match parse_thread_header(line) {
+                Some(ThreadHeader::Name(index, name)) => {
+                    names.insert(index, name.to_string());
+                    continue;
+                }
+                Some(ThreadHeader::Start { index, crashed }) => {
+                    in_thread = true;
+                    in_last_exception = false;
+                    report.threads.push(Thread {
+                        index,
+                        name: names.remove(&index),
+                        crashed,
+                        frames: vec![],
+                    });
+                    continue;
+                }
+                None => {}
+            }
+            if line.starts_with("Last Exception Backtrace:") {
+                in_thread = false;
+                in_last_exception = true;
+                continue;
+            }
+            if line.starts_with("Binary Images:") {
+                break;
+            }
+            let frames = match report.threads.last_mut() {
+                Some(thread) if in_thread => &mut thread.frames,
+                _ => &mut report.last_exception_backtrace,
+            };
+            if let Some(addresses) = parse_address_list(line).filter(|_| in_last_exception) {
+                for (i, address) in addresses.into_iter().enumerate() {
+                    frames.extend(parse_frame_line(&format_frame_line(i, &images, address)).as_ref().map(Frame::from));
+                }
+            } else if let Some(frame) = parse_frame_line(line) {
+                frames.push(Frame::from(&frame));
+            }
+        }
+        report.images = images;
+        report
+    }
+
+    /// Resolves `frame` with any symbols backend, identifying its module by
+    /// image name and the debug id of its Binary Images entry.
+    pub fn lookup_frame<'a>(&self, frame: &Frame, symbolicator: &'a dyn Symbolicator) -> Option<Symbol<'a>> {
+        let image = self.images.iter().find(|image| image.matches(&frame.image));
+        let debug_id = image.map(BinaryImage::debug_id);
+        let mut module = ModuleId::new(&frame.image);
+        if let Some(debug_id) = &debug_id {
+            module = module.with_debug_id(debug_id);
+        }
+        // The offset of a symbolicated frame is from its symbol.
+        let rel_addr = match frame.symbol {
+            Some(_) => frame.address.checked_sub(image?.start)?,
+            None => frame.offset,
+        };
+        symbolicator.lookup(&module, rel_addr)
+    }
+
+    pub fn crashed_thread(&self) -> Option<&Thread> {
+        self.threads.iter().find(|thread| thread.crashed)
+    }
+
+    /// Every frame with its thread's index, the Last Exception Backtrace's
+    /// first.
+    pub fn frames(&self) -> impl Iterator<Item = (Option<usize>, &Frame)> {
+        let last_exception = self.last_exception_backtrace.iter().map(|frame| (None, frame));
+        last_exception.chain(self.threads.iter().flat_map(|thread| thread.frames.iter().map(move |frame| (Some(thread.index), frame))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report() {
+        let input = "\
+Incident Identifier: 1234
+Exception Type:  EXC_BAD_ACCESS (SIGSEGV)
+Exception Subtype: KERN_INVALID_ADDRESS at 0x0000000000000010
+Triggered by Thread:  1
+
+Last Exception Backtrace:
+(0x106904010 0x106904040)
+
+Thread 0 name:  Dispatch queue: com.apple.main-thread
+Thread 0:
+0   libsystem_kernel.dylib        \t0x00000001bc0d8dd0 0x1bc0b2000 + 159184
+
+Thread 1 Crashed:
+0   UnityFramework                \t0x0000000107415a94 0x106904000 + 11606676
+1   UnityFramework                \t0x0000000107415a98 Foo::bar() + 8
+
+Binary Images:
+0x106904000 - 0x108dbffff UnityFramework arm64  <0c6b39e0a6fa3bfcbdad0d9b1d7a5b1f> /private/var/containers/UnityFramework
+";
+        let report = IpsReport::parse(input);
+        assert_eq!(report.metadata[0], (String::from("Incident Identifier"), String::from("1234")));
+        assert_eq!(report.exception.crashed_thread.as_deref(), Some("1"));
+        assert_eq!(report.exception.fault_address.as_deref(), Some("0x0000000000000010"));
+        assert_eq!(report.images.len(), 1);
+
+        let offsets: Vec<u64> = report.last_exception_backtrace.iter().map(|frame| frame.offset).collect();
+        assert_eq!(offsets, vec![0x10, 0x40]);
+        assert_eq!(report.last_exception_backtrace[1].index, 1);
+        assert_eq!(report.threads.len(), 2);
+        assert_eq!(report.threads[0].name.as_deref(), Some("Dispatch queue: com.apple.main-thread"));
+
+        let crashed = report.crashed_thread().unwrap();
+        assert_eq!(crashed.index, 1);
+        assert_eq!(crashed.frames[0].offset, 11606676);
+        assert_eq!(crashed.frames[1].symbol.as_deref(), Some("Foo::bar()"));
+
+        let threads: Vec<Option<usize>> = report.frames().map(|(thread, _)| thread).collect();
+        assert_eq!(threads, vec![None, None, Some(0), Some(1), Some(1)]);
+
+        let mut builder = addr2line_breakpad::SymbolFileBuilder::new();
+        builder
+            .set_module("mac", "arm64", "0C6B39E0A6FA3BFCBDAD0D9B1D7A5B1F0", "UnityFramework")
+            .add_function(0x10, 0x10, 0, "first")
+            .add_function(0xb11a90, 0x10, 0, "Foo::bar()");
+        let symbol_file = builder.build();
+        let functions: Vec<Option<String>> = report.frames().map(|(_, frame)| report.lookup_frame(frame, &symbol_file).map(|symbol| symbol.function_name.into_owned())).collect();
+        assert_eq!(functions, vec![Some(String::from("first")), None, None, Some(String::from("Foo::bar()")), Some(String::from("Foo::bar()"))]);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["threads"][1]["frames"][1]["symbol"], "Foo::bar()");
+        assert_eq!(json["images"][0]["name"], "UnityFramework");
+    }
+
+    #[test]
+    fn test_parse_exception_summary_crashed_thread() {
+        assert_eq!(parse_exception_summary("Thread 3 Crashed:\n").crashed_thread.as_deref(), Some("3"));
+        assert_eq!(parse_exception_summary("Crashed Thread: 1\nThread 3 Crashed:\n").crashed_thread.as_deref(), Some("1"));
+        // No index, as in a damaged report.
+        assert_eq!(parse_exception_summary("Thread Crashed:\n").crashed_thread, None);
+    }
+
+    #[test]
+    fn test_exception_summary_fault_address_only() {
+        let summary = parse_exception_summary("Exception Subtype: KERN_INVALID_ADDRESS at 0x0000000000000010\n");
+        assert_eq!(summary.fault_address.as_deref(), Some("0x0000000000000010"));
+        assert!(!summary.is_empty());
+        assert!(parse_exception_summary("Thread 0:\n").is_empty());
+    }
+}